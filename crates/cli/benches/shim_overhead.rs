@@ -0,0 +1,96 @@
+// Measures how much latency the `node` shim adds on top of exec'ing the real binary.
+//
+// cargo bench -p cli --bench shim_overhead
+//
+// A fake node (`exit 0` shell script) is installed as the default version under a
+// temporary SNM_BASE_DIR, so the numbers only contain snm's own resolution work.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const ITERATIONS: usize = 200;
+const NODE_VERSION: &str = "20.11.1";
+
+#[cfg(unix)]
+fn main() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let base_dir = env::temp_dir().join(format!("snm-bench-{}", std::process::id()));
+    let node_bin_dir = base_dir.join("node_bin");
+    let bin_dir = node_bin_dir.join(NODE_VERSION).join("bin");
+    fs::create_dir_all(&bin_dir).expect("create bench bin dir error");
+
+    let fake_node = bin_dir.join("node");
+    fs::write(&fake_node, "#!/bin/sh\nexit 0\n").expect("write fake node error");
+    fs::set_permissions(&fake_node, fs::Permissions::from_mode(0o755))
+        .expect("chmod fake node error");
+
+    symlink(
+        node_bin_dir.join(NODE_VERSION),
+        node_bin_dir.join(format!("{}-default", NODE_VERSION)),
+    )
+    .expect("create default symlink error");
+
+    let shim = PathBuf::from(env!("CARGO_BIN_EXE_node"));
+
+    let baseline = measure(&fake_node, &base_dir);
+    let shimmed = measure(&shim, &base_dir);
+
+    report("direct exec", &baseline);
+    report("node shim", &shimmed);
+    println!(
+        "{:<12} median {:>8.2?}",
+        "overhead",
+        median(&shimmed).saturating_sub(median(&baseline))
+    );
+
+    fs::remove_dir_all(&base_dir).ok();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("shim_overhead bench only runs on unix");
+}
+
+fn measure(program: &Path, base_dir: &Path) -> Vec<Duration> {
+    let mut samples = Vec::with_capacity(ITERATIONS);
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let status = Command::new(program)
+            .arg("--version")
+            .env("SNM_BASE_DIR", base_dir)
+            .env("SNM_STRICT", "false")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .expect("spawn error");
+        samples.push(start.elapsed());
+        assert!(
+            status.success(),
+            "{} exited with {}",
+            program.display(),
+            status
+        );
+    }
+    samples.sort();
+    samples
+}
+
+fn median(samples: &[Duration]) -> Duration {
+    samples[samples.len() / 2]
+}
+
+fn report(name: &str, samples: &[Duration]) {
+    let total: Duration = samples.iter().sum();
+    println!(
+        "{:<12} median {:>8.2?}  mean {:>8.2?}  p95 {:>8.2?}",
+        name,
+        median(samples),
+        total / samples.len() as u32,
+        samples[samples.len() * 95 / 100],
+    );
+}
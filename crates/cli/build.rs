@@ -0,0 +1,7 @@
+fn main() {
+    // `snm self update` picks the release asset built for the same target
+    println!(
+        "cargo:rustc-env=SNM_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+}
@@ -0,0 +1,484 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    env::current_dir,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, ValueEnum};
+use colored::*;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use snm_core::{
+    config::SnmConfig,
+    lockfile::{Lockfile, PackageId},
+    model::{output::OutputFormat, SnmError},
+    print_warning, println_error, println_success,
+    utils::{download::DownloadBuilder, health::check_multi_lock_file, tarball::read_zip_entries},
+};
+
+#[derive(Parser, Debug)]
+pub struct AuditCommandArgs {
+    #[arg(
+        long,
+        help = "OSV advisories, a JSON array of OSV records or a zip of them. Defaults to the database synced with --sync"
+    )]
+    pub db: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Download the advisory database from SNM_AUDIT_DB_URL first"
+    )]
+    pub sync: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "low",
+        help = "Exit with 3 only for advisories of this severity or higher"
+    )]
+    pub audit_level: Severity,
+
+    #[arg(long, help = "Skip packages only reachable through devDependencies")]
+    pub omit_dev: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// GitHub advisories put `LOW` / `MODERATE` / `HIGH` / `CRITICAL` into
+    /// `database_specific.severity`, anything else counts as low.
+    fn from_osv(value: Option<&str>) -> Self {
+        match value.map(str::to_ascii_uppercase).as_deref() {
+            Some("CRITICAL") => Severity::Critical,
+            Some("HIGH") => Severity::High,
+            Some("MODERATE") | Some("MEDIUM") => Severity::Moderate,
+            _ => Severity::Low,
+        }
+    }
+
+    fn colored(&self) -> ColoredString {
+        let label = format!("{:<8}", self.to_possible_value().unwrap().get_name());
+        match self {
+            Severity::Critical => label.bright_red().bold(),
+            Severity::High => label.bright_red(),
+            Severity::Moderate => label.bright_yellow(),
+            Severity::Low => label.bright_black(),
+        }
+    }
+}
+
+// OSV 记录只保留用得到的字段，同步后的缓存也是这个格式
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvRecord {
+    id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    database_specific: Option<OsvDatabaseSpecific>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvAffected {
+    package: OsvPackage,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ranges: Vec<OsvRange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    introduced: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fixed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_affected: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OsvDatabaseSpecific {
+    severity: Option<String>,
+}
+
+/// One advisory matching one locked package, `--output json` prints these.
+#[derive(Debug, Serialize)]
+struct Finding {
+    package: String,
+    version: String,
+    advisory: String,
+    aliases: Vec<String>,
+    severity: Severity,
+    summary: String,
+    /// Lowest fixed version above the locked one.
+    fixed: Option<String>,
+    /// Dependency chains from the importers, at most three.
+    paths: Vec<Vec<String>>,
+}
+
+pub async fn audit_impl(args: AuditCommandArgs) -> Result<(), SnmError> {
+    let snm_config = SnmConfig::new();
+
+    if args.sync {
+        sync_db(&snm_config).await?;
+    }
+
+    let db_path_buf = args
+        .db
+        .clone()
+        .unwrap_or_else(|| snm_config.get_audit_db_file_path_buf());
+    if !db_path_buf.is_file() {
+        return Err(SnmError::Error(format!(
+            "Advisory database {} not found, run snm audit --sync or pass --db",
+            db_path_buf.display()
+        )));
+    }
+    let record_vec = read_db(&db_path_buf)?;
+
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let lock_file = check_multi_lock_file()?
+        .into_iter()
+        .next()
+        .ok_or(SnmError::Error(format!(
+            "No lock file found in {}, install the dependencies first",
+            dir.display()
+        )))?;
+    let mut lockfile = Lockfile::read(&dir.join(&lock_file))?;
+    lockfile.fill_importer_manifests(&dir);
+
+    let mut finding_vec = find_vulnerabilities(&lockfile, &record_vec, args.omit_dev);
+    finding_vec.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.package.cmp(&b.package))
+            .then_with(|| a.advisory.cmp(&b.advisory))
+    });
+
+    print_findings(
+        snm_config.get_output_format(),
+        &finding_vec,
+        lockfile.packages.len(),
+    );
+
+    let count = finding_vec
+        .iter()
+        .filter(|finding| finding.severity >= args.audit_level)
+        .count();
+    if count > 0 {
+        return Err(SnmError::VulnerabilitiesFound { count });
+    }
+    Ok(())
+}
+
+async fn sync_db(snm_config: &SnmConfig) -> Result<(), SnmError> {
+    let url = snm_config.get_audit_db_url();
+    let download_dir_path_buf = snm_config.get_download_dir_path_buf();
+    fs::create_dir_all(&download_dir_path_buf)
+        .map_err(SnmError::io("create dir", &download_dir_path_buf))?;
+    let download_path_buf = download_dir_path_buf.join("advisory_db.download");
+
+    // 整个 OSV 导出有几百 MB，不能套用默认的请求超时
+    DownloadBuilder::new()
+        .retries(2)
+        .timeout(None)
+        .download(&url, &download_path_buf)
+        .await?;
+    let record_vec = read_db(&download_path_buf);
+    fs::remove_file(&download_path_buf).map_err(SnmError::io("remove", &download_path_buf))?;
+    let record_vec = record_vec?;
+
+    let db_path_buf = snm_config.get_audit_db_file_path_buf();
+    let json = serde_json::to_string(&record_vec).map_err(SnmError::json(&db_path_buf))?;
+    fs::write(&db_path_buf, json).map_err(SnmError::io("write", &db_path_buf))?;
+
+    println_success!(
+        "Synced {} npm advisories {}",
+        record_vec.len(),
+        format!("from {}", url).bright_black()
+    );
+    Ok(())
+}
+
+/// npm advisories of a JSON array or a zip of single OSV records.
+fn read_db(path: &Path) -> Result<Vec<OsvRecord>, SnmError> {
+    let mut record_vec: Vec<OsvRecord> = vec![];
+
+    // 只看开头的魔数，zip 交给 read_zip_entries 去读
+    let mut magic = vec![];
+    File::open(path)
+        .and_then(|file| file.take(2).read_to_end(&mut magic))
+        .map_err(SnmError::io("read", path))?;
+    if magic == b"PK" {
+        read_zip_entries(path, |name, content| {
+            if name.ends_with(".json") {
+                let record = serde_json::from_slice::<OsvRecord>(content)
+                    .map_err(SnmError::json(format!("{}/{}", path.display(), name)))?;
+                record_vec.push(record);
+            }
+            Ok(())
+        })?;
+    } else {
+        let content = fs::read(path).map_err(SnmError::io("read", path))?;
+        record_vec = serde_json::from_slice(&content).map_err(SnmError::json(path))?;
+    }
+
+    for record in record_vec.iter_mut() {
+        record
+            .affected
+            .retain(|affected| affected.package.ecosystem == "npm");
+    }
+    record_vec.retain(|record| !record.affected.is_empty());
+    Ok(record_vec)
+}
+
+fn find_vulnerabilities(
+    lockfile: &Lockfile,
+    record_vec: &[OsvRecord],
+    omit_dev: bool,
+) -> Vec<Finding> {
+    let mut affected_map: HashMap<&str, Vec<(&OsvRecord, &OsvAffected)>> = HashMap::new();
+    for record in record_vec {
+        for affected in &record.affected {
+            affected_map
+                .entry(affected.package.name.as_str())
+                .or_default()
+                .push((record, affected));
+        }
+    }
+
+    let dev_only = if omit_dev {
+        lockfile.dev_only()
+    } else {
+        Default::default()
+    };
+
+    let mut finding_vec = vec![];
+    for id in lockfile.packages.keys() {
+        if dev_only.contains(id) {
+            continue;
+        }
+        let Some(affected_vec) = affected_map.get(id.name.as_str()) else {
+            continue;
+        };
+        // link: / file: 之类的非 semver 版本没法比较
+        let Ok(version) = Version::parse(&id.version) else {
+            continue;
+        };
+
+        for (record, affected) in affected_vec {
+            if !is_affected(affected, &id.version, &version) {
+                continue;
+            }
+            finding_vec.push(Finding {
+                package: id.name.clone(),
+                version: id.version.clone(),
+                advisory: record.id.clone(),
+                aliases: record.aliases.clone(),
+                severity: Severity::from_osv(
+                    record
+                        .database_specific
+                        .as_ref()
+                        .and_then(|database_specific| database_specific.severity.as_deref()),
+                ),
+                summary: record.summary.clone(),
+                fixed: fixed_version(affected, &version),
+                paths: lockfile.paths_to(&PackageId::new(&id.name, &id.version), 3),
+            });
+        }
+    }
+    finding_vec
+}
+
+fn is_affected(affected: &OsvAffected, raw: &str, version: &Version) -> bool {
+    affected.versions.iter().any(|listed| listed == raw)
+        || affected
+            .ranges
+            .iter()
+            .filter(|range| range.kind == "SEMVER" || range.kind == "ECOSYSTEM")
+            .any(|range| range_affects(range, version))
+}
+
+/// OSV range evaluation: walking the events sorted by version, `introduced` at or
+/// below the version turns it affected, a `fixed` at or below / `last_affected`
+/// below turns it back.
+fn range_affects(range: &OsvRange, version: &Version) -> bool {
+    let mut event_vec: Vec<(Version, &OsvEvent)> = range
+        .events
+        .iter()
+        .filter_map(|event| {
+            let raw = event
+                .introduced
+                .as_deref()
+                .or(event.fixed.as_deref())
+                .or(event.last_affected.as_deref())?;
+            let event_version = match raw {
+                "0" => Version::parse("0.0.0-0").ok()?,
+                _ => Version::parse(raw).ok()?,
+            };
+            Some((event_version, event))
+        })
+        .collect();
+    event_vec.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut affected = false;
+    for (event_version, event) in event_vec {
+        let order = version.cmp(&event_version);
+        if event.introduced.is_some() && order != Ordering::Less {
+            affected = true;
+        } else if (event.fixed.is_some() && order != Ordering::Less)
+            || (event.last_affected.is_some() && order == Ordering::Greater)
+        {
+            affected = false;
+        }
+    }
+    affected
+}
+
+fn fixed_version(affected: &OsvAffected, version: &Version) -> Option<String> {
+    affected
+        .ranges
+        .iter()
+        .flat_map(|range| &range.events)
+        .filter_map(|event| Version::parse(event.fixed.as_deref()?).ok())
+        .filter(|fixed| fixed > version)
+        .min()
+        .map(|fixed| fixed.to_string())
+}
+
+fn print_findings(format: OutputFormat, finding_vec: &[Finding], package_count: usize) {
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(finding_vec) {
+            Ok(json) => println!("{}", json),
+            Err(error) => println_error!("serialize output error {}", error),
+        },
+        OutputFormat::Plain => {
+            for finding in finding_vec {
+                println!(
+                    "{}\t{}\t{}@{}",
+                    finding.severity.to_possible_value().unwrap().get_name(),
+                    finding.advisory,
+                    finding.package,
+                    finding.version
+                );
+            }
+        }
+        OutputFormat::Table => {
+            for finding in finding_vec {
+                println!(
+                    "{} {} {} {}",
+                    finding.severity.colored(),
+                    format!("{}@{}", finding.package, finding.version).bright_white(),
+                    finding.advisory.bright_black(),
+                    finding.summary
+                );
+                match &finding.fixed {
+                    Some(fixed) => println!("         fixed in {}", fixed.bright_green()),
+                    None => println!("         {}", "no fix available".bright_black()),
+                }
+                for path in &finding.paths {
+                    println!("         {}", path.join(" > ").bright_black());
+                }
+            }
+
+            if finding_vec.is_empty() {
+                println_success!("No known vulnerabilities in {} packages", package_count);
+            } else {
+                let mut count_vec: Vec<String> = vec![];
+                for severity in [
+                    Severity::Critical,
+                    Severity::High,
+                    Severity::Moderate,
+                    Severity::Low,
+                ] {
+                    let count = finding_vec
+                        .iter()
+                        .filter(|finding| finding.severity == severity)
+                        .count();
+                    if count > 0 {
+                        count_vec.push(format!(
+                            "{} {}",
+                            count,
+                            severity.to_possible_value().unwrap().get_name()
+                        ));
+                    }
+                }
+                print_warning!(
+                    "Found {} vulnerabilities ( {} ) in {} packages\n",
+                    finding_vec.len(),
+                    count_vec.join(", "),
+                    package_count
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(event_vec: &[(&str, &str)]) -> OsvRange {
+        OsvRange {
+            kind: "SEMVER".to_string(),
+            events: event_vec
+                .iter()
+                .map(|(kind, version)| OsvEvent {
+                    introduced: (*kind == "introduced").then(|| version.to_string()),
+                    fixed: (*kind == "fixed").then(|| version.to_string()),
+                    last_affected: (*kind == "last_affected").then(|| version.to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn evaluate_osv_ranges() {
+        let fixed = range(&[("introduced", "0"), ("fixed", "2.0.0")]);
+        let two_lines = range(&[
+            ("introduced", "1.0.0"),
+            ("fixed", "1.2.3"),
+            ("introduced", "2.0.0"),
+            ("last_affected", "2.1.0"),
+        ]);
+        let affects = |range: &OsvRange, version: &str| {
+            range_affects(range, &Version::parse(version).unwrap())
+        };
+
+        assert!(affects(&fixed, "1.9.9"));
+        assert!(affects(&fixed, "2.0.0-beta.1"));
+        assert!(!affects(&fixed, "2.0.0"));
+
+        assert!(!affects(&two_lines, "0.9.0"));
+        assert!(affects(&two_lines, "1.2.2"));
+        assert!(!affects(&two_lines, "1.5.0"));
+        assert!(affects(&two_lines, "2.1.0"));
+        assert!(!affects(&two_lines, "2.1.1"));
+    }
+}
@@ -1,112 +1,489 @@
-use std::{env::current_dir, fs};
+use std::{
+    env::current_dir,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
 
+use clap::Parser;
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Select};
-use regex::Regex;
+use dialoguer::{theme::ColorfulTheme, Confirm, Select};
 use semver::{Prerelease, Version};
-use snm_core::model::{PackageJson, SnmError};
-
-pub fn bump_impl() -> Result<(), SnmError> {
-    let package_json = PackageJson::from_here()?;
-    let current_version =
-        Version::parse(package_json.version.unwrap_or("0.0.0".to_string()).as_str())
-            .expect("parse version error");
-    let prerelease_number = current_version.pre.parse::<u8>().unwrap_or(0) + 1;
-
-    let major = current_version.major;
-    let minor = current_version.minor;
-    let patch = current_version.patch;
-
-    let zero_pre_release = Prerelease::new("0").expect("create prerelease error 0");
-
-    let versions_and_strings = vec![
-        create_version_and_string("major", major + 1, 0, 0, None)?,
-        create_version_and_string("minor", major, minor + 1, 0, None)?,
-        create_version_and_string("patch", major, minor, patch + 1, None)?,
-        create_version_and_string("premajor", major + 1, 0, 0, Some(zero_pre_release.clone()))?,
-        create_version_and_string(
-            "preminor",
-            major,
-            minor + 1,
-            0,
-            Some(zero_pre_release.clone()),
-        )?,
-        create_version_and_string("prepatch", major, minor, patch + 1, Some(zero_pre_release))?,
-        create_version_and_string(
-            "prerelease",
-            major,
-            minor,
-            patch,
-            Some(
-                Prerelease::new(prerelease_number.to_string().as_str())
-                    .expect(format!("create prerelease error {}", prerelease_number).as_str()),
-            ),
-        )?,
-    ];
-
-    let selections: Vec<String> = versions_and_strings
+use snm_core::{
+    config::SnmConfig,
+    model::{PackageJsonDocument, SnmError},
+    println_success,
+    utils::git::{
+        get_commit_vec, get_dirty_file_vec, get_latest_version_tag, git, is_repository, tag_exists,
+    },
+};
+
+use crate::{
+    bump_workspace::bump_workspace_impl,
+    changelog::{prepend_changelog, ConventionalCommit, ReleaseRules},
+};
+
+const RELEASE_TYPE_VEC: [&str; 7] = [
+    "major",
+    "minor",
+    "patch",
+    "premajor",
+    "preminor",
+    "prepatch",
+    "prerelease",
+];
+
+#[derive(Parser, Debug, Default)]
+pub struct BumpCommandArgs {
+    #[arg(
+        help = "major, minor, patch, premajor, preminor, prepatch, prerelease or an exact version. Asks when omitted"
+    )]
+    pub release: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "release",
+        help = "Pick the release type from conventional commits since the last v* tag and update CHANGELOG.md"
+    )]
+    pub auto: bool,
+
+    #[arg(long, help = "Prerelease identifier, e.g. beta gives 1.1.0-beta.0")]
+    pub preid: Option<String>,
+
+    #[arg(short, long, help = "Don't ask for confirmation")]
+    pub yes: bool,
+
+    #[arg(long, help = "Print the new version without writing package.json")]
+    pub dry_run: bool,
+
+    #[arg(long, help = "Commit package.json and the npm lock file")]
+    pub commit: bool,
+
+    #[arg(long, help = "Commit and create an annotated v{version} tag")]
+    pub tag: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Commit and tag message, {version} is replaced, with --workspace also {packages}. Defaults to SNM_BUMP_COMMIT_MESSAGE"
+    )]
+    pub message: Option<String>,
+
+    #[arg(long, help = "Commit even if tracked files have uncommitted changes")]
+    pub allow_dirty: bool,
+
+    #[arg(
+        long,
+        help = "Bump every changed workspace package and the ranges its siblings depend on it with"
+    )]
+    pub workspace: bool,
+}
+
+pub fn bump_impl(args: BumpCommandArgs) -> Result<(), SnmError> {
+    if args.workspace {
+        return bump_workspace_impl(args);
+    }
+
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let mut document = PackageJsonDocument::from_file_path(&dir.join("package.json"))?;
+
+    let raw_version = document
+        .get::<String>(&["version"])?
+        .unwrap_or("0.0.0".to_string());
+    let current_version = Version::parse(&raw_version).map_err(|error| {
+        SnmError::Error(format!(
+            "Invalid version {} in {}: {}",
+            raw_version,
+            document.file_path().display(),
+            error
+        ))
+    })?;
+    let use_git = (args.commit || args.tag) && !args.dry_run;
+    if use_git {
+        check_work_tree(&dir, args.allow_dirty)?;
+    }
+
+    let preid = args.preid.as_deref();
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+
+    let auto_release = if args.auto {
+        Some(recommend_release(&dir, &current_version, preid)?)
+    } else {
+        None
+    };
+    let release = auto_release
+        .as_ref()
+        .map(|auto_release| &auto_release.release)
+        .or(args.release.as_ref());
+
+    let new_version = match release {
+        Some(release) => {
+            let new_version = next_version(&current_version, release, preid)?;
+            if !args.yes && !args.dry_run && interactive {
+                confirm(format!(
+                    "🤔 Bump {} -> {} ?",
+                    current_version,
+                    new_version.to_string().bright_green()
+                ))?;
+            }
+            new_version
+        }
+        None if interactive => select_version(&current_version, preid)?,
+        None => {
+            return Err(SnmError::Error(format!(
+                "Missing release type, e.g. snm bump patch , one of {} or an exact version",
+                RELEASE_TYPE_VEC.join(", ")
+            )))
+        }
+    };
+
+    if args.dry_run {
+        println!("{}", new_version);
+        return Ok(());
+    }
+
+    let tag_name = format!("v{}", new_version);
+    if args.tag && tag_exists(&dir, &tag_name) {
+        return Err(SnmError::Error(format!("Tag {} already exists", tag_name)));
+    }
+
+    document.set(&["version"], &new_version.to_string())?;
+    document.save()?;
+    let mut changed_file_vec = vec![document.file_path().to_path_buf()];
+    changed_file_vec.extend(bump_lock_file_version(&dir, &new_version)?);
+    if let Some(auto_release) = &auto_release {
+        let changelog_path_buf = dir.join("CHANGELOG.md");
+        let section = auto_release.rules.render_section(
+            &new_version,
+            &chrono::Local::now().format("%Y-%m-%d").to_string(),
+            &auto_release.commit_vec,
+        );
+        prepend_changelog(&changelog_path_buf, &section)?;
+        changed_file_vec.push(changelog_path_buf);
+    }
+
+    println_success!(
+        "Bumped version {} -> {}",
+        current_version.to_string().bright_black(),
+        new_version.to_string().bright_green()
+    );
+
+    if use_git {
+        let message = args
+            .message
+            .unwrap_or_else(|| SnmConfig::new().get_bump_commit_message())
+            .replace("{version}", &new_version.to_string());
+        commit_and_tag(
+            &dir,
+            &changed_file_vec,
+            &message,
+            &args.tag.then_some(tag_name).into_iter().collect::<Vec<_>>(),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(crate) struct AutoRelease {
+    pub release: String,
+    pub rules: ReleaseRules,
+    pub commit_vec: Vec<ConventionalCommit>,
+}
+
+impl AutoRelease {
+    /// The release type recommended by the conventional commits after `since` that
+    /// touched `path`, `None` when none of them is worth a release.
+    pub fn collect(
+        dir: &Path,
+        since: Option<&str>,
+        path: &Path,
+        current_version: &Version,
+        preid: Option<&str>,
+    ) -> Result<Option<Self>, SnmError> {
+        let commit_vec: Vec<ConventionalCommit> = get_commit_vec(dir, since, path)?
+            .iter()
+            .filter_map(|(hash, message)| ConventionalCommit::parse(hash, message))
+            .collect();
+        let rules = ReleaseRules::from_config(&SnmConfig::new());
+
+        let Some(level) = rules.recommend(&commit_vec, current_version) else {
+            return Ok(None);
+        };
+
+        // --preid 时发预发布版本，已经是预发布则只递增序号
+        let release = match preid {
+            Some(_) if !current_version.pre.is_empty() => "prerelease".to_string(),
+            Some(_) => format!("pre{}", level),
+            None => level.to_string(),
+        };
+
+        Ok(Some(Self {
+            release,
+            rules,
+            commit_vec,
+        }))
+    }
+}
+
+fn recommend_release(
+    dir: &Path,
+    current_version: &Version,
+    preid: Option<&str>,
+) -> Result<AutoRelease, SnmError> {
+    let since = get_latest_version_tag(dir);
+    AutoRelease::collect(dir, since.as_deref(), dir, current_version, preid)?.ok_or_else(|| {
+        let rules = ReleaseRules::from_config(&SnmConfig::new());
+        SnmError::Error(format!(
+            "Nothing to release, no breaking change or {} commit since {}",
+            [&rules.minor_type_vec[..], &rules.patch_type_vec[..]]
+                .concat()
+                .join(" / "),
+            since.as_deref().unwrap_or("the first commit")
+        ))
+    })
+}
+
+pub(crate) fn confirm(prompt: String) -> Result<(), SnmError> {
+    let confirmed = Confirm::new()
+        .with_prompt(prompt)
+        .default(true)
+        .interact()
+        .map_err(SnmError::prompt)?;
+    if !confirmed {
+        return Err(SnmError::SilentExit);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_work_tree(dir: &Path, allow_dirty: bool) -> Result<(), SnmError> {
+    if !is_repository(dir) {
+        return Err(SnmError::Error(format!(
+            "{} is not inside a git repository",
+            dir.display()
+        )));
+    }
+    let dirty_file_vec = get_dirty_file_vec(dir)?;
+    if !allow_dirty && !dirty_file_vec.is_empty() {
+        return Err(SnmError::Error(format!(
+            "Git working tree is dirty: {} , commit or stash them first, or pass --allow-dirty",
+            dirty_file_vec.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+// npm 的 lock 文件里也记录了根包的版本号，pnpm / yarn 的没有
+fn bump_lock_file_version(dir: &Path, version: &Version) -> Result<Vec<PathBuf>, SnmError> {
+    let mut changed_file_vec = vec![];
+    for lock_file in ["package-lock.json", "npm-shrinkwrap.json"] {
+        let lock_file_path_buf = dir.join(lock_file);
+        if !lock_file_path_buf.is_file() {
+            continue;
+        }
+        let mut document = PackageJsonDocument::from_file_path(&lock_file_path_buf)?;
+        for path in [&["version"][..], &["packages", "", "version"][..]] {
+            if document.get::<String>(path)?.is_some() {
+                document.set(path, &version.to_string())?;
+            }
+        }
+        document.save()?;
+        changed_file_vec.push(lock_file_path_buf);
+    }
+    Ok(changed_file_vec)
+}
+
+pub(crate) fn commit_and_tag(
+    dir: &Path,
+    file_vec: &[PathBuf],
+    message: &str,
+    tag_name_vec: &[String],
+) -> Result<(), SnmError> {
+    let mut add_args = vec!["add".to_string(), "--".to_string()];
+    add_args.extend(file_vec.iter().map(|file| file.display().to_string()));
+    git(
+        dir,
+        &add_args.iter().map(String::as_str).collect::<Vec<_>>(),
+    )?;
+    git(dir, &["commit", "-m", message])?;
+
+    for tag_name in tag_name_vec {
+        git(dir, &["tag", "-a", tag_name, "-m", message])?;
+    }
+
+    if tag_name_vec.is_empty() {
+        println_success!("Committed {}", message.bright_black());
+    } else {
+        println_success!(
+            "Committed and tagged {}",
+            tag_name_vec.join(", ").bright_green()
+        );
+    }
+    Ok(())
+}
+
+fn select_version(current_version: &Version, preid: Option<&str>) -> Result<Version, SnmError> {
+    let version_vec = RELEASE_TYPE_VEC
+        .iter()
+        .map(|release| next_version(current_version, release, preid))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let item_vec: Vec<String> = RELEASE_TYPE_VEC
         .iter()
-        .map(|(_, s)| s.clone())
+        .zip(&version_vec)
+        .map(|(release, version)| format!("{:<12} {}", release, version.to_string().bright_black()))
         .collect();
+
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt(format!(
-            "请选择要升级的版本号: {} ",
+            "Select the next version of {}",
             current_version.to_string().bright_purple()
         ))
         .default(0)
-        .items(&selections[..])
-        .interact()
-        .expect("bump_impl Select error");
+        .items(&item_vec)
+        .interact_opt()
+        .map_err(SnmError::prompt)?
+        .ok_or(SnmError::SilentExit)?;
+
+    Ok(version_vec[selection].clone())
+}
 
-    let dir = current_dir().expect("get current dir failed");
+/// The version after `current` for a release type, same rules as `npm version`:
+/// `1.2.0-beta.3` + `prerelease` is `1.2.0-beta.4`, + `minor` is `1.2.0`.
+/// Anything else is taken as an exact version.
+pub(crate) fn next_version(
+    current: &Version,
+    release: &str,
+    preid: Option<&str>,
+) -> Result<Version, SnmError> {
+    let is_pre = !current.pre.is_empty();
+    let (major, minor, patch) = (current.major, current.minor, current.patch);
 
-    let c = fs::read_to_string(dir.join("package.json")).expect(
-        format!(
-            "bump_impl read_to_string error {:?}",
-            dir.join("package.json").display()
-        )
-        .as_str(),
-    );
+    let (next, pre) = match release {
+        "major" if is_pre && minor == 0 && patch == 0 => ((major, 0, 0), None),
+        "major" => ((major + 1, 0, 0), None),
+        "minor" if is_pre && patch == 0 => ((major, minor, 0), None),
+        "minor" => ((major, minor + 1, 0), None),
+        "patch" if is_pre => ((major, minor, patch), None),
+        "patch" => ((major, minor, patch + 1), None),
+        "premajor" => ((major + 1, 0, 0), Some(first_prerelease(preid))),
+        "preminor" => ((major, minor + 1, 0), Some(first_prerelease(preid))),
+        "prepatch" => ((major, minor, patch + 1), Some(first_prerelease(preid))),
+        "prerelease" if !is_pre => ((major, minor, patch + 1), Some(first_prerelease(preid))),
+        "prerelease" => (
+            (major, minor, patch),
+            Some(increment_prerelease(current.pre.as_str(), preid)),
+        ),
+        exact => {
+            let version = Version::parse(exact.trim_start_matches('v')).map_err(|_| {
+                SnmError::Error(format!(
+                    "Invalid release type {} , expected one of {} or an exact version",
+                    exact,
+                    RELEASE_TYPE_VEC.join(", ")
+                ))
+            })?;
+            if version == *current {
+                return Err(SnmError::Error(format!("Version is already {}", version)));
+            }
+            return Ok(version);
+        }
+    };
 
-    let version_regex = Regex::new(r#""version"\s*:\s*"[^"]*""#).expect("create regex error");
-    let replacement = format!(
-        r#""version": "{}""#,
-        versions_and_strings[selection].0.to_string()
-    );
+    let mut version = Version::new(next.0, next.1, next.2);
+    if let Some(pre) = pre {
+        version.pre = Prerelease::new(&pre)
+            .map_err(|error| SnmError::Error(format!("Invalid preid {}: {}", pre, error)))?;
+    }
+    Ok(version)
+}
 
-    let x = version_regex.replace(&c, replacement.as_str());
+fn first_prerelease(preid: Option<&str>) -> String {
+    match preid {
+        Some(preid) => format!("{}.0", preid),
+        None => "0".to_string(),
+    }
+}
 
-    fs::write(dir.join("package.json"), x.to_string()).expect(
-        format!(
-            "bump_impl write error {:?}",
-            dir.join("package.json").display()
-        )
-        .as_str(),
-    );
+// beta.3 -> beta.4 , beta -> beta.0 , 换了 preid 则从 0 开始
+fn increment_prerelease(pre: &str, preid: Option<&str>) -> String {
+    let mut identifier_vec: Vec<String> = pre.split('.').map(String::from).collect();
 
-    println!(
-        "您选择了: {} , {:?}",
-        selections[selection], versions_and_strings[selection].0
-    );
+    if let Some(preid) = preid {
+        if identifier_vec[0] != preid {
+            return first_prerelease(Some(preid));
+        }
+    }
 
-    Ok(())
+    match identifier_vec
+        .iter()
+        .rposition(|identifier| identifier.parse::<u64>().is_ok())
+    {
+        Some(index) => {
+            let number = identifier_vec[index].parse::<u64>().unwrap_or_default();
+            identifier_vec[index] = (number + 1).to_string();
+        }
+        None => identifier_vec.push("0".to_string()),
+    }
+    identifier_vec.join(".")
 }
 
-fn create_version_and_string(
-    version_type: &str,
-    major: u64,
-    minor: u64,
-    patch: u64,
-    pre: Option<Prerelease>,
-) -> Result<(Version, String), SnmError> {
-    let mut new_version = Version::new(major, minor, patch);
-    if let Some(p) = pre {
-        new_version.pre = p.clone();
-    }
-    let version_string = format!(
-        "{:<12} {}",
-        version_type,
-        new_version.to_string().bright_black()
-    );
-    Ok((new_version, version_string))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(current: &str, release: &str, preid: Option<&str>) -> String {
+        next_version(&Version::parse(current).unwrap(), release, preid)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn release_types() {
+        let cases = [
+            ("1.2.3", "major", None, "2.0.0"),
+            ("1.2.3", "minor", None, "1.3.0"),
+            ("1.2.3", "patch", None, "1.2.4"),
+            ("1.2.3", "premajor", None, "2.0.0-0"),
+            ("1.2.3", "preminor", Some("beta"), "1.3.0-beta.0"),
+            ("1.2.3", "prepatch", Some("rc"), "1.2.4-rc.0"),
+            ("1.2.3", "prerelease", None, "1.2.4-0"),
+            ("2.0.0-rc.1", "major", None, "2.0.0"),
+            ("1.3.0-beta.2", "minor", None, "1.3.0"),
+            ("1.2.4-0", "patch", None, "1.2.4"),
+            ("1.2.3", "v1.5.0", None, "1.5.0"),
+        ];
+
+        for (current, release, preid, expected) in cases {
+            assert_eq!(
+                next(current, release, preid),
+                expected,
+                "{} {}",
+                current,
+                release
+            );
+        }
+    }
+
+    #[test]
+    fn prerelease_increments_the_last_number() {
+        let cases = [
+            ("1.0.0-beta.3", None, "1.0.0-beta.4"),
+            ("1.0.0-beta.3", Some("beta"), "1.0.0-beta.4"),
+            ("1.0.0-beta.9", None, "1.0.0-beta.10"),
+            ("1.0.0-255", None, "1.0.0-256"),
+            ("1.0.0-beta", None, "1.0.0-beta.0"),
+            ("1.0.0-beta.1.alpha", None, "1.0.0-beta.2.alpha"),
+            ("1.0.0-beta.3", Some("rc"), "1.0.0-rc.0"),
+        ];
+
+        for (current, preid, expected) in cases {
+            assert_eq!(next(current, "prerelease", preid), expected, "{}", current);
+        }
+    }
+
+    #[test]
+    fn invalid_release() {
+        let current = Version::parse("1.0.0").unwrap();
+        assert!(next_version(&current, "huge", None).is_err());
+        assert!(next_version(&current, "1.0.0", None).is_err());
+        assert!(next_version(&current, "premajor", Some("bad preid")).is_err());
+    }
 }
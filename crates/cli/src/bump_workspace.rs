@@ -0,0 +1,413 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env::current_dir,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+use semver::Version;
+use snm_core::{
+    config::SnmConfig,
+    model::{PackageJsonDocument, SnmError, Workspace},
+    println_success,
+    utils::git::{get_commit_vec, get_latest_version_tag, is_repository, tag_exists},
+};
+
+use crate::{
+    bump::{check_work_tree, commit_and_tag, confirm, next_version, AutoRelease, BumpCommandArgs},
+    changelog::prepend_changelog,
+};
+
+const DEPENDENCY_FIELD_VEC: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+struct PlannedBump {
+    name: String,
+    relative_dir: String,
+    current_version: Version,
+    new_version: Version,
+    auto_release: Option<AutoRelease>,
+}
+
+struct SpecifierUpdate {
+    dependent: String,
+    field: &'static str,
+    dependency: String,
+    from: String,
+    to: String,
+}
+
+/// A package.json of the workspace, the root one has an empty relative dir.
+struct Manifest {
+    name: String,
+    relative_dir: String,
+    document: PackageJsonDocument,
+}
+
+/// `snm bump --workspace`: bump every workspace package changed since its last
+/// `name@version` tag ( or the last `v*` tag ) and move the ranges siblings use to
+/// depend on it.
+pub fn bump_workspace_impl(args: BumpCommandArgs) -> Result<(), SnmError> {
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let workspace = Workspace::find(&dir)?.ok_or(SnmError::Error(format!(
+        "Not found pnpm-workspace.yaml or a package.json with workspaces above {}",
+        dir.display()
+    )))?;
+    let root_dir = &workspace.root_dir;
+
+    if args.release.is_none() && !args.auto {
+        return Err(SnmError::Error(
+            "snm bump --workspace needs a release type or --auto".to_string(),
+        ));
+    }
+    if !is_repository(root_dir) {
+        return Err(SnmError::Error(format!(
+            "{} is not inside a git repository, changed packages can not be found",
+            root_dir.display()
+        )));
+    }
+    let use_git = (args.commit || args.tag) && !args.dry_run;
+    if use_git {
+        check_work_tree(root_dir, args.allow_dirty)?;
+    }
+
+    let mut manifest_vec = vec![Manifest {
+        name: "(root)".to_string(),
+        relative_dir: String::new(),
+        document: PackageJsonDocument::from_file_path(&root_dir.join("package.json"))?,
+    }];
+    for package in &workspace.package_vec {
+        manifest_vec.push(Manifest {
+            name: package.name.clone(),
+            relative_dir: package.relative_dir.clone(),
+            document: PackageJsonDocument::from_file_path(&package.dir.join("package.json"))?,
+        });
+    }
+
+    let preid = args.preid.as_deref();
+    let fallback_tag = get_latest_version_tag(root_dir);
+    let mut plan_vec = vec![];
+
+    for manifest in manifest_vec.iter().skip(1) {
+        let Some(raw_version) = manifest.document.get::<String>(&["version"])? else {
+            continue;
+        };
+        let current_version = Version::parse(&raw_version).map_err(|error| {
+            SnmError::Error(format!(
+                "Invalid version {} in {}: {}",
+                raw_version,
+                manifest.document.file_path().display(),
+                error
+            ))
+        })?;
+
+        let package_tag = format!("{}@{}", manifest.name, current_version);
+        let since = if tag_exists(root_dir, &package_tag) {
+            Some(package_tag)
+        } else {
+            fallback_tag.clone()
+        };
+        let package_dir = root_dir.join(&manifest.relative_dir);
+
+        let (release, auto_release) = match &args.release {
+            Some(release) => {
+                if get_commit_vec(root_dir, since.as_deref(), &package_dir)?.is_empty() {
+                    continue;
+                }
+                (release.clone(), None)
+            }
+            None => {
+                let Some(auto_release) = AutoRelease::collect(
+                    root_dir,
+                    since.as_deref(),
+                    &package_dir,
+                    &current_version,
+                    preid,
+                )?
+                else {
+                    continue;
+                };
+                (auto_release.release.clone(), Some(auto_release))
+            }
+        };
+
+        plan_vec.push(PlannedBump {
+            name: manifest.name.clone(),
+            relative_dir: manifest.relative_dir.clone(),
+            new_version: next_version(&current_version, &release, preid)?,
+            current_version,
+            auto_release,
+        });
+    }
+
+    if plan_vec.is_empty() {
+        println_success!("No workspace package changed since its last release");
+        return Ok(());
+    }
+
+    let new_version_map: HashMap<&str, &Version> = plan_vec
+        .iter()
+        .map(|plan| (plan.name.as_str(), &plan.new_version))
+        .collect();
+
+    let mut update_map: BTreeMap<usize, Vec<SpecifierUpdate>> = BTreeMap::new();
+    for (index, manifest) in manifest_vec.iter().enumerate() {
+        for field in DEPENDENCY_FIELD_VEC {
+            let Some(dependency_map) = manifest
+                .document
+                .get::<BTreeMap<String, String>>(&[field])?
+            else {
+                continue;
+            };
+            for (dependency, from) in dependency_map {
+                let Some(new_version) = new_version_map.get(dependency.as_str()) else {
+                    continue;
+                };
+                if let Some(to) = rewrite_specifier(&from, new_version) {
+                    update_map.entry(index).or_default().push(SpecifierUpdate {
+                        dependent: manifest.name.clone(),
+                        field,
+                        dependency,
+                        from,
+                        to,
+                    });
+                }
+            }
+        }
+    }
+
+    print_plan(&plan_vec, update_map.values().flatten());
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let tag_name_vec: Vec<String> = plan_vec
+        .iter()
+        .map(|plan| format!("{}@{}", plan.name, plan.new_version))
+        .collect();
+    if args.tag {
+        if let Some(tag_name) = tag_name_vec
+            .iter()
+            .find(|tag_name| tag_exists(root_dir, tag_name))
+        {
+            return Err(SnmError::Error(format!("Tag {} already exists", tag_name)));
+        }
+    }
+
+    if !args.yes && std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        confirm(format!("🤔 Bump {} packages ?", plan_vec.len()))?;
+    }
+
+    let mut changed_file_vec: Vec<PathBuf> = vec![];
+
+    for plan in &plan_vec {
+        let manifest = manifest_vec
+            .iter_mut()
+            .find(|manifest| manifest.relative_dir == plan.relative_dir)
+            .expect("planned package is a manifest");
+        manifest
+            .document
+            .set(&["version"], &plan.new_version.to_string())?;
+
+        if let Some(auto_release) = &plan.auto_release {
+            let changelog_path_buf = root_dir.join(&plan.relative_dir).join("CHANGELOG.md");
+            let section = auto_release.rules.render_section(
+                &plan.new_version,
+                &chrono::Local::now().format("%Y-%m-%d").to_string(),
+                &auto_release.commit_vec,
+            );
+            prepend_changelog(&changelog_path_buf, &section)?;
+            changed_file_vec.push(changelog_path_buf);
+        }
+    }
+
+    for (index, update_vec) in &update_map {
+        let document = &mut manifest_vec[*index].document;
+        for update in update_vec {
+            document.set(&[update.field, &update.dependency], &update.to)?;
+        }
+    }
+
+    for (index, manifest) in manifest_vec.iter().enumerate() {
+        let changed = update_map.contains_key(&index)
+            || plan_vec
+                .iter()
+                .any(|plan| plan.relative_dir == manifest.relative_dir);
+        if changed {
+            manifest.document.save()?;
+            changed_file_vec.push(manifest.document.file_path().to_path_buf());
+        }
+    }
+
+    changed_file_vec.extend(bump_lock_file(
+        root_dir,
+        &plan_vec,
+        &update_map,
+        &manifest_vec,
+    )?);
+
+    println_success!("Bumped {}", tag_name_vec.join(", ").bright_green());
+
+    if use_git {
+        let message = commit_message(
+            args.message
+                .unwrap_or_else(|| SnmConfig::new().get_bump_workspace_commit_message()),
+            &plan_vec,
+            &tag_name_vec,
+        );
+        let tag_name_vec = if args.tag { tag_name_vec } else { vec![] };
+        commit_and_tag(root_dir, &changed_file_vec, &message, &tag_name_vec)?;
+    }
+
+    Ok(())
+}
+
+/// `{packages}` is the bumped `name@version` list, `{version}` the new versions,
+/// usually a single one when the packages are released together.
+fn commit_message(template: String, plan_vec: &[PlannedBump], tag_name_vec: &[String]) -> String {
+    let mut version_vec: Vec<String> = vec![];
+    for plan in plan_vec {
+        let version = plan.new_version.to_string();
+        if !version_vec.contains(&version) {
+            version_vec.push(version);
+        }
+    }
+    template
+        .replace("{packages}", &tag_name_vec.join(", "))
+        .replace("{version}", &version_vec.join(", "))
+}
+
+// package-lock.json 里每个 workspace 包都有一份 version 和依赖范围
+fn bump_lock_file(
+    root_dir: &Path,
+    plan_vec: &[PlannedBump],
+    update_map: &BTreeMap<usize, Vec<SpecifierUpdate>>,
+    manifest_vec: &[Manifest],
+) -> Result<Vec<PathBuf>, SnmError> {
+    let mut changed_file_vec = vec![];
+    for lock_file in ["package-lock.json", "npm-shrinkwrap.json"] {
+        let lock_file_path_buf = root_dir.join(lock_file);
+        if !lock_file_path_buf.is_file() {
+            continue;
+        }
+        let mut document = PackageJsonDocument::from_file_path(&lock_file_path_buf)?;
+
+        for plan in plan_vec {
+            let path = ["packages", plan.relative_dir.as_str(), "version"];
+            if document.get::<String>(&path)?.is_some() {
+                document.set(&path, &plan.new_version.to_string())?;
+            }
+        }
+        for (index, update_vec) in update_map {
+            let relative_dir = manifest_vec[*index].relative_dir.as_str();
+            for update in update_vec {
+                let path = [
+                    "packages",
+                    relative_dir,
+                    update.field,
+                    update.dependency.as_str(),
+                ];
+                if document.get::<String>(&path)?.is_some() {
+                    document.set(&path, &update.to)?;
+                }
+            }
+        }
+
+        document.save()?;
+        changed_file_vec.push(lock_file_path_buf);
+    }
+    Ok(changed_file_vec)
+}
+
+fn print_plan<'a>(
+    plan_vec: &[PlannedBump],
+    update_iter: impl Iterator<Item = &'a SpecifierUpdate>,
+) {
+    let width = plan_vec
+        .iter()
+        .map(|plan| plan.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for plan in plan_vec {
+        println!(
+            "{:<width$}  {:<14} -> {}",
+            plan.name,
+            plan.current_version.to_string().bright_black(),
+            plan.new_version.to_string().bright_green(),
+            width = width
+        );
+    }
+
+    let update_vec: Vec<&SpecifierUpdate> = update_iter.collect();
+    if update_vec.is_empty() {
+        return;
+    }
+    println!();
+    for update in update_vec {
+        println!(
+            "{}  {} {}  {} -> {}",
+            update.dependent,
+            update.field.bright_black(),
+            update.dependency,
+            update.from.bright_black(),
+            update.to.bright_green()
+        );
+    }
+}
+
+/// The specifier pointing at `version` instead, `None` when it can stay:
+/// `^1.2.0` -> `^1.3.0`, `workspace:~1.2.0` -> `workspace:~1.3.0`, while
+/// `workspace:^`, `workspace:*`, `*` or `>=1` already accept any new version.
+fn rewrite_specifier(specifier: &str, version: &Version) -> Option<String> {
+    let (protocol, range) = match specifier.strip_prefix("workspace:") {
+        Some(range) => ("workspace:", range),
+        None => ("", specifier),
+    };
+    let (operator, old_version) = match range.strip_prefix(['^', '~']) {
+        Some(old_version) => (&range[..1], old_version),
+        None => ("", range),
+    };
+
+    Version::parse(old_version).ok()?;
+    let new_specifier = format!("{}{}{}", protocol, operator, version);
+    (new_specifier != specifier).then_some(new_specifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_internal_specifiers() {
+        let version = Version::parse("1.3.0").unwrap();
+        let cases = [
+            ("^1.2.0", Some("^1.3.0")),
+            ("~1.2.0", Some("~1.3.0")),
+            ("1.2.0", Some("1.3.0")),
+            ("workspace:^1.2.0", Some("workspace:^1.3.0")),
+            ("workspace:1.2.0", Some("workspace:1.3.0")),
+            ("^1.3.0", None),
+            ("workspace:^", None),
+            ("workspace:*", None),
+            ("*", None),
+            (">=1.0.0", None),
+            ("file:../core", None),
+        ];
+
+        for (specifier, expected) in cases {
+            assert_eq!(
+                rewrite_specifier(specifier, &version).as_deref(),
+                expected,
+                "{}",
+                specifier
+            );
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::{fs, path::Path};
+
+use semver::Version;
+use snm_core::{config::SnmConfig, model::SnmError};
+
+/// `feat(core)!: drop node 16` style commit message, see https://www.conventionalcommits.org
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    pub hash: String,
+    pub kind: String,
+    pub scope: Option<String>,
+    pub subject: String,
+    pub breaking: bool,
+}
+
+impl ConventionalCommit {
+    pub fn parse(hash: &str, message: &str) -> Option<Self> {
+        let mut line_iter = message.lines();
+        let (prefix, subject) = line_iter.next()?.split_once(": ")?;
+
+        let (prefix, breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?.to_string())),
+            None => (prefix, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        let breaking = breaking
+            || line_iter.any(|line| {
+                line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+            });
+
+        Some(Self {
+            hash: hash.to_string(),
+            kind: kind.to_lowercase(),
+            scope,
+            subject: subject.trim().to_string(),
+            breaking,
+        })
+    }
+}
+
+/// Which commit types release which level, from `SNM_BUMP_MINOR_TYPES` and
+/// `SNM_BUMP_PATCH_TYPES`. Breaking changes always release a major version.
+pub struct ReleaseRules {
+    pub minor_type_vec: Vec<String>,
+    pub patch_type_vec: Vec<String>,
+}
+
+impl ReleaseRules {
+    pub fn from_config(snm_config: &SnmConfig) -> Self {
+        Self {
+            minor_type_vec: snm_config.get_bump_minor_type_vec(),
+            patch_type_vec: snm_config.get_bump_patch_type_vec(),
+        }
+    }
+
+    /// `major`, `minor` or `patch`, `None` when no commit is worth a release.
+    /// Before 1.0.0 a breaking change only releases a minor version.
+    pub fn recommend(
+        &self,
+        commit_vec: &[ConventionalCommit],
+        current: &Version,
+    ) -> Option<&'static str> {
+        if commit_vec.iter().any(|commit| commit.breaking) {
+            return Some(if current.major == 0 { "minor" } else { "major" });
+        }
+        if commit_vec
+            .iter()
+            .any(|commit| self.minor_type_vec.contains(&commit.kind))
+        {
+            return Some("minor");
+        }
+        if commit_vec
+            .iter()
+            .any(|commit| self.patch_type_vec.contains(&commit.kind))
+        {
+            return Some("patch");
+        }
+        None
+    }
+
+    pub fn render_section(
+        &self,
+        version: &Version,
+        date: &str,
+        commit_vec: &[ConventionalCommit],
+    ) -> String {
+        let group_vec: [(&str, Vec<&ConventionalCommit>); 3] = [
+            (
+                "⚠ BREAKING CHANGES",
+                commit_vec.iter().filter(|commit| commit.breaking).collect(),
+            ),
+            (
+                "Features",
+                commit_vec
+                    .iter()
+                    .filter(|commit| self.minor_type_vec.contains(&commit.kind))
+                    .collect(),
+            ),
+            (
+                "Bug Fixes",
+                commit_vec
+                    .iter()
+                    .filter(|commit| self.patch_type_vec.contains(&commit.kind))
+                    .collect(),
+            ),
+        ];
+
+        let mut section = format!("## {} ({})\n", version, date);
+        for (title, group) in group_vec.iter().filter(|(_, group)| !group.is_empty()) {
+            section.push_str(&format!("\n### {}\n\n", title));
+            for commit in group {
+                let scope = commit
+                    .scope
+                    .as_ref()
+                    .map(|scope| format!("**{}:** ", scope))
+                    .unwrap_or_default();
+                let short_hash = commit.hash.get(..7).unwrap_or(&commit.hash);
+                section.push_str(&format!("* {}{} ({})\n", scope, commit.subject, short_hash));
+            }
+        }
+        section
+    }
+}
+
+/// Puts `section` on top of CHANGELOG.md, below its `# ` title if it has one.
+pub fn prepend_changelog(changelog_path: &Path, section: &str) -> Result<(), SnmError> {
+    let content = if changelog_path.exists() {
+        fs::read_to_string(changelog_path).map_err(SnmError::io("read", changelog_path))?
+    } else {
+        "# Changelog\n".to_string()
+    };
+
+    let (title, rest) = if content.starts_with("# ") {
+        content.split_once('\n').unwrap_or((&content, ""))
+    } else {
+        ("", content.as_str())
+    };
+    let rest = rest.trim_start_matches('\n');
+
+    let mut new_content = String::new();
+    if !title.is_empty() {
+        new_content.push_str(title);
+        new_content.push_str("\n\n");
+    }
+    new_content.push_str(section);
+    if !rest.is_empty() {
+        new_content.push('\n');
+        new_content.push_str(rest);
+    }
+
+    fs::write(changelog_path, new_content).map_err(SnmError::io("write", changelog_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> ReleaseRules {
+        ReleaseRules {
+            minor_type_vec: vec!["feat".to_string()],
+            patch_type_vec: vec!["fix".to_string(), "perf".to_string()],
+        }
+    }
+
+    fn commit(message: &str) -> ConventionalCommit {
+        ConventionalCommit::parse("0123456789abcdef", message).unwrap()
+    }
+
+    #[test]
+    fn parse_header_and_footer() {
+        assert_eq!(
+            commit("feat(cli)!: drop node 16"),
+            ConventionalCommit {
+                hash: "0123456789abcdef".to_string(),
+                kind: "feat".to_string(),
+                scope: Some("cli".to_string()),
+                subject: "drop node 16".to_string(),
+                breaking: true,
+            }
+        );
+        assert!(commit("fix: x\n\nBREAKING CHANGE: y").breaking);
+        assert!(!commit("fix: x\n\nmentions BREAKING CHANGE: inline").breaking);
+        assert_eq!(ConventionalCommit::parse("h", "Merge branch 'main'"), None);
+        assert_eq!(ConventionalCommit::parse("h", "feat(cli: oops"), None);
+    }
+
+    #[test]
+    fn recommend_level() {
+        let one = Version::new(1, 2, 3);
+        let rules = rules();
+
+        assert_eq!(rules.recommend(&[commit("chore: x")], &one), None);
+        assert_eq!(
+            rules.recommend(&[commit("perf: x"), commit("docs: y")], &one),
+            Some("patch")
+        );
+        assert_eq!(
+            rules.recommend(&[commit("fix: x"), commit("feat: y")], &one),
+            Some("minor")
+        );
+        assert_eq!(rules.recommend(&[commit("fix!: x")], &one), Some("major"));
+        assert_eq!(
+            rules.recommend(&[commit("fix!: x")], &Version::new(0, 4, 0)),
+            Some("minor")
+        );
+    }
+
+    #[test]
+    fn render_and_prepend() {
+        let section = rules().render_section(
+            &Version::new(1, 3, 0),
+            "2024-05-01",
+            &[
+                commit("feat(core): add x"),
+                commit("fix: y"),
+                commit("chore: z"),
+            ],
+        );
+        assert_eq!(
+            section,
+            "## 1.3.0 (2024-05-01)\n\n### Features\n\n* **core:** add x (0123456)\n\n### Bug Fixes\n\n* y (0123456)\n"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CHANGELOG.md");
+        prepend_changelog(&path, "## 1.0.0\n").unwrap();
+        prepend_changelog(&path, "## 1.1.0\n").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Changelog\n\n## 1.1.0\n\n## 1.0.0\n"
+        );
+    }
+}
@@ -1,19 +1,29 @@
+use audit::audit_impl;
 use bump::bump_impl;
 use clap::{command, Parser};
 use colored::*;
 use fig::fig_spec_impl;
 use manage_command::ManageCommands;
+use migrate::migrate_impl;
 use ni::{
     npm_args::NpmArgsTransform,
     pnpm_args::PnpmArgsTransform,
     trait_transform_args::{CommandArgsCreatorTrait, InstallCommandArgs},
 };
+use node::node_upgrade_impl;
+use node_command::NodeCommands;
+use pm::{pm_pin_impl, pm_upgrade_impl};
+use pm_command::PmCommands;
+use run::run_impl;
+use script_picker::pick_script_impl;
+use self_command::SelfCommands;
+use self_manage::{self_uninstall_impl, self_update_impl};
 use snm_command::SnmCommands;
 use snm_core::{
     config::SnmConfig,
     model::{
-        dispatch_manage::DispatchManage, package_json::PackageManager, snm_error::handle_snm_error,
-        trait_manage::ManageTrait, PackageJson, SnmError,
+        dispatch_manage::DispatchManage, output::OutputFormat, package_json::PackageManager,
+        snm_error::handle_snm_error, trait_manage::ManageTrait, PackageJson, SnmError,
     },
     println_success,
 };
@@ -25,31 +35,56 @@ use std::{
     path::PathBuf,
     process::{Command, Stdio},
 };
+mod audit;
 mod bump;
+mod bump_workspace;
+mod changelog;
 mod fig;
 mod manage_command;
+mod migrate;
 mod ni;
+mod node;
+mod node_command;
+mod pm;
+mod pm_command;
+mod run;
+mod script_picker;
+mod self_command;
+mod self_manage;
 mod snm_command;
 
 #[derive(Parser, Debug)]
 struct SnmCli {
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "Output format of list, list-remote and install."
+    )]
+    output: Option<OutputFormat>,
+
     #[command(subcommand)]
     command: SnmCommands,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), SnmError> {
-    SnmConfig::new().init()?;
+async fn main() {
+    let cli = SnmCli::parse();
 
-    if let Err(error) = execute_cli().await {
+    if let Some(output) = cli.output {
+        SnmConfig::new().set_output_format(output);
+    }
+
+    if let Err(error) = SnmConfig::new().init() {
         handle_snm_error(error);
     }
 
-    Ok(())
+    if let Err(error) = execute_cli(cli).await {
+        handle_snm_error(error);
+    }
 }
 
-async fn execute_cli() -> Result<(), SnmError> {
-    let cli = SnmCli::parse();
+async fn execute_cli(cli: SnmCli) -> Result<(), SnmError> {
     let trim_version = |version: String| version.trim_start_matches(['v', 'V']).trim().to_owned();
     match cli.command {
         // manage start
@@ -110,32 +145,36 @@ async fn execute_cli() -> Result<(), SnmError> {
             }
         },
         SnmCommands::Node { command } => match command {
-            ManageCommands::Default { version } => {
-                let v: &String = &trim_version(version);
-                DispatchManage::new(Box::new(SnmNode::new()))
-                    .set_default(v)
-                    .await?
-            }
-            ManageCommands::Install { version } => {
-                let v: &String = &trim_version(version);
-                DispatchManage::new(Box::new(SnmNode::new()))
-                    .install(v)
-                    .await?;
-            }
-            ManageCommands::Uninstall { version } => {
-                let v: &String = &trim_version(version);
-                DispatchManage::new(Box::new(SnmNode::new()))
-                    .un_install(v)
-                    .await?;
-            }
-            ManageCommands::List => {
-                DispatchManage::new(Box::new(SnmNode::new())).list().await?;
-            }
-            ManageCommands::ListRemote { all } => {
-                DispatchManage::new(Box::new(SnmNode::new()))
-                    .list_remote(all)
-                    .await?;
-            }
+            NodeCommands::Manage(command) => match command {
+                ManageCommands::Default { version } => {
+                    let v: &String = &trim_version(version);
+                    DispatchManage::new(Box::new(SnmNode::new()))
+                        .set_default(v)
+                        .await?
+                }
+                ManageCommands::Install { version } => {
+                    let v: &String = &trim_version(version);
+                    DispatchManage::new(Box::new(SnmNode::new()))
+                        .install(v)
+                        .await?;
+                }
+                ManageCommands::Uninstall { version } => {
+                    let v: &String = &trim_version(version);
+                    DispatchManage::new(Box::new(SnmNode::new()))
+                        .un_install(v)
+                        .await?;
+                }
+                ManageCommands::List => {
+                    DispatchManage::new(Box::new(SnmNode::new())).list().await?;
+                }
+                ManageCommands::ListRemote { all } => {
+                    DispatchManage::new(Box::new(SnmNode::new()))
+                        .list_remote(all)
+                        .await?;
+                }
+            },
+            NodeCommands::Upgrade { major, yes } => node_upgrade_impl(major, yes).await?,
+            NodeCommands::SyncAdvisory => SnmNode::new().sync_advisory().await?,
         },
         // manage end
 
@@ -162,15 +201,39 @@ async fn execute_cli() -> Result<(), SnmError> {
         SnmCommands::FigSpec => {
             fig_spec_impl()?;
         }
-        SnmCommands::Bump => {
-            bump_impl()?;
+        SnmCommands::Bump(args) => {
+            bump_impl(args)?;
+        }
+        SnmCommands::Migrate(args) => {
+            migrate_impl(args)?;
         }
+        SnmCommands::Audit(args) => {
+            audit_impl(args).await?;
+        }
+        SnmCommands::Pm { command } => match command {
+            PmCommands::Pin { spec } => pm_pin_impl(&spec).await?,
+            PmCommands::Upgrade { range } => pm_upgrade_impl(range).await?,
+        },
+        SnmCommands::SelfManage { command } => match command {
+            SelfCommands::Update {
+                version,
+                channel,
+                force,
+            } => self_update_impl(version, channel, force).await?,
+            SelfCommands::Uninstall { yes, purge } => self_uninstall_impl(yes, purge)?,
+        },
         SnmCommands::Dlx(args) => {
             execute_command(|creator| creator.get_dlx_command(args)).await?;
         }
         SnmCommands::Exec(args) => {
             execute_command(|creator| creator.get_exec_command(args)).await?;
         }
+        SnmCommands::Run(args) if args.recursive || !args.filter.is_empty() => {
+            run_impl(args).await?;
+        }
+        SnmCommands::Run(args) if args.args.is_empty() => {
+            pick_script_impl().await?;
+        }
         SnmCommands::Run(args) => {
             execute_command(|creator| creator.get_run_command(args)).await?;
         }
@@ -182,23 +245,24 @@ async fn execute_cli() -> Result<(), SnmError> {
 }
 
 async fn get_bin() -> Result<((String, String), PathBuf), SnmError> {
-    let dir = current_dir().expect("get current dir failed");
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
     let package_json_path_buf = dir.join("package.json");
     if package_json_path_buf.exists() {
         let package_json: PackageJson = PackageJson::from_file_path(&package_json_path_buf)?;
-        let package_manager = package_json.parse_package_manager()?;
-        let manager = get_manage(&package_manager).await?;
-        let dispatcher = DispatchManage::new(manager);
-        let (_, bin_path_buf) = dispatcher.proxy_process(&package_manager.name).await?;
-        return Ok((
-            (package_manager.name, package_manager.version),
-            bin_path_buf,
-        ));
-    } else {
-        let dispatcher = DispatchManage::new(Box::new(SnmPnpm::new()));
-        let (version, bin_path_buf) = dispatcher.proxy_process("pnpm").await?;
-        return Ok((("pnpm".to_string(), version), bin_path_buf));
+        if let Some(package_manager) = package_json.resolve_package_manager()? {
+            let manager = get_manage(&package_manager).await?;
+            let dispatcher = DispatchManage::new(manager);
+            let (_, bin_path_buf) = dispatcher.proxy_process(&package_manager.name)?;
+            return Ok((
+                (package_manager.name, package_manager.version),
+                bin_path_buf,
+            ));
+        }
     }
+    // 既没有 packageManager 也没有 lock 文件时使用默认的 pnpm
+    let dispatcher = DispatchManage::new(Box::new(SnmPnpm::new()));
+    let (version, bin_path_buf) = dispatcher.proxy_process("pnpm")?;
+    Ok((("pnpm".to_string(), version), bin_path_buf))
 }
 
 async fn execute_command<F>(get_command_args: F) -> Result<(), SnmError>
@@ -210,7 +274,7 @@ where
     let command_args_creator: Box<dyn CommandArgsCreatorTrait> = match name.as_str() {
         "npm" => Box::new(NpmArgsTransform {}),
         "pnpm" => Box::new(PnpmArgsTransform {}),
-        _ => panic!("Unsupported package manager"),
+        _ => return Err(SnmError::UnsupportedPackageManager { name, version }),
     };
 
     let args = get_command_args(command_args_creator.as_ref())?;
@@ -228,7 +292,7 @@ where
         .stdin(Stdio::inherit())
         .spawn()
         .and_then(|process| process.wait_with_output())
-        .expect("spawn error");
+        .map_err(SnmError::io("spawn", &bin_path_buf))?;
     Ok(())
 }
 
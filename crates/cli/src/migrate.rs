@@ -0,0 +1,172 @@
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use colored::*;
+use snm_core::{
+    lockfile::{Lockfile, LockfileKind},
+    model::{workspace::read_pnpm_workspace_patterns, PackageJson, PackageJsonDocument, SnmError},
+    print_warning, println_success,
+    utils::detect_package_manager::{detect_package_manager, write_package_manager},
+};
+
+#[derive(Parser, Debug)]
+pub struct MigrateCommandArgs {
+    #[arg(long, help = "npm, pnpm or yarn")]
+    pub to: String,
+
+    #[arg(
+        long,
+        help = "npm, pnpm or yarn, only needed when several lock files exist"
+    )]
+    pub from: Option<String>,
+}
+
+/// `snm migrate --to pnpm`: convert the lock file to another package manager,
+/// keeping the resolved versions, then point packageManager at it.
+pub fn migrate_impl(args: MigrateCommandArgs) -> Result<(), SnmError> {
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let package_json_path_buf = dir.join("package.json");
+    if !package_json_path_buf.is_file() {
+        return Err(SnmError::Error(format!(
+            "Not found package.json file here {}",
+            package_json_path_buf.display()
+        )));
+    }
+
+    let to = parse_kind(&args.to)?;
+    let from = args.from.as_deref().map(parse_kind).transpose()?;
+    let source_path_buf = find_source(&dir, to, from)?;
+
+    let mut lockfile = Lockfile::read(&source_path_buf)?;
+    lockfile.fill_importer_manifests(&dir);
+
+    let target_path_buf = dir.join(to.file_name());
+    fs::write(&target_path_buf, lockfile.write(to))
+        .map_err(SnmError::io("write", &target_path_buf))?;
+
+    migrate_workspaces(&dir, to)?;
+
+    fs::remove_file(&source_path_buf).map_err(SnmError::io("remove", &source_path_buf))?;
+
+    // 源 lock 文件删掉后，从新 lock 文件的格式推断版本
+    let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
+    let pinned = package_json
+        .package_manager
+        .as_deref()
+        .is_some_and(|raw| raw.split('@').next() == Some(to.package_manager()));
+    if !pinned {
+        if let Some(package_manager) = detect_package_manager(&dir)? {
+            write_package_manager(&package_json_path_buf, &package_manager.raw)?;
+        }
+    }
+
+    println_success!(
+        "Migrated {} -> {} {}",
+        file_name(&source_path_buf).bright_black(),
+        to.file_name().bright_green(),
+        format!(
+            "({} packages, {} importers)",
+            lockfile.packages.len(),
+            lockfile.importers.len()
+        )
+        .bright_black()
+    );
+    Ok(())
+}
+
+fn parse_kind(name: &str) -> Result<LockfileKind, SnmError> {
+    LockfileKind::from_package_manager(name).ok_or(SnmError::Error(format!(
+        "Unsupported package manager {}, expected npm, pnpm or yarn",
+        name
+    )))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The lock file to convert, any one not already of the target kind.
+fn find_source(
+    dir: &Path,
+    to: LockfileKind,
+    from: Option<LockfileKind>,
+) -> Result<PathBuf, SnmError> {
+    let candidate_vec: Vec<PathBuf> = [
+        "package-lock.json",
+        "npm-shrinkwrap.json",
+        "pnpm-lock.yaml",
+        "yarn.lock",
+    ]
+    .iter()
+    .map(|lock_file| dir.join(lock_file))
+    .filter(|path| path.is_file())
+    .filter(|path| {
+        let kind = LockfileKind::from_file_name(&file_name(path));
+        kind != Some(to) && from.is_none_or(|from| kind == Some(from))
+    })
+    .collect();
+
+    match candidate_vec.as_slice() {
+        [] => Err(SnmError::Error(format!(
+            "No lock file to migrate to {} found in {}",
+            to.package_manager(),
+            dir.display()
+        ))),
+        [source] => Ok(source.clone()),
+        _ => Err(SnmError::Error(format!(
+            "Multiple lock files found: {} , pass --from to pick one.",
+            candidate_vec
+                .iter()
+                .map(|path| file_name(path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// pnpm reads workspaces from pnpm-workspace.yaml, npm and yarn from package.json.
+fn migrate_workspaces(dir: &Path, to: LockfileKind) -> Result<(), SnmError> {
+    let pnpm_workspace_path_buf = dir.join("pnpm-workspace.yaml");
+    let package_json_path_buf = dir.join("package.json");
+    let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
+
+    match to {
+        LockfileKind::Pnpm => {
+            let Some(workspaces) = package_json.workspaces else {
+                return Ok(());
+            };
+            if pnpm_workspace_path_buf.exists() {
+                return Ok(());
+            }
+            let content = workspaces
+                .patterns()
+                .iter()
+                .fold(String::from("packages:\n"), |content, pattern| {
+                    content + &format!("  - '{}'\n", pattern.replace('\'', "''"))
+                });
+            fs::write(&pnpm_workspace_path_buf, content)
+                .map_err(SnmError::io("write", &pnpm_workspace_path_buf))?;
+        }
+        LockfileKind::Npm | LockfileKind::Yarn => {
+            if package_json.workspaces.is_some() || !pnpm_workspace_path_buf.is_file() {
+                return Ok(());
+            }
+            let Some(pattern_vec) = read_pnpm_workspace_patterns(&pnpm_workspace_path_buf)? else {
+                return Ok(());
+            };
+            let mut document = PackageJsonDocument::from_file_path(&package_json_path_buf)?;
+            document.set(&["workspaces"], &pattern_vec)?;
+            document.save()?;
+            print_warning!(
+                "Copied the packages of pnpm-workspace.yaml to workspaces of package.json, remove pnpm-workspace.yaml when it has no other settings\n"
+            );
+        }
+    }
+    Ok(())
+}
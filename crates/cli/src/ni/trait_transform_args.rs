@@ -52,6 +52,37 @@ pub struct ExecCommandArgs {
 pub struct RunCommandArgs {
     #[arg(help = "script file path")]
     pub args: Vec<String>,
+    #[arg(
+        long,
+        help = "Run in the workspace packages whose name or path matches the glob, can be repeated"
+    )]
+    pub filter: Vec<String>,
+    #[arg(short, long, help = "Run in every workspace package")]
+    pub recursive: bool,
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "How many packages run the script at the same time"
+    )]
+    pub parallel: usize,
+    #[arg(
+        long,
+        help = "Wait for the internal dependencies of a package before running it"
+    )]
+    pub topological: bool,
+}
+
+impl RunCommandArgs {
+    /// Plain `run <script> [args]` in the current package.
+    pub fn from_args(args: Vec<String>) -> Self {
+        Self {
+            args,
+            filter: vec![],
+            recursive: false,
+            parallel: 1,
+            topological: false,
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -0,0 +1,317 @@
+use std::{
+    collections::BTreeMap,
+    env::current_dir,
+    fs,
+    io::IsTerminal,
+    ops::Not,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+use dialoguer::Confirm;
+use semver::Version;
+use snm_core::{
+    model::{
+        dispatch_manage::DispatchManage, trait_manage::ManageTrait,
+        trait_shared_behavior::SharedBehaviorTrait, SnmError,
+    },
+    print_warning, println_success,
+};
+use snm_node::{node_model::Lts, snm_node::SnmNode};
+
+// 随 node 一起发布的包，新版本自带
+const BUNDLED_PACKAGE_VEC: [&str; 2] = ["npm", "corepack"];
+
+/// `snm node upgrade [--major]`: install the newest release of the major of every
+/// installed version, then move the default, the global packages and `.node-version`
+/// over and offer to remove the old version.
+pub async fn node_upgrade_impl(major: bool, yes: bool) -> Result<(), SnmError> {
+    let snm_node = SnmNode::new();
+    let dispatcher = DispatchManage::new(Box::new(SnmNode::new()));
+
+    let (dir_vec, default_v) = dispatcher.read_runtime_dir_name_vec()?;
+    if dir_vec.is_empty() {
+        return Err(SnmError::Error(format!(
+            "Node list is empty, please use {} to install one.",
+            "snm node install <version>".bright_green().bold()
+        )));
+    }
+
+    let release_vec: Vec<(Version, bool)> = snm_node
+        .get_node_list_remote()
+        .await?
+        .into_iter()
+        .filter_map(|node| {
+            let version = Version::parse(node.version.trim_start_matches('v')).ok()?;
+            Some((version, matches!(node.lts, Lts::Str(_))))
+        })
+        .collect();
+
+    // 旧版本 -> 新版本，多个旧版本可能升级到同一个新版本
+    let mut upgrade_map: BTreeMap<Version, Version> = BTreeMap::new();
+    for v in &dir_vec {
+        let Ok(current) = Version::parse(v) else {
+            continue;
+        };
+        if let Some(target) = upgrade_target(&current, &release_vec, major) {
+            upgrade_map.insert(current, target);
+        }
+    }
+
+    if upgrade_map.is_empty() {
+        println_success!("Every installed node version is the newest of its line");
+        return Ok(());
+    }
+
+    for (current, target) in &upgrade_map {
+        let (old_v, new_v) = (current.to_string(), target.to_string());
+
+        if snm_node.get_anchor_file_path_buf(&new_v).exists().not() {
+            dispatcher.install(&new_v).await?;
+        }
+
+        let package_vec = migrate_global_packages(
+            &snm_node.get_runtime_dir_path_buf(&old_v),
+            &snm_node.get_runtime_dir_path_buf(&new_v),
+        )?;
+        if package_vec.is_empty().not() {
+            println_success!(
+                "Moved global packages {} to {}",
+                package_vec.join(", ").bright_green(),
+                new_v
+            );
+            if current.major != target.major {
+                print_warning!(
+                    "Global packages with native addons may need {}\n",
+                    "npm rebuild -g".bright_green().bold()
+                );
+            }
+        }
+
+        if default_v.as_ref() == Some(&old_v) {
+            dispatcher.set_default(&new_v).await?;
+            println_success!("Default node {} -> {}", old_v, new_v.bright_green());
+        }
+
+        update_node_version_file(&old_v, &new_v, yes)?;
+
+        if confirm(yes, format!("🤔 Remove node {} ?", old_v), false)? {
+            dispatcher.un_install(&old_v).await?;
+        }
+
+        println_success!("Upgraded node {} -> {}", old_v, new_v.bright_green());
+    }
+
+    Ok(())
+}
+
+/// The newest release of the major of `current`, with `major` the newest LTS
+/// release when that is newer. `None` when `current` is already the newest.
+fn upgrade_target(
+    current: &Version,
+    release_vec: &[(Version, bool)],
+    major: bool,
+) -> Option<Version> {
+    release_vec
+        .iter()
+        .filter(|(version, lts)| version.major == current.major || (major && *lts))
+        .map(|(version, _)| version)
+        .filter(|version| version.pre.is_empty() && *version > current)
+        .max()
+        .cloned()
+}
+
+/// Copy the packages installed with `npm install -g` and their bin links, packages
+/// already in the new version are kept.
+fn migrate_global_packages(
+    old_dir_path_buf: &Path,
+    new_dir_path_buf: &Path,
+) -> Result<Vec<String>, SnmError> {
+    let old_modules_path_buf = old_dir_path_buf.join("lib").join("node_modules");
+    let new_modules_path_buf = new_dir_path_buf.join("lib").join("node_modules");
+    if old_modules_path_buf.exists().not() {
+        return Ok(vec![]);
+    }
+
+    let mut package_vec = vec![];
+    for name in read_package_names(&old_modules_path_buf)? {
+        if BUNDLED_PACKAGE_VEC.contains(&name.as_str()) {
+            continue;
+        }
+        let to_path_buf = new_modules_path_buf.join(&name);
+        if to_path_buf.exists() {
+            continue;
+        }
+        copy_dir_all(&old_modules_path_buf.join(&name), &to_path_buf)?;
+        package_vec.push(name);
+    }
+
+    // bin 下是 npm 创建的指向 ../lib/node_modules 的相对链接，原样复制即可
+    #[cfg(unix)]
+    {
+        let old_bin_path_buf = old_dir_path_buf.join("bin");
+        let new_bin_path_buf = new_dir_path_buf.join("bin");
+        for entry in fs::read_dir(&old_bin_path_buf)
+            .map_err(SnmError::io("read dir", &old_bin_path_buf))?
+            .filter_map(|entry| entry.ok())
+        {
+            let Ok(link) = fs::read_link(entry.path()) else {
+                continue;
+            };
+            let points_to_migrated = package_vec
+                .iter()
+                .any(|name| link.starts_with(Path::new("../lib/node_modules").join(name)));
+            let to_path_buf = new_bin_path_buf.join(entry.file_name());
+            if points_to_migrated && to_path_buf.symlink_metadata().is_err() {
+                std::os::unix::fs::symlink(&link, &to_path_buf)
+                    .map_err(SnmError::io("create symlink", &to_path_buf))?;
+            }
+        }
+    }
+
+    Ok(package_vec)
+}
+
+/// `name` and `@scope/name` dirs of a node_modules.
+fn read_package_names(modules_path_buf: &Path) -> Result<Vec<String>, SnmError> {
+    let mut name_vec = vec![];
+    for entry in fs::read_dir(modules_path_buf)
+        .map_err(SnmError::io("read dir", modules_path_buf))?
+        .filter_map(|entry| entry.ok())
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        if name.starts_with('@') {
+            for scoped in fs::read_dir(entry.path())
+                .map_err(SnmError::io("read dir", entry.path()))?
+                .filter_map(|entry| entry.ok())
+            {
+                name_vec.push(format!("{}/{}", name, scoped.file_name().to_string_lossy()));
+            }
+        } else {
+            name_vec.push(name);
+        }
+    }
+    name_vec.sort();
+    Ok(name_vec)
+}
+
+fn copy_dir_all(from: &Path, to: &Path) -> Result<(), SnmError> {
+    fs::create_dir_all(to).map_err(SnmError::io("create dir", to))?;
+    for entry in fs::read_dir(from)
+        .map_err(SnmError::io("read dir", from))?
+        .filter_map(|entry| entry.ok())
+    {
+        let from_path_buf = entry.path();
+        let to_path_buf = to.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .map_err(SnmError::io("read", &from_path_buf))?;
+        if file_type.is_dir() {
+            copy_dir_all(&from_path_buf, &to_path_buf)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            {
+                let link = fs::read_link(&from_path_buf)
+                    .map_err(SnmError::io("read link", &from_path_buf))?;
+                std::os::unix::fs::symlink(link, &to_path_buf)
+                    .map_err(SnmError::io("create symlink", &to_path_buf))?;
+            }
+        } else {
+            fs::copy(&from_path_buf, &to_path_buf).map_err(SnmError::io("copy", &from_path_buf))?;
+        }
+    }
+    Ok(())
+}
+
+/// Point `.node-version` of the current dir at `new_v` when it names `old_v`.
+fn update_node_version_file(old_v: &str, new_v: &str, yes: bool) -> Result<(), SnmError> {
+    let node_version_path_buf: PathBuf = current_dir()
+        .map_err(SnmError::io("get current dir", "."))?
+        .join(".node-version");
+    let Ok(content) = fs::read_to_string(&node_version_path_buf) else {
+        return Ok(());
+    };
+
+    let pinned = content.trim();
+    if pinned.trim_start_matches(['v', 'V']) != old_v {
+        return Ok(());
+    }
+
+    if confirm(
+        yes,
+        format!(
+            "🤔 Update {} to {} ?",
+            node_version_path_buf.display(),
+            new_v
+        ),
+        true,
+    )?
+    .not()
+    {
+        return Ok(());
+    }
+
+    // 保留原来的 v 前缀写法
+    let prefix = &pinned[..pinned.len() - old_v.len()];
+    fs::write(&node_version_path_buf, format!("{}{}\n", prefix, new_v))
+        .map_err(SnmError::io("write", &node_version_path_buf))?;
+    println_success!(
+        "Updated {} to {}",
+        node_version_path_buf.display(),
+        new_v.bright_green()
+    );
+    Ok(())
+}
+
+/// Without a terminal nothing can be asked, the old version is kept then.
+fn confirm(yes: bool, prompt: String, default: bool) -> Result<bool, SnmError> {
+    if yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        print_warning!(
+            "{} skipped without a terminal, pass {} to confirm\n",
+            prompt.trim_start_matches("🤔 ").trim_end_matches(" ?"),
+            "--yes".bright_green()
+        );
+        return Ok(false);
+    }
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(default)
+        .interact()
+        .map_err(SnmError::prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_target_stays_on_the_major_unless_asked() {
+        let release_vec: Vec<(Version, bool)> = [
+            ("18.19.0", true),
+            ("20.11.0", true),
+            ("20.12.2", true),
+            ("21.7.3", false),
+            ("22.0.0-rc.1", false),
+        ]
+        .iter()
+        .map(|(version, lts)| (Version::parse(version).unwrap(), *lts))
+        .collect();
+
+        let target = |current: &str, major: bool| {
+            upgrade_target(&Version::parse(current).unwrap(), &release_vec, major)
+                .map(|version| version.to_string())
+        };
+
+        assert_eq!(target("20.11.0", false).as_deref(), Some("20.12.2"));
+        assert_eq!(target("20.12.2", false), None);
+        assert_eq!(target("18.0.0", true).as_deref(), Some("20.12.2"));
+        // 比最新 LTS 还新的非 LTS 版本只在本大版本内升级
+        assert_eq!(target("21.0.0", true).as_deref(), Some("21.7.3"));
+    }
+}
@@ -0,0 +1,26 @@
+use clap::Subcommand;
+
+use crate::manage_command::ManageCommands;
+
+#[derive(Subcommand, Debug)]
+pub enum NodeCommands {
+    #[command(flatten)]
+    Manage(ManageCommands),
+    /// Move installed node versions to the newest release of their major
+    Upgrade {
+        #[arg(
+            long,
+            help = "Also move to a newer major, the newest LTS release at least"
+        )]
+        major: bool,
+
+        #[arg(
+            short,
+            long,
+            help = "Don't ask before updating .node-version and removing old versions"
+        )]
+        yes: bool,
+    },
+    /// Refresh the node release data the node shim warns from
+    SyncAdvisory,
+}
@@ -0,0 +1,271 @@
+use std::{env::current_dir, fs};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use colored::*;
+use reqwest::header::AUTHORIZATION;
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+use snm_core::{
+    config::{Npmrc, SnmConfig},
+    model::{PackageJson, SnmError},
+    println_success,
+    utils::{
+        detect_package_manager::write_package_manager,
+        download::{DownloadBuilder, WriteStrategy},
+        http::http_client,
+    },
+};
+
+const SUPPORTED_PACKAGE_MANAGER_VEC: [&str; 4] = ["npm", "pnpm", "yarn", "bun"];
+
+struct Resolved {
+    version: Version,
+    manifest: Value,
+    packument_url: String,
+}
+
+/// `snm pm pin pnpm@^9`: resolve the newest matching version, download and verify
+/// it, then write `pnpm@9.x.y+sha512.<hex>` to packageManager like corepack does.
+pub async fn pm_pin_impl(spec: &str) -> Result<(), SnmError> {
+    let package_json_path_buf = current_dir()
+        .map_err(SnmError::io("get current dir", "."))?
+        .join("package.json");
+    if !package_json_path_buf.exists() {
+        return Err(SnmError::Error(format!(
+            "Not found package.json file here {}",
+            package_json_path_buf.display()
+        )));
+    }
+
+    let (name, range) = match spec.rsplit_once('@') {
+        Some((name, range)) if !name.is_empty() => (name, range),
+        _ => (spec, "latest"),
+    };
+
+    let raw = pin(name, range).await?;
+    write_package_manager(&package_json_path_buf, &raw)?;
+
+    println_success!(
+        "Pinned {} {}",
+        raw.bright_green(),
+        format!("in {}", package_json_path_buf.display()).bright_black()
+    );
+    Ok(())
+}
+
+/// `snm pm upgrade [range]`: pin the newest version within `range`, `^current` by default.
+pub async fn pm_upgrade_impl(range: Option<String>) -> Result<(), SnmError> {
+    let package_json = PackageJson::from_here()?;
+    let current = package_json.parse_package_manager()?;
+    let range = range.unwrap_or_else(|| format!("^{}", current.version));
+
+    let raw = pin(&current.name, &range).await?;
+
+    if raw == current.raw {
+        println_success!(
+            "{} is already the newest within {}",
+            current.raw.bright_green(),
+            range
+        );
+        return Ok(());
+    }
+
+    if let Some(package_json_path_buf) = &package_json._raw_file_path {
+        write_package_manager(package_json_path_buf, &raw)?;
+    }
+
+    println_success!(
+        "Upgraded packageManager {} -> {}",
+        current.raw.bright_black(),
+        raw.bright_green()
+    );
+    Ok(())
+}
+
+async fn pin(name: &str, range: &str) -> Result<String, SnmError> {
+    if !SUPPORTED_PACKAGE_MANAGER_VEC.contains(&name) {
+        return Err(SnmError::UnsupportedPackageManager {
+            name: name.to_string(),
+            version: range.to_string(),
+        });
+    }
+
+    let snm_config = SnmConfig::new();
+    let mut best: Option<Resolved> = None;
+
+    for package in registry_package_vec(name) {
+        // 校验和只信任主源（或 SNM_NPM_SHASUM_REGISTRY_HOST），不走镜像
+        let registry = snm_config.get_npm_shasum_registry_host(package);
+        let packument_url = format!("{}/{}", registry, package);
+
+        let packument = match get_json(&packument_url).await {
+            Ok(packument) => packument,
+            Err(SnmError::ResourceNotFound { .. }) => continue,
+            Err(error) => return Err(error),
+        };
+
+        if let Some((version, manifest)) = resolve_version(&packument, range)? {
+            if best.as_ref().is_none_or(|best| version > best.version) {
+                best = Some(Resolved {
+                    version,
+                    manifest,
+                    packument_url,
+                });
+            }
+        }
+    }
+
+    let resolved = best.ok_or(SnmError::Error(format!(
+        "No {} version matches {}",
+        name, range
+    )))?;
+
+    let tarball = resolved
+        .manifest
+        .get("dist")
+        .and_then(|dist| dist.get("tarball"))
+        .and_then(Value::as_str)
+        .ok_or(SnmError::Error(format!(
+            "{}@{} has no dist.tarball",
+            name, resolved.version
+        )))?;
+    let tarball_url = reqwest::Url::parse(&resolved.packument_url)
+        .and_then(|base| base.join(tarball))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| tarball.to_string());
+
+    let tarball_path_buf = snm_config.get_download_dir_path_buf().join(format!(
+        "{}-{}-{}.tgz",
+        name,
+        resolved.version,
+        std::process::id()
+    ));
+
+    let result = async {
+        DownloadBuilder::new()
+            .retries(2)
+            .timeout(None)
+            .write_strategy(WriteStrategy::WriteAfterDelete)
+            .download(&tarball_url, &tarball_path_buf)
+            .await?;
+        fs::read(&tarball_path_buf).map_err(SnmError::io("read", &tarball_path_buf))
+    }
+    .await;
+    let _ = fs::remove_file(&tarball_path_buf);
+    let bytes = result?;
+
+    verify(&bytes, &resolved.manifest, &tarball_url)?;
+
+    Ok(format!(
+        "{}@{}+sha512.{:x}",
+        name,
+        resolved.version,
+        Sha512::digest(&bytes)
+    ))
+}
+
+// yarn 2 以后发布在 @yarnpkg/cli-dist
+fn registry_package_vec(name: &str) -> Vec<&str> {
+    match name {
+        "yarn" => vec!["yarn", "@yarnpkg/cli-dist"],
+        _ => vec![name],
+    }
+}
+
+/// A dist-tag ( `latest`, `next` ... ) or the newest version matching the range.
+fn resolve_version(packument: &Value, range: &str) -> Result<Option<(Version, Value)>, SnmError> {
+    let Some(version_map) = packument.get("versions").and_then(Value::as_object) else {
+        return Ok(None);
+    };
+
+    let tag = if range.is_empty() { "latest" } else { range };
+    if let Some(tagged) = packument
+        .get("dist-tags")
+        .and_then(|dist_tags| dist_tags.get(tag))
+        .and_then(Value::as_str)
+    {
+        return Ok(version_map.get(tagged).and_then(|manifest| {
+            Version::parse(tagged)
+                .ok()
+                .map(|version| (version, manifest.clone()))
+        }));
+    }
+
+    let version_req = VersionReq::parse(range)
+        .map_err(|error| SnmError::Error(format!("Invalid version range {}: {}", range, error)))?;
+
+    Ok(version_map
+        .iter()
+        .filter_map(|(version, manifest)| {
+            Version::parse(version)
+                .ok()
+                .filter(|version| version_req.matches(version))
+                .map(|version| (version, manifest))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(version, manifest)| (version, manifest.clone())))
+}
+
+fn verify(bytes: &[u8], manifest: &Value, tarball_url: &str) -> Result<(), SnmError> {
+    let dist = manifest.get("dist");
+
+    let integrity = dist
+        .and_then(|dist| dist.get("integrity"))
+        .and_then(Value::as_str)
+        .and_then(|integrity| {
+            integrity
+                .split_whitespace()
+                .find_map(|hash| hash.strip_prefix("sha512-"))
+        });
+
+    let (expect, actual) = match integrity {
+        Some(expect) => (expect.to_string(), STANDARD.encode(Sha512::digest(bytes))),
+        None => {
+            let shasum = dist
+                .and_then(|dist| dist.get("shasum"))
+                .and_then(Value::as_str)
+                .ok_or(SnmError::Error(format!(
+                    "No integrity or shasum for {}",
+                    tarball_url
+                )))?;
+            (shasum.to_string(), format!("{:x}", Sha1::digest(bytes)))
+        }
+    };
+
+    if expect != actual {
+        return Err(SnmError::ChecksumMismatch {
+            file_path: tarball_url.to_string(),
+            expect,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+async fn get_json(url: &str) -> Result<Value, SnmError> {
+    let mut request = http_client()?.get(url);
+    if let Some(authorization) = Npmrc::load().get_authorization(url) {
+        request = request.header(AUTHORIZATION, authorization);
+    }
+
+    let response = request.send().await.map_err(SnmError::http(url))?;
+
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Err(SnmError::ResourceNotFound {
+            download_url: url.to_string(),
+        });
+    }
+    if !status.is_success() {
+        return Err(SnmError::Http {
+            url: url.to_string(),
+            status: Some(status.as_u16()),
+            message: status.to_string(),
+        });
+    }
+
+    let text = response.text().await.map_err(SnmError::http(url))?;
+    serde_json::from_str(&text).map_err(SnmError::invalid_response(url))
+}
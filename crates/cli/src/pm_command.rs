@@ -0,0 +1,15 @@
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum PmCommands {
+    /// Write the exact version and hash of a package manager to packageManager
+    Pin {
+        #[arg(help = "npm, pnpm, yarn or bun, optionally with a range or tag, e.g. pnpm@^9")]
+        spec: String,
+    },
+    /// Move packageManager to the newest version within a range
+    Upgrade {
+        #[arg(help = "Semver range, defaults to ^ of the current version, e.g. ^9 or latest")]
+        range: Option<String>,
+    },
+}
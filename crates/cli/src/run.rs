@@ -0,0 +1,244 @@
+use std::{
+    collections::HashSet,
+    env::{current_dir, set_current_dir},
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+use colored::*;
+use snm_core::{
+    model::{PackageJson, SnmError, Workspace, WorkspacePackage},
+    println_success,
+};
+
+use crate::ni::{
+    npm_args::NpmArgsTransform,
+    pnpm_args::PnpmArgsTransform,
+    trait_transform_args::{CommandArgsCreatorTrait, RunCommandArgs},
+};
+
+const PREFIX_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::BrightRed,
+];
+
+pub async fn run_impl(args: RunCommandArgs) -> Result<(), SnmError> {
+    let script = args
+        .args
+        .first()
+        .cloned()
+        .ok_or(SnmError::Error("Missing the script to run".to_string()))?;
+
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let workspace = Workspace::find(&dir)?.ok_or(SnmError::Error(format!(
+        "No pnpm-workspace.yaml or package.json workspaces found from {}",
+        dir.display()
+    )))?;
+
+    // 包管理器以 workspace 根目录的 packageManager 为准
+    set_current_dir(&workspace.root_dir).map_err(SnmError::io("enter", &workspace.root_dir))?;
+    let (bin, process_args) = get_runner(&args).await?;
+
+    let selected_vec: Vec<&WorkspacePackage> = workspace
+        .filter(&args.filter)?
+        .into_iter()
+        .filter(|package| {
+            package
+                .package_json
+                .scripts
+                .as_ref()
+                .is_some_and(|scripts| scripts.contains_key(&script))
+        })
+        .collect();
+
+    if selected_vec.is_empty() {
+        return Err(SnmError::Error(format!(
+            "None of the selected workspace packages has a {} script",
+            script
+        )));
+    }
+
+    // 没有 --topological 时遇到循环依赖也照常执行，只是不保证顺序
+    let ordered_vec = match workspace.topological_sort(&selected_vec) {
+        Ok(ordered_vec) => ordered_vec,
+        Err(error) if args.topological => return Err(error),
+        Err(_) => selected_vec,
+    };
+
+    let failed_vec = schedule(&workspace, &ordered_vec, &bin, &process_args, &args);
+
+    if !failed_vec.is_empty() {
+        return Err(SnmError::Error(format!(
+            "{} failed in {}",
+            script,
+            failed_vec.join(", ")
+        )));
+    }
+
+    println_success!("{} finished in {} packages", script, ordered_vec.len());
+    Ok(())
+}
+
+async fn get_runner(args: &RunCommandArgs) -> Result<(PathBuf, Vec<String>), SnmError> {
+    let run_args = RunCommandArgs::from_args(args.args.clone());
+
+    // yarn 不由 snm 管理，直接使用 PATH 中的 yarn
+    let is_yarn = PackageJson::from_here()
+        .ok()
+        .and_then(|package_json| package_json.resolve_package_manager().ok().flatten())
+        .is_some_and(|package_manager| package_manager.name == "yarn");
+
+    if is_yarn {
+        let mut process_args = vec!["run".to_string()];
+        process_args.extend(run_args.args);
+        println_success!("Use {}", "yarn".bright_green());
+        return Ok((PathBuf::from("yarn"), process_args));
+    }
+
+    let ((name, version), bin_path_buf) = crate::get_bin().await?;
+
+    let command_args_creator: Box<dyn CommandArgsCreatorTrait> = match name.as_str() {
+        "npm" => Box::new(NpmArgsTransform {}),
+        "pnpm" => Box::new(PnpmArgsTransform {}),
+        _ => return Err(SnmError::UnsupportedPackageManager { name, version }),
+    };
+
+    println_success!(
+        "Use {}. {}",
+        format!("{:<8}", &version).bright_green(),
+        format!("by {}", bin_path_buf.display()).bright_black()
+    );
+
+    Ok((
+        bin_path_buf,
+        command_args_creator.get_run_command(run_args)?,
+    ))
+}
+
+/// Runs `ordered_vec` with at most `--parallel` packages at a time and returns the
+/// names of the failed ones. Nothing new is started after a failure.
+fn schedule(
+    workspace: &Workspace,
+    ordered_vec: &[&WorkspacePackage],
+    bin: &Path,
+    process_args: &[String],
+    args: &RunCommandArgs,
+) -> Vec<String> {
+    let dependency_map = if args.topological {
+        workspace.internal_dependencies(ordered_vec)
+    } else {
+        Default::default()
+    };
+
+    let width = ordered_vec
+        .iter()
+        .map(|package| package.name.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut pending_vec: Vec<(usize, &WorkspacePackage)> =
+        ordered_vec.iter().copied().enumerate().collect();
+    let mut done_set: HashSet<&str> = HashSet::new();
+    let mut failed_vec = vec![];
+    let mut running = 0;
+
+    let (sender, receiver) = mpsc::channel::<(&str, bool)>();
+
+    thread::scope(|scope| loop {
+        while failed_vec.is_empty() && running < args.parallel.max(1) {
+            let ready_index = pending_vec.iter().position(|(_, package)| {
+                dependency_map
+                    .get(package.name.as_str())
+                    .is_none_or(|dependency_vec| {
+                        dependency_vec.iter().all(|name| done_set.contains(name))
+                    })
+            });
+
+            let Some(ready_index) = ready_index else {
+                break;
+            };
+
+            let (index, package) = pending_vec.remove(ready_index);
+            let prefix = format!("{:<width$} |", package.name, width = width)
+                .color(PREFIX_COLORS[index % PREFIX_COLORS.len()])
+                .to_string();
+            let sender = sender.clone();
+            running += 1;
+
+            scope.spawn(move || {
+                let success = run_package(package, bin, process_args, &prefix);
+                sender.send((package.name.as_str(), success)).ok();
+            });
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let Ok((name, success)) = receiver.recv() else {
+            break;
+        };
+        running -= 1;
+
+        if success {
+            done_set.insert(name);
+        } else {
+            failed_vec.push(name.to_string());
+        }
+    });
+
+    failed_vec
+}
+
+fn run_package(
+    package: &WorkspacePackage,
+    bin: &Path,
+    process_args: &[String],
+    prefix: &str,
+) -> bool {
+    let child = Command::new(bin)
+        .args(process_args)
+        .current_dir(&package.dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(error) => {
+            eprintln!("{} spawn {} failed: {}", prefix, bin.display(), error);
+            return false;
+        }
+    };
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(move || forward_lines(stdout, |line| println!("{} {}", prefix, line)));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(move || forward_lines(stderr, |line| eprintln!("{} {}", prefix, line)));
+        }
+    });
+
+    child.wait().is_ok_and(|status| status.success())
+}
+
+fn forward_lines(reader: impl Read, print: impl Fn(&str)) {
+    for line in BufReader::new(reader).lines() {
+        match line {
+            Ok(line) => print(&line),
+            Err(_) => break,
+        }
+    }
+}
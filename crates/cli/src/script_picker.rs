@@ -0,0 +1,179 @@
+use std::{collections::HashMap, env::current_dir, fs, io::IsTerminal, path::Path};
+
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use serde::{Deserialize, Serialize};
+use snm_core::{
+    config::SnmConfig,
+    model::{PackageJson, SnmError, Workspace},
+};
+
+use crate::{execute_command, ni::trait_transform_args::RunCommandArgs, run::run_impl};
+
+const MAX_RECENT: usize = 10;
+
+/// A script of the current package ( `package` is `None` ) or of a workspace package.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ScriptChoice {
+    package: Option<String>,
+    script: String,
+}
+
+struct ScriptItem {
+    choice: ScriptChoice,
+    relative_dir: Option<String>,
+    command: String,
+}
+
+impl ScriptItem {
+    fn label(&self) -> String {
+        match &self.choice.package {
+            Some(package) => format!("{} › {}", package, self.choice.script),
+            None => self.choice.script.clone(),
+        }
+    }
+}
+
+/// `snm run` without a script: pick one of the scripts of package.json and of the
+/// workspace packages, recently picked ones first. Without a terminal the scripts
+/// are only listed.
+pub async fn pick_script_impl() -> Result<(), SnmError> {
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
+    let workspace = Workspace::find(&dir)?;
+    let project_dir = workspace
+        .as_ref()
+        .map(|workspace| workspace.root_dir.clone())
+        .unwrap_or(dir.clone());
+
+    let mut item_vec = collect_items(&dir, workspace.as_ref())?;
+    if item_vec.is_empty() {
+        return Err(SnmError::Error(format!(
+            "No scripts found in {}",
+            dir.join("package.json").display()
+        )));
+    }
+
+    let history_file_path_buf = SnmConfig::new().get_run_history_file_path_buf();
+    let mut history = read_history(&history_file_path_buf);
+    let recent_vec = history
+        .entry(project_dir.display().to_string())
+        .or_default();
+    item_vec.sort_by_key(|item| {
+        recent_vec
+            .iter()
+            .position(|recent| *recent == item.choice)
+            .unwrap_or(MAX_RECENT)
+    });
+
+    let width = item_vec
+        .iter()
+        .map(|item| item.label().chars().count())
+        .max()
+        .unwrap_or(0);
+    let line_vec: Vec<String> = item_vec
+        .iter()
+        .map(|item| format!("{:<width$}  {}", item.label(), item.command, width = width))
+        .collect();
+
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        line_vec.iter().for_each(|line| println!("{}", line));
+        return Ok(());
+    }
+
+    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Pick a script to run")
+        .default(0)
+        .items(&line_vec)
+        .interact_opt()
+        .map_err(SnmError::prompt)?;
+
+    let Some(selection) = selection else {
+        return Ok(());
+    };
+    let item = &item_vec[selection];
+
+    recent_vec.retain(|recent| *recent != item.choice);
+    recent_vec.insert(0, item.choice.clone());
+    recent_vec.truncate(MAX_RECENT);
+    // 记录失败不影响执行
+    write_history(&history_file_path_buf, &history).ok();
+
+    let script_args = vec![item.choice.script.clone()];
+    match &item.relative_dir {
+        Some(relative_dir) => {
+            let mut args = RunCommandArgs::from_args(script_args);
+            args.filter = vec![glob::Pattern::escape(relative_dir)];
+            run_impl(args).await
+        }
+        None => {
+            execute_command(|creator| {
+                creator.get_run_command(RunCommandArgs::from_args(script_args))
+            })
+            .await
+        }
+    }
+}
+
+fn collect_items(dir: &Path, workspace: Option<&Workspace>) -> Result<Vec<ScriptItem>, SnmError> {
+    let mut item_vec = vec![];
+
+    let package_json_path_buf = dir.join("package.json");
+    if package_json_path_buf.is_file() {
+        let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
+        item_vec.extend(script_items(&package_json, None));
+    }
+
+    for package in workspace
+        .iter()
+        .flat_map(|workspace| &workspace.package_vec)
+    {
+        if package.dir == dir {
+            continue;
+        }
+        item_vec.extend(
+            script_items(&package.package_json, Some(&package.name))
+                .into_iter()
+                .map(|item| ScriptItem {
+                    relative_dir: Some(package.relative_dir.clone()),
+                    ..item
+                }),
+        );
+    }
+
+    Ok(item_vec)
+}
+
+fn script_items(package_json: &PackageJson, package: Option<&String>) -> Vec<ScriptItem> {
+    let mut item_vec: Vec<ScriptItem> = package_json
+        .scripts
+        .iter()
+        .flatten()
+        .map(|(script, command)| ScriptItem {
+            choice: ScriptChoice {
+                package: package.cloned(),
+                script: script.clone(),
+            },
+            relative_dir: None,
+            command: command.clone(),
+        })
+        .collect();
+    item_vec.sort_by(|a, b| a.choice.script.cmp(&b.choice.script));
+    item_vec
+}
+
+fn read_history(file_path_buf: &Path) -> HashMap<String, Vec<ScriptChoice>> {
+    fs::read_to_string(file_path_buf)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_history(
+    file_path_buf: &Path,
+    history: &HashMap<String, Vec<ScriptChoice>>,
+) -> Result<(), SnmError> {
+    if let Some(parent) = file_path_buf.parent() {
+        fs::create_dir_all(parent).map_err(SnmError::io("create", parent))?;
+    }
+    let content = serde_json::to_string_pretty(history).map_err(SnmError::json(file_path_buf))?;
+    fs::write(file_path_buf, content).map_err(SnmError::io("write", file_path_buf))
+}
@@ -0,0 +1,33 @@
+use clap::Subcommand;
+
+#[derive(Subcommand, Debug)]
+pub enum SelfCommands {
+    /// Update snm and its shims
+    Update {
+        #[arg(
+            long,
+            help = "Install this version instead of the latest of the channel"
+        )]
+        version: Option<String>,
+
+        #[arg(
+            long,
+            help = "Release channel, e.g. stable or beta. Defaults to SNM_RELEASE_CHANNEL or stable"
+        )]
+        channel: Option<String>,
+
+        #[arg(long, help = "Reinstall even if the version is already installed")]
+        force: bool,
+    },
+    /// Remove snm, its shims and the PATH lines added by install.sh
+    Uninstall {
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+
+        #[arg(
+            long,
+            help = "Also remove SNM_BASE_DIR with every installed node, npm and pnpm"
+        )]
+        purge: bool,
+    },
+}
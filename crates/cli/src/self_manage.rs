@@ -0,0 +1,493 @@
+use std::{
+    collections::HashMap,
+    env::{self, consts::EXE_SUFFIX},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+use dialoguer::Confirm;
+use flate2::read::GzDecoder;
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use snm_core::{
+    config::SnmConfig,
+    model::SnmError,
+    print_warning, println_success,
+    utils::{
+        download::{DownloadBuilder, WriteStrategy},
+        http::http_client,
+    },
+};
+
+const TARGET: &str = env!("SNM_TARGET");
+
+const BINARY_NAME_VEC: [&str; 6] = ["snm", "node", "npm", "npx", "pnpm", "pnpx"];
+
+/// `manifest.json` published next to the release archives.
+///
+/// ```json
+/// {
+///   "channels": { "stable": "0.2.0", "beta": "0.3.0-beta.1" },
+///   "releases": {
+///     "0.2.0": {
+///       "x86_64-unknown-linux-gnu": {
+///         "url": "v0.2.0/x86_64-unknown-linux-gnu.tar.gz",
+///         "sha256": "..."
+///       }
+///     }
+///   }
+/// }
+/// ```
+///
+/// `url` may be relative to the manifest. The manifest must come with
+/// `{manifest_url}.sig`, a hex encoded ed25519 signature made with the release key.
+#[derive(Debug, Deserialize)]
+struct ReleaseManifest {
+    channels: HashMap<String, String>,
+    releases: HashMap<String, HashMap<String, ReleaseAsset>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    url: String,
+    sha256: String,
+}
+
+pub async fn self_update_impl(
+    version: Option<String>,
+    channel: Option<String>,
+    force: bool,
+) -> Result<(), SnmError> {
+    let snm_config = SnmConfig::new();
+    let manifest_url = snm_config.get_release_manifest_url();
+
+    let manifest_text = get_text(&manifest_url).await?;
+
+    // 校验和来自 manifest，不验证签名的话整个更新就没有保障
+    let public_key = snm_config.get_release_public_key().ok_or_else(|| {
+        SnmError::Error(format!(
+            "This snm was built without a release public key, set {} to verify the release manifest",
+            "SNM_RELEASE_PUBLIC_KEY".bright_green()
+        ))
+    })?;
+    let signature_url = format!("{}.sig", manifest_url);
+    let signature = get_text(&signature_url).await?;
+    verify_signature(&public_key, &signature, manifest_text.as_bytes())?;
+
+    let manifest: ReleaseManifest =
+        serde_json::from_str(&manifest_text).map_err(SnmError::invalid_response(&manifest_url))?;
+
+    let target_version = match version {
+        Some(version) => version.trim_start_matches(['v', 'V']).to_string(),
+        None => {
+            let channel = channel.unwrap_or_else(|| snm_config.get_release_channel());
+            manifest.channels.get(&channel).cloned().ok_or_else(|| {
+                let mut channel_vec: Vec<&String> = manifest.channels.keys().collect();
+                channel_vec.sort();
+                SnmError::Error(format!(
+                    "Unknown release channel {}, available: {}",
+                    channel.bright_red(),
+                    channel_vec
+                        .iter()
+                        .map(|channel| channel.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })?
+        }
+    };
+
+    let current_version = env!("CARGO_PKG_VERSION");
+
+    if !force && is_same_version(current_version, &target_version) {
+        println_success!(
+            "snm {} is already installed",
+            current_version.bright_green()
+        );
+        return Ok(());
+    }
+
+    let asset = manifest
+        .releases
+        .get(&target_version)
+        .ok_or_else(|| {
+            SnmError::Error(format!(
+                "snm {} is not in the release manifest",
+                target_version.bright_red()
+            ))
+        })?
+        .get(TARGET)
+        .ok_or_else(|| {
+            SnmError::Error(format!(
+                "snm {} has no release for {}",
+                target_version.bright_red(),
+                TARGET
+            ))
+        })?;
+
+    let asset_url = reqwest::Url::parse(&manifest_url)
+        .and_then(|base| base.join(&asset.url))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| asset.url.to_string());
+
+    let install_dir_path_buf = get_install_dir_path_buf()?;
+
+    // 放在安装目录下，保证最后的 rename 在同一个文件系统上
+    let staging_dir_path_buf =
+        install_dir_path_buf.join(format!(".snm-update-{}", std::process::id()));
+
+    let result = async {
+        let archive_path_buf = staging_dir_path_buf.join(format!("{}.tar.gz", TARGET));
+
+        DownloadBuilder::new()
+            .retries(3)
+            .write_strategy(WriteStrategy::WriteAfterDelete)
+            .timeout(None)
+            .download(&asset_url, &archive_path_buf)
+            .await?;
+
+        let actual = sha256_file(&archive_path_buf)?;
+        if !actual.eq_ignore_ascii_case(asset.sha256.trim()) {
+            return Err(SnmError::ChecksumMismatch {
+                file_path: asset_url.to_string(),
+                expect: asset.sha256.to_string(),
+                actual,
+            });
+        }
+
+        let unpacked_dir_path_buf = staging_dir_path_buf.join("unpacked");
+        unpack(&archive_path_buf, &unpacked_dir_path_buf)?;
+
+        replace_binaries(&unpacked_dir_path_buf, &install_dir_path_buf)
+    }
+    .await;
+
+    let _ = fs::remove_dir_all(&staging_dir_path_buf);
+    result?;
+
+    println_success!(
+        "Updated snm {} -> {} {}",
+        current_version,
+        target_version.bright_green(),
+        format!("at {}", install_dir_path_buf.display()).bright_black()
+    );
+
+    Ok(())
+}
+
+pub fn self_uninstall_impl(yes: bool, purge: bool) -> Result<(), SnmError> {
+    let snm_config = SnmConfig::new();
+    let install_dir_path_buf = get_install_dir_path_buf()?;
+
+    if !yes {
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "🤔 Remove snm and its shims from {}{} ?",
+                install_dir_path_buf.display(),
+                if purge {
+                    format!(
+                        " and everything in {}",
+                        snm_config.get_base_dir_path_buf().display()
+                    )
+                } else {
+                    String::new()
+                }
+            ))
+            .interact()
+            .map_err(SnmError::prompt)?;
+
+        if !confirmed {
+            return Err(SnmError::SilentExit);
+        }
+    }
+
+    for rc_file_path_buf in get_rc_file_path_buf_vec() {
+        if remove_rc_lines(&rc_file_path_buf, &install_dir_path_buf)? {
+            println_success!("Removed snm from {}", rc_file_path_buf.display());
+        }
+    }
+
+    if purge {
+        let base_dir_path_buf = snm_config.get_base_dir_path_buf();
+        if base_dir_path_buf.exists() {
+            fs::remove_dir_all(&base_dir_path_buf)
+                .map_err(SnmError::io("remove dir", &base_dir_path_buf))?;
+        }
+    }
+
+    for name in BINARY_NAME_VEC {
+        let binary_path_buf = install_dir_path_buf.join(format!("{}{}", name, EXE_SUFFIX));
+        if binary_path_buf.exists() {
+            // windows 上正在运行的 snm.exe 删不掉，只能提示用户手动删除
+            if let Err(error) = fs::remove_file(&binary_path_buf) {
+                print_warning!(
+                    "Could not remove {}: {}, please delete it manually",
+                    binary_path_buf.display(),
+                    error
+                );
+            }
+        }
+    }
+
+    // 只在目录已经空了的时候才会成功
+    let _ = fs::remove_dir(&install_dir_path_buf);
+
+    println_success!("snm has been uninstalled, open a new terminal to refresh PATH");
+
+    Ok(())
+}
+
+fn get_install_dir_path_buf() -> Result<PathBuf, SnmError> {
+    let exe_path_buf = env::current_exe()
+        .and_then(|path_buf| path_buf.canonicalize())
+        .map_err(SnmError::io("get current exe", "snm"))?;
+
+    exe_path_buf
+        .parent()
+        .map(|parent| parent.to_path_buf())
+        .ok_or_else(|| SnmError::Error(format!("{} has no parent dir", exe_path_buf.display())))
+}
+
+async fn get_text(url: &str) -> Result<String, SnmError> {
+    let response = http_client()?
+        .get(url)
+        .send()
+        .await
+        .map_err(SnmError::http(url))?;
+
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Err(SnmError::ResourceNotFound {
+            download_url: url.to_string(),
+        });
+    }
+    if !status.is_success() {
+        return Err(SnmError::Http {
+            url: url.to_string(),
+            status: Some(status.as_u16()),
+            message: status.to_string(),
+        });
+    }
+
+    response.text().await.map_err(SnmError::http(url))
+}
+
+fn verify_signature(public_key: &str, signature: &str, message: &[u8]) -> Result<(), SnmError> {
+    let invalid = |message: &str| SnmError::Error(format!("Release manifest {}", message));
+
+    let public_key = decode_hex(public_key).ok_or_else(|| invalid("public key is not hex"))?;
+    let signature = decode_hex(signature).ok_or_else(|| invalid("signature is not hex"))?;
+
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+        .verify(message, &signature)
+        .map_err(|_| invalid("signature does not match SNM_RELEASE_PUBLIC_KEY"))
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(value.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+fn is_same_version(current: &str, target: &str) -> bool {
+    match (Version::parse(current), Version::parse(target)) {
+        (Ok(current), Ok(target)) => current == target,
+        _ => current == target,
+    }
+}
+
+fn sha256_file(path: &Path) -> Result<String, SnmError> {
+    let mut file = File::open(path).map_err(SnmError::io("open", path))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 8192];
+    loop {
+        let n = file.read(&mut buffer).map_err(SnmError::io("read", path))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn unpack(archive_path: &Path, output_path: &Path) -> Result<(), SnmError> {
+    let file = File::open(archive_path).map_err(SnmError::io("open", archive_path))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(output_path)
+        .map_err(SnmError::archive(archive_path))?;
+
+    let snm_path_buf = output_path.join(format!("snm{}", EXE_SUFFIX));
+    if !snm_path_buf.is_file() {
+        return Err(SnmError::Archive {
+            file_path: archive_path.display().to_string(),
+            message: format!("snm{} is missing", EXE_SUFFIX),
+        });
+    }
+
+    Ok(())
+}
+
+// 先准备好全部新文件，再把旧文件挪到一边换上新文件，任何一步失败都把已经换掉的恢复回去
+fn replace_binaries(from_dir: &Path, install_dir: &Path) -> Result<(), SnmError> {
+    let pid = std::process::id();
+
+    let mut staged_vec: Vec<(PathBuf, PathBuf)> = vec![];
+    for name in BINARY_NAME_VEC {
+        let file_name = format!("{}{}", name, EXE_SUFFIX);
+        let new_path_buf = from_dir.join(&file_name);
+        if !new_path_buf.is_file() {
+            continue;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&new_path_buf, fs::Permissions::from_mode(0o755))
+                .map_err(SnmError::io("chmod", &new_path_buf))?;
+        }
+
+        staged_vec.push((new_path_buf, install_dir.join(&file_name)));
+    }
+
+    let mut replaced_vec: Vec<(PathBuf, Option<PathBuf>)> = vec![];
+
+    let result = (|| {
+        for (new_path_buf, target_path_buf) in &staged_vec {
+            let backup_path_buf = if target_path_buf.exists() {
+                let file_name = target_path_buf.file_name().unwrap_or_default();
+                let backup_path_buf =
+                    install_dir.join(format!(".{}.old-{}", file_name.to_string_lossy(), pid));
+                fs::rename(target_path_buf, &backup_path_buf)
+                    .map_err(SnmError::io("move", target_path_buf))?;
+                Some(backup_path_buf)
+            } else {
+                None
+            };
+
+            replaced_vec.push((target_path_buf.clone(), backup_path_buf));
+
+            fs::rename(new_path_buf, target_path_buf)
+                .map_err(SnmError::io("move", new_path_buf))?;
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            for (_, backup_path_buf) in replaced_vec {
+                if let Some(backup_path_buf) = backup_path_buf {
+                    // windows 上运行中的 snm.exe 只能改名不能删除，留到下次
+                    let _ = fs::remove_file(backup_path_buf);
+                }
+            }
+            Ok(())
+        }
+        Err(error) => {
+            for (target_path_buf, backup_path_buf) in replaced_vec.into_iter().rev() {
+                let _ = fs::remove_file(&target_path_buf);
+                if let Some(backup_path_buf) = backup_path_buf {
+                    if fs::rename(&backup_path_buf, &target_path_buf).is_err() {
+                        print_warning!(
+                            "Could not restore {}, the previous version is kept at {}\n",
+                            target_path_buf.display(),
+                            backup_path_buf.display()
+                        );
+                    }
+                }
+            }
+            Err(error)
+        }
+    }
+}
+
+fn get_rc_file_path_buf_vec() -> Vec<PathBuf> {
+    let Some(home_dir) = dirs::home_dir() else {
+        return vec![];
+    };
+
+    let zsh_dir = env::var("ZDOTDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home_dir.clone());
+
+    vec![
+        zsh_dir.join(".zshrc"),
+        home_dir.join(".bashrc"),
+        home_dir.join(".bash_profile"),
+        home_dir.join(".profile"),
+    ]
+}
+
+/// Drop the `# snm` marker and the `export PATH=...` line install.sh appended, a
+/// line without the marker only when it is exactly the one install.sh writes.
+/// Returns whether the file changed.
+fn remove_rc_lines(rc_file_path: &Path, install_dir: &Path) -> Result<bool, SnmError> {
+    if !rc_file_path.is_file() {
+        return Ok(false);
+    }
+
+    let content = fs::read_to_string(rc_file_path).map_err(SnmError::io("read", rc_file_path))?;
+    let install_dir = install_dir.display().to_string();
+    // 没有标记时只删 install.sh 原样写入的那一行，避免误删 ~/.snm-tools 这类相似路径
+    let install_line = format!("export PATH=\"{}:$PATH\"", install_dir);
+    let is_install_line = |line: &str| line.trim() == install_line;
+    let is_snm_path_line = |line: &str| {
+        line.contains("export PATH=")
+            && line
+                .match_indices(install_dir.as_str())
+                .any(|(position, _)| {
+                    line[position + install_dir.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(|c| matches!(c, ':' | '"' | '\'' | '/'))
+                })
+    };
+
+    let line_vec: Vec<&str> = content.lines().collect();
+    let mut kept_vec: Vec<&str> = vec![];
+    let mut index = 0;
+
+    while index < line_vec.len() {
+        let line = line_vec[index];
+        let next_is_path = line_vec
+            .get(index + 1)
+            .is_some_and(|next| is_snm_path_line(next));
+
+        if line.trim() == "# snm" && next_is_path {
+            // install.sh 在标记前面还加了一个空行
+            if kept_vec.last().is_some_and(|last| last.trim().is_empty()) {
+                kept_vec.pop();
+            }
+            index += 2;
+            continue;
+        }
+
+        if is_install_line(line) {
+            index += 1;
+            continue;
+        }
+
+        kept_vec.push(line);
+        index += 1;
+    }
+
+    if kept_vec.len() == line_vec.len() {
+        return Ok(false);
+    }
+
+    let mut new_content = kept_vec.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+
+    fs::write(rc_file_path, new_content).map_err(SnmError::io("write", rc_file_path))?;
+
+    Ok(true)
+}
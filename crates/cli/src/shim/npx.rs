@@ -3,17 +3,21 @@ mod shim;
 use crate::shim::launch_shim;
 use shim::check;
 use snm_core::model::snm_error::handle_snm_error;
+use snm_node::snm_node::SnmNode;
 use snm_npm::snm_npm::SnmNpm;
 
 const BIN_NAME: &str = "npx";
 
-#[tokio::main]
-async fn main() {
+fn main() {
     env_logger::init();
 
-    match check(BIN_NAME) {
-        Ok(_) => {
-            launch_shim(Box::new(SnmNpm::new()), BIN_NAME).await;
+    match check(BIN_NAME).and_then(|_| SnmNpm::new().use_bundled_npm()) {
+        // node 自带的 npm，跟随 node 的版本解析
+        Ok(true) => {
+            launch_shim(Box::new(SnmNode::new()), BIN_NAME);
+        }
+        Ok(false) => {
+            launch_shim(Box::new(SnmNpm::new()), BIN_NAME);
         }
         Err(error) => handle_snm_error(error),
     }
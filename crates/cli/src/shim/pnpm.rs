@@ -5,13 +5,12 @@ use snm_pnpm::snm_pnpm::SnmPnpm;
 
 mod shim;
 const BIN_NAME: &str = "pnpm";
-#[tokio::main]
-async fn main() {
+fn main() {
     env_logger::init();
 
     match check("pnpm") {
         Ok(_) => {
-            launch_shim(Box::new(SnmPnpm::new()), BIN_NAME).await;
+            launch_shim(Box::new(SnmPnpm::new()), BIN_NAME);
         }
         Err(error) => handle_snm_error(error),
     }
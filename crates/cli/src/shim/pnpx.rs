@@ -7,13 +7,12 @@ use snm_pnpm::snm_pnpm::SnmPnpm;
 
 const BIN_NAME: &str = "pnpx";
 
-#[tokio::main]
-async fn main() {
+fn main() {
     env_logger::init();
 
     match check(BIN_NAME) {
         Ok(_) => {
-            launch_shim(Box::new(SnmPnpm::new()), BIN_NAME).await;
+            launch_shim(Box::new(SnmPnpm::new()), BIN_NAME);
         }
         Err(error) => handle_snm_error(error),
     }
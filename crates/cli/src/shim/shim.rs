@@ -12,9 +12,15 @@ use snm_core::{
     println_success,
 };
 
-pub async fn launch_shim(manager: Box<dyn ManageTrait>, bin_name: &str) {
+pub fn launch_shim(manager: Box<dyn ManageTrait>, bin_name: &str) {
+    let shim_trait = manager.get_shim_trait();
     let dispatcher = DispatchManage::new(manager);
-    match dispatcher.proxy_process(bin_name).await {
+    match dispatcher
+        .proxy_process(bin_name)
+        .and_then(|(v, bin_path_buf)| {
+            shim_trait.check_advisory(&v)?;
+            Ok((v, bin_path_buf))
+        }) {
         Ok((v, bin_path_buf)) => {
             println_success!(
                 "Use {:<8}. {}",
@@ -37,11 +43,13 @@ pub async fn launch_shim(manager: Box<dyn ManageTrait>, bin_name: &str) {
 }
 
 pub fn check(actual_package_manager: &str) -> Result<(), SnmError> {
-    let dir = current_dir().expect("get current dir failed");
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
     let package_json_path_buf = dir.join("package.json");
     if package_json_path_buf.exists() {
         let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
-        let package_manager = package_json.parse_package_manager()?;
+        let Some(package_manager) = package_json.resolve_package_manager()? else {
+            return Ok(());
+        };
         if package_manager.name != actual_package_manager {
             return Err(SnmError::NotMatchPackageManager {
                 expect: package_manager.name,
@@ -42,7 +42,7 @@ async fn exec() -> Result<(), SnmError> {
             Box::new(SnmYarnPkg::new())
         };
 
-        launch_shim(instance, BIN_NAME).await;
+        launch_shim(instance, BIN_NAME);
     } else {
         unimplemented!("yarn unimpl")
     }
@@ -1,11 +1,17 @@
 use clap::Subcommand;
 
 use crate::{
+    audit::AuditCommandArgs,
+    bump::BumpCommandArgs,
     manage_command::ManageCommands,
+    migrate::MigrateCommandArgs,
     ni::trait_transform_args::{
         AddCommandArgs, DeleteCommandArgs, DlxCommandArgs, ExecCommandArgs, InstallCommandArgs,
         RunCommandArgs, SetCacheArgs,
     },
+    node_command::NodeCommands,
+    pm_command::PmCommands,
+    self_command::SelfCommands,
 };
 
 #[derive(Subcommand, Debug)]
@@ -13,7 +19,7 @@ pub enum SnmCommands {
     #[command(about = "Manage node versions.")]
     Node {
         #[command(subcommand)]
-        command: ManageCommands,
+        command: NodeCommands,
     },
 
     #[command(about = "Manage npm versions.")]
@@ -40,8 +46,14 @@ pub enum SnmCommands {
     #[command(about = "Delete packages from node_modules and from the project's package.json.")]
     Delete(DeleteCommandArgs),
 
-    #[command(about = "bump version.")]
-    Bump,
+    #[command(about = "Bump the version of package.json.")]
+    Bump(BumpCommandArgs),
+
+    #[command(about = "Convert the lock file to another package manager.")]
+    Migrate(MigrateCommandArgs),
+
+    #[command(about = "Check the lock file against a local OSV advisory database.")]
+    Audit(AuditCommandArgs),
 
     #[command(
         about = "Fetches a package from the registry without installing it as a dependency, hotloads it, and runs whatever default command binary it exposes.."
@@ -60,4 +72,16 @@ pub enum SnmCommands {
 
     #[command(about = "write fig spec to autocomplete build directory.")]
     FigSpec,
+
+    #[command(about = "Pin or upgrade the packageManager of package.json.")]
+    Pm {
+        #[command(subcommand)]
+        command: PmCommands,
+    },
+
+    #[command(name = "self", about = "Update or uninstall snm itself.")]
+    SelfManage {
+        #[command(subcommand)]
+        command: SelfCommands,
+    },
 }
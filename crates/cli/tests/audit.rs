@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use snm_test_support::{Fixtures, MockServer};
+use tempfile::TempDir;
+
+const PACKAGE_LOCK: &str = r#"{
+  "name": "demo",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "demo",
+      "version": "1.0.0",
+      "dependencies": {
+        "ms": "^2.1.0"
+      },
+      "devDependencies": {
+        "debug": "2.6.8"
+      }
+    },
+    "node_modules/debug": {
+      "version": "2.6.8",
+      "dev": true,
+      "dependencies": {
+        "ms": "2.0.0"
+      }
+    },
+    "node_modules/debug/node_modules/ms": {
+      "version": "2.0.0",
+      "dev": true
+    },
+    "node_modules/ms": {
+      "version": "2.1.3"
+    }
+  }
+}
+"#;
+
+const ADVISORY_DB: &str = r#"[
+  {
+    "id": "GHSA-0000-ms",
+    "summary": "Inefficient regular expression in ms",
+    "affected": [
+      {
+        "package": { "ecosystem": "npm", "name": "ms" },
+        "ranges": [
+          { "type": "SEMVER", "events": [{ "introduced": "0" }, { "fixed": "2.0.1" }] }
+        ]
+      }
+    ],
+    "database_specific": { "severity": "HIGH" }
+  },
+  {
+    "id": "GHSA-0000-debug",
+    "summary": "Debug leaks",
+    "affected": [
+      { "package": { "ecosystem": "npm", "name": "debug" }, "versions": ["2.6.8"] }
+    ],
+    "database_specific": { "severity": "LOW" }
+  },
+  {
+    "id": "PYSEC-0000-ms",
+    "affected": [
+      {
+        "package": { "ecosystem": "PyPI", "name": "ms" },
+        "ranges": [{ "type": "ECOSYSTEM", "events": [{ "introduced": "0" }] }]
+      }
+    ]
+  }
+]
+"#;
+
+fn project() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("package.json"),
+        r#"{"name": "demo", "version": "1.0.0"}"#,
+    )
+    .unwrap();
+    fs::write(dir.path().join("package-lock.json"), PACKAGE_LOCK).unwrap();
+    fs::write(dir.path().join("advisories.json"), ADVISORY_DB).unwrap();
+    dir
+}
+
+fn audit(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snm"))
+        .arg("audit")
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("SNM_BASE_DIR", ".snm")
+        .env_remove("SNM_OUTPUT")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn report_vulnerable_paths_and_gate_on_severity() {
+    let dir = project();
+
+    let output = audit(dir.path(), &["--db", "advisories.json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(3), "{}", stdout);
+    assert!(stdout.contains("ms@2.0.0"), "{}", stdout);
+    assert!(stdout.contains("fixed in 2.0.1"), "{}", stdout);
+    assert!(
+        stdout.contains("demo > debug@2.6.8 > ms@2.0.0"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("debug@2.6.8"), "{}", stdout);
+    assert!(!stdout.contains("ms@2.1.3"), "{}", stdout);
+    assert!(!stdout.contains("PYSEC"), "{}", stdout);
+
+    let output = audit(
+        dir.path(),
+        &["--db", "advisories.json", "--audit-level", "critical"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = audit(dir.path(), &["--db", "advisories.json", "--omit-dev"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No known vulnerabilities"));
+}
+
+#[test]
+fn sync_into_the_cache() {
+    let server = MockServer::start(Fixtures::new().route("/npm/all.json", ADVISORY_DB));
+    let dir = project();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snm"))
+        .args(["audit", "--sync", "--output", "json"])
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .env("SNM_BASE_DIR", ".snm")
+        .env("SNM_AUDIT_DB_URL", format!("{}/npm/all.json", server.url()))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+
+    let finding_vec: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(finding_vec[0]["advisory"], "GHSA-0000-ms");
+    assert_eq!(finding_vec[0]["severity"], "high");
+    assert_eq!(finding_vec[1]["advisory"], "GHSA-0000-debug");
+
+    // 之后不带 --db 也能用缓存
+    let cached = fs::read_to_string(dir.path().join(".snm/advisory_db.json")).unwrap();
+    assert!(!cached.contains("PYSEC"), "{}", cached);
+    assert_eq!(audit(dir.path(), &[]).status.code(), Some(3));
+}
+
+#[test]
+fn missing_database() {
+    let dir = project();
+
+    let output = audit(dir.path(), &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("snm audit --sync"));
+}
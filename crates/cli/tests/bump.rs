@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+const PACKAGE_JSON: &str =
+    "{\n    \"name\": \"demo\",\n    \"version\": \"1.2.0-beta.3\",\n    \"private\": true\n}\n";
+
+fn bump(project: &TempDir, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snm"))
+        .arg("bump")
+        .args(args)
+        .current_dir(project.path())
+        .env("HOME", project.path())
+        .env("SNM_BASE_DIR", ".snm")
+        .output()
+        .unwrap()
+}
+
+fn project() -> TempDir {
+    let project = TempDir::new().unwrap();
+    fs::write(project.path().join("package.json"), PACKAGE_JSON).unwrap();
+    project
+}
+
+#[test]
+fn dry_run_prints_the_new_version() {
+    let project = project();
+
+    let output = bump(&project, &["prerelease", "--dry-run"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1.2.0-beta.4\n");
+    assert_eq!(
+        fs::read_to_string(project.path().join("package.json")).unwrap(),
+        PACKAGE_JSON
+    );
+}
+
+#[test]
+fn bump_writes_only_the_version() {
+    let project = project();
+
+    let output = bump(&project, &["preminor", "--preid", "rc", "--yes"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        fs::read_to_string(project.path().join("package.json")).unwrap(),
+        PACKAGE_JSON.replace("1.2.0-beta.3", "1.3.0-rc.0")
+    );
+}
+
+#[test]
+fn release_type_is_required_without_a_terminal() {
+    let project = project();
+
+    let output = bump(&project, &[]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Missing release type"));
+}
+
+fn git(project: &TempDir, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {:?}", args, output);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn git_project() -> TempDir {
+    let project = project();
+    fs::write(
+        project.path().join("package-lock.json"),
+        "{\n  \"name\": \"demo\",\n  \"version\": \"1.2.0-beta.3\",\n  \"lockfileVersion\": 3,\n  \"packages\": {\n    \"\": {\n      \"name\": \"demo\",\n      \"version\": \"1.2.0-beta.3\"\n    }\n  }\n}\n",
+    )
+    .unwrap();
+    git(&project, &["init", "-q"]);
+    git(&project, &["config", "user.name", "snm"]);
+    git(&project, &["config", "user.email", "snm@example.com"]);
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "init"]);
+    project
+}
+
+#[test]
+fn tag_commits_package_json_and_lock_file() {
+    let project = git_project();
+
+    let output = bump(&project, &["patch", "--tag", "-m", "release {version}"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        git(&project, &["log", "-1", "--format=%s"]),
+        "release 1.2.0"
+    );
+    assert_eq!(git(&project, &["cat-file", "-t", "v1.2.0"]), "tag");
+    assert_eq!(git(&project, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git(&project, &["show", "--name-only", "--format=", "HEAD"]),
+        "package-lock.json\npackage.json"
+    );
+    let lock = fs::read_to_string(project.path().join("package-lock.json")).unwrap();
+    assert_eq!(lock.matches("\"version\": \"1.2.0\"").count(), 2);
+}
+
+#[test]
+fn dirty_work_tree_is_refused() {
+    let project = git_project();
+    fs::write(project.path().join("package-lock.json"), "{}").unwrap();
+
+    let output = bump(&project, &["patch", "--commit"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("dirty: package-lock.json ,"));
+    assert_eq!(
+        fs::read_to_string(project.path().join("package.json")).unwrap(),
+        PACKAGE_JSON
+    );
+
+    let output = bump(&project, &["patch", "--commit", "--allow-dirty"]);
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn auto_recommends_from_commits_since_the_last_tag() {
+    let project = git_project();
+    assert!(bump(&project, &["minor", "--tag"]).status.success());
+
+    for (file, message) in [
+        ("a.js", "fix(cli): keep CRLF"),
+        ("README.md", "docs: typo"),
+        ("b.js", "feat: add --auto"),
+    ] {
+        fs::write(project.path().join(file), message).unwrap();
+        git(&project, &["add", file]);
+        git(&project, &["commit", "-q", "-m", message]);
+    }
+
+    let output = bump(&project, &["--auto", "--dry-run"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1.3.0\n");
+
+    let output = bump(&project, &["--auto", "--tag"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let changelog = fs::read_to_string(project.path().join("CHANGELOG.md")).unwrap();
+    assert!(
+        changelog.starts_with("# Changelog\n\n## 1.3.0 ("),
+        "{}",
+        changelog
+    );
+    assert!(changelog.contains("### Features\n\n* add --auto ("));
+    assert!(changelog.contains("### Bug Fixes\n\n* **cli:** keep CRLF ("));
+    assert!(!changelog.contains("typo"));
+    assert_eq!(git(&project, &["status", "--porcelain"]), "");
+
+    let output = bump(&project, &["--auto", "--dry-run"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Nothing to release"));
+}
+
+fn write(project: &TempDir, file: &str, content: &str) {
+    let path = project.path().join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn workspace_bumps_changed_packages_and_their_dependents() {
+    let project = TempDir::new().unwrap();
+    write(
+        &project,
+        "package.json",
+        "{\n  \"private\": true,\n  \"workspaces\": [\"packages/*\"]\n}\n",
+    );
+    write(
+        &project,
+        "packages/core/package.json",
+        "{\n  \"name\": \"@org/core\",\n  \"version\": \"1.2.0\"\n}\n",
+    );
+    write(
+        &project,
+        "packages/app/package.json",
+        "{\n  \"name\": \"@org/app\",\n  \"version\": \"0.1.0\",\n  \"dependencies\": {\n    \"@org/core\": \"^1.2.0\",\n    \"@org/utils\": \"workspace:^\"\n  }\n}\n",
+    );
+    write(
+        &project,
+        "packages/utils/package.json",
+        "{\n  \"name\": \"@org/utils\",\n  \"version\": \"2.0.0\",\n  \"devDependencies\": {\n    \"@org/core\": \"workspace:~1.2.0\"\n  }\n}\n",
+    );
+    git(&project, &["init", "-q"]);
+    git(&project, &["config", "user.name", "snm"]);
+    git(&project, &["config", "user.email", "snm@example.com"]);
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "init"]);
+    git(&project, &["tag", "v0.0.0"]);
+
+    write(&project, "packages/core/index.js", "");
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "feat(core): index"]);
+
+    let output = bump(&project, &["--workspace", "--auto", "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("@org/core"), "{}", stdout);
+    assert!(stdout.contains("workspace:~1.3.0"), "{}", stdout);
+    assert!(!stdout.contains("0.1.0"), "{}", stdout);
+
+    let output = bump(&project, &["--workspace", "--auto", "--tag"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(
+        fs::read_to_string(project.path().join("packages/core/package.json")).unwrap(),
+        "{\n  \"name\": \"@org/core\",\n  \"version\": \"1.3.0\"\n}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(project.path().join("packages/app/package.json")).unwrap(),
+        "{\n  \"name\": \"@org/app\",\n  \"version\": \"0.1.0\",\n  \"dependencies\": {\n    \"@org/core\": \"^1.3.0\",\n    \"@org/utils\": \"workspace:^\"\n  }\n}\n"
+    );
+    assert!(
+        fs::read_to_string(project.path().join("packages/utils/package.json"))
+            .unwrap()
+            .contains("\"@org/core\": \"workspace:~1.3.0\"")
+    );
+    assert!(project.path().join("packages/core/CHANGELOG.md").exists());
+    assert_eq!(git(&project, &["cat-file", "-t", "@org/core@1.3.0"]), "tag");
+    assert_eq!(
+        git(&project, &["log", "-1", "--format=%s"]),
+        "chore(release): publish @org/core@1.3.0"
+    );
+    assert_eq!(git(&project, &["status", "--porcelain"]), "");
+
+    let output = bump(&project, &["--workspace", "--auto", "--yes"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No workspace package changed"));
+
+    write(&project, "packages/utils/index.js", "");
+    git(&project, &["add", "."]);
+    git(&project, &["commit", "-q", "-m", "fix(utils): index"]);
+    let output = bump(
+        &project,
+        &[
+            "--workspace",
+            "patch",
+            "--commit",
+            "-m",
+            "release {version}",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        git(&project, &["log", "-1", "--format=%s"]),
+        "release 0.1.1, 2.0.1"
+    );
+}
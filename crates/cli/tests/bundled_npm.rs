@@ -0,0 +1,56 @@
+use std::{
+    env, fs,
+    process::{Command, Output},
+};
+
+use snm_core::model::dispatch_manage::DispatchManage;
+use snm_node::snm_node::SnmNode;
+use snm_test_support::{block_on, Fixtures, TestEnv};
+
+fn run(env: &TestEnv, program: &str, args: &[&str]) -> Output {
+    Command::new(program)
+        .args(args)
+        .current_dir(env.project_dir())
+        .output()
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn npm_shim_runs_the_npm_bundled_with_node() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1").npm_package("npm", "9.9.2"));
+    env::set_var("SNM_BUNDLED_NPM", "true");
+    env::set_var("SNM_NODE_ADVISORY", "off");
+
+    let node_dispatcher = DispatchManage::new(Box::new(SnmNode::new()));
+    block_on(node_dispatcher.install("20.11.1")).unwrap();
+    block_on(node_dispatcher.set_default("20.11.1")).unwrap();
+
+    // 没有 package.json 也没有默认 npm
+    let output = run(&env, env!("CARGO_BIN_EXE_npm"), &["--version"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout.contains("node_bin/20.11.1/bin/npm"), "{}", stdout);
+    assert!(stdout.contains("10.2.4"), "{}", stdout);
+
+    // packageManager 指定了 npm 时仍然用指定的版本
+    env::set_var("SNM_STRICT", "true");
+    fs::write(
+        env.project_dir().join("package.json"),
+        r#"{ "name": "fixture", "packageManager": "npm@9.9.2" }"#,
+    )
+    .unwrap();
+    let output = run(&env, env!("CARGO_BIN_EXE_npm"), &["--version"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("node_modules/npm/9.9.2"), "{}", stdout);
+
+    env::set_var("SNM_STRICT", "false");
+    let output = run(
+        &env,
+        env!("CARGO_BIN_EXE_snm"),
+        &["npm", "list", "--output", "plain"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("9.9.2\n"), "{}", stdout);
+    assert!(stdout.contains("10.2.4\tbundled 20.11.1"), "{}", stdout);
+}
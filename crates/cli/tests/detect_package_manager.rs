@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+fn npm_shim(project: &TempDir, write_package_manager: bool) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_npm"))
+        .arg("--version")
+        .current_dir(project.path())
+        .env("HOME", project.path())
+        .env("SNM_BASE_DIR", ".snm")
+        .env(
+            "SNM_WRITE_PACKAGE_MANAGER",
+            write_package_manager.to_string(),
+        )
+        .output()
+        .unwrap()
+}
+
+fn yarn_project() -> TempDir {
+    let project = TempDir::new().unwrap();
+    fs::write(
+        project.path().join("package.json"),
+        "{\n  \"name\": \"demo\"\n}\n",
+    )
+    .unwrap();
+    fs::write(project.path().join("yarn.lock"), "# yarn lockfile v1\n").unwrap();
+    project
+}
+
+#[test]
+fn lock_file_decides_the_package_manager() {
+    let project = yarn_project();
+
+    let output = npm_shim(&project, false);
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("configured to use yarn"), "{}", stdout);
+    assert_eq!(
+        fs::read_to_string(project.path().join("package.json")).unwrap(),
+        "{\n  \"name\": \"demo\"\n}\n"
+    );
+}
+
+#[test]
+fn inferred_package_manager_is_written_back_on_demand() {
+    let project = yarn_project();
+
+    npm_shim(&project, true);
+
+    assert_eq!(
+        fs::read_to_string(project.path().join("package.json")).unwrap(),
+        "{\n  \"name\": \"demo\",\n  \"packageManager\": \"yarn@1.22.22\"\n}\n"
+    );
+}
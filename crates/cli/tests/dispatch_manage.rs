@@ -0,0 +1,297 @@
+use std::process::Command;
+
+use snm_core::model::{dispatch_manage::DispatchManage, SnmError};
+use snm_node::snm_node::SnmNode;
+use snm_npm::snm_npm::SnmNpm;
+use snm_test_support::{block_on, fixtures::node_file_name, Fixtures, MockServer, TestEnv};
+
+fn node_dispatcher() -> DispatchManage {
+    DispatchManage::new(Box::new(SnmNode::new()))
+}
+
+fn npm_dispatcher() -> DispatchManage {
+    DispatchManage::new(Box::new(SnmNpm::new()))
+}
+
+// a url nothing listens on
+fn dead_url() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+#[test]
+fn install_node_extracts_into_runtime_dir() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1"));
+
+    block_on(node_dispatcher().install("20.11.1")).unwrap();
+
+    let runtime_dir = env.base_dir().join("node_bin").join("20.11.1");
+    assert!(runtime_dir.join("bin").join("node").exists());
+    assert!(runtime_dir.join("bin").join("npm").exists());
+
+    let download_dir = env.base_dir().join("download").join("20.11.1");
+    let leftover = std::fs::read_dir(download_dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .count();
+    assert_eq!(leftover, 0, "downloaded tarball should be removed");
+    assert!(env
+        .base_dir()
+        .join("download")
+        .join("node@20.11.1.lock")
+        .exists());
+
+    let staging = std::fs::read_dir(env.base_dir().join("node_bin"))
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with('.'))
+        .count();
+    assert_eq!(staging, 0, "staging dir should be renamed into place");
+}
+
+#[test]
+fn install_node_with_wrong_shasum_fails_without_activating() {
+    let env = TestEnv::new(Fixtures::new().node_with_bad_shasum("20.11.1"));
+
+    let result = block_on(node_dispatcher().install("20.11.1"));
+
+    assert!(matches!(result, Err(SnmError::ChecksumMismatch { .. })));
+    assert!(!env.base_dir().join("node_bin").join("20.11.1").exists());
+}
+
+#[test]
+fn install_missing_node_version_is_resource_not_found() {
+    let _env = TestEnv::new(Fixtures::new().node("20.11.1"));
+
+    let result = block_on(node_dispatcher().install("18.0.0"));
+
+    assert!(matches!(result, Err(SnmError::ResourceNotFound { .. })));
+}
+
+#[test]
+fn undecodable_node_index_is_an_invalid_response() {
+    let _env = TestEnv::new(
+        Fixtures::new()
+            .node("20.11.1")
+            .route("/dist/index.json", "<html>captive portal</html>"),
+    );
+
+    let result = block_on(SnmNode::new().get_node_list_remote());
+
+    let error = result.unwrap_err();
+    assert!(
+        matches!(error, SnmError::InvalidResponse { .. }),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.exit_code(), 65);
+}
+
+#[test]
+fn set_default_then_proxy_process_resolves_default() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1").node("18.19.0"));
+
+    block_on(node_dispatcher().install("20.11.1")).unwrap();
+    block_on(node_dispatcher().install("18.19.0")).unwrap();
+    block_on(node_dispatcher().set_default("18.19.0")).unwrap();
+
+    assert!(env
+        .base_dir()
+        .join("node_bin")
+        .join("18.19.0-default")
+        .exists());
+
+    let (version, binary) = node_dispatcher().proxy_process("node").unwrap();
+    assert_eq!(version, "18.19.0");
+    assert_eq!(
+        binary,
+        env.base_dir()
+            .join("node_bin")
+            .join("18.19.0")
+            .join("bin")
+            .join("node")
+    );
+
+    block_on(node_dispatcher().set_default("20.11.1")).unwrap();
+    let (version, _) = node_dispatcher().proxy_process("node").unwrap();
+    assert_eq!(version, "20.11.1");
+    assert!(!env
+        .base_dir()
+        .join("node_bin")
+        .join("18.19.0-default")
+        .exists());
+}
+
+#[test]
+fn proxy_process_without_default_fails() {
+    let _env = TestEnv::new(Fixtures::new().node("20.11.1"));
+
+    let result = node_dispatcher().proxy_process("node");
+
+    assert!(matches!(result, Err(SnmError::Error(_))));
+}
+
+#[test]
+fn un_install_removes_runtime_dir() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1").node("18.19.0"));
+
+    block_on(node_dispatcher().install("20.11.1")).unwrap();
+    block_on(node_dispatcher().install("18.19.0")).unwrap();
+    block_on(node_dispatcher().set_default("20.11.1")).unwrap();
+
+    block_on(node_dispatcher().un_install("18.19.0")).unwrap();
+
+    assert!(!env.base_dir().join("node_bin").join("18.19.0").exists());
+    assert!(env.base_dir().join("node_bin").join("20.11.1").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn strict_proxy_process_installs_node_version_file() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1"));
+    env.set_var("SNM_STRICT", "true");
+    env.write_project_file(".node-version", "v20.11.1\n");
+    env.enter_project();
+
+    let (version, binary) = node_dispatcher().proxy_process("node").unwrap();
+
+    assert_eq!(version, "20.11.1");
+    let output = Command::new(&binary).arg("--version").output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "v20.11.1");
+}
+
+#[test]
+fn strict_proxy_process_installs_package_manager() {
+    let env = TestEnv::new(Fixtures::new().npm_package("npm", "9.9.2"));
+    env.set_var("SNM_STRICT", "true");
+    env.write_project_file(
+        "package.json",
+        r#"{ "name": "fixture", "packageManager": "npm@9.9.2" }"#,
+    );
+    env.enter_project();
+
+    let (version, binary) = npm_dispatcher().proxy_process("npm").unwrap();
+
+    assert_eq!(version, "9.9.2");
+    assert!(binary.ends_with("bin/npm-cli.js"));
+    assert!(env
+        .base_dir()
+        .join("node_modules")
+        .join("npm")
+        .join("9.9.2")
+        .join("package.json")
+        .exists());
+}
+
+#[test]
+fn install_then_default_npm() {
+    let env = TestEnv::new(Fixtures::new().npm_package("npm", "9.9.2"));
+
+    block_on(npm_dispatcher().install("9.9.2")).unwrap();
+    block_on(npm_dispatcher().set_default("9.9.2")).unwrap();
+
+    let (version, binary) = npm_dispatcher().proxy_process("npm").unwrap();
+    assert_eq!(version, "9.9.2");
+    assert!(binary.starts_with(env.base_dir().canonicalize().unwrap()));
+}
+
+#[test]
+fn install_node_falls_back_to_mirror_when_primary_is_down() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1"));
+    let dead = dead_url();
+    let mirror = format!("{}/dist", env.server.url());
+    env.set_var(
+        "SNM_NODEJS_DIST_URL_KEY",
+        &format!("{}/dist,{}", dead, mirror),
+    );
+    env.set_var("SNM_NODEJS_SHASUM_URL_KEY", &mirror);
+
+    block_on(node_dispatcher().install("20.11.1")).unwrap();
+
+    assert!(env
+        .base_dir()
+        .join("node_bin")
+        .join("20.11.1")
+        .join("bin")
+        .join("node")
+        .exists());
+
+    let health = std::fs::read_to_string(env.base_dir().join("mirror_health.json")).unwrap();
+    assert!(health.contains(&dead));
+}
+
+#[test]
+fn install_node_from_tampered_mirror_fails_checksum() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.1"));
+    let tampered = MockServer::start(Fixtures::new().route(
+        &format!("/dist/v20.11.1/{}", node_file_name("20.11.1")),
+        "not a tarball",
+    ));
+    env.set_var(
+        "SNM_NODEJS_DIST_URL_KEY",
+        &format!("{}/dist,{}/dist", dead_url(), tampered.url()),
+    );
+    env.set_var(
+        "SNM_NODEJS_SHASUM_URL_KEY",
+        &format!("{}/dist", env.server.url()),
+    );
+
+    let result = block_on(node_dispatcher().install("20.11.1"));
+
+    assert!(matches!(result, Err(SnmError::ChecksumMismatch { .. })));
+    assert!(!env.base_dir().join("node_bin").join("20.11.1").exists());
+    assert!(!tampered
+        .requests()
+        .iter()
+        .any(|path| path.ends_with("SHASUMS256.txt")));
+}
+
+#[test]
+fn install_npm_from_private_registry_with_npmrc_token() {
+    let env = TestEnv::new(
+        Fixtures::new()
+            .npm_package("npm", "9.9.2")
+            .require_authorization("Bearer s3cret"),
+    );
+    let registry = env.server.url().trim_start_matches("http:").to_string();
+    env.remove_var("SNM_NPM_REGISTRY_HOST");
+    env.set_var("SNM_TEST_NPM_TOKEN", "s3cret");
+    env.write_project_file(
+        ".npmrc",
+        &format!(
+            "registry={}/\n{}/:_authToken=${{SNM_TEST_NPM_TOKEN}}\n",
+            env.server.url(),
+            registry
+        ),
+    );
+    env.enter_project();
+
+    block_on(npm_dispatcher().install("9.9.2")).unwrap();
+
+    assert!(env
+        .base_dir()
+        .join("node_modules")
+        .join("npm")
+        .join("9.9.2")
+        .join("package.json")
+        .exists());
+}
+
+#[test]
+fn install_npm_from_private_registry_without_token_is_rejected() {
+    let _env = TestEnv::new(
+        Fixtures::new()
+            .npm_package("npm", "9.9.2")
+            .require_authorization("Bearer s3cret"),
+    );
+
+    let result = block_on(npm_dispatcher().install("9.9.2"));
+
+    assert!(matches!(
+        result,
+        Err(SnmError::Http {
+            status: Some(401),
+            ..
+        })
+    ));
+}
@@ -0,0 +1,96 @@
+use std::fs;
+
+use snm_core::{config::SnmConfig, model::SnmError, utils::http::build_http_client};
+use snm_test_support::{block_on, Fixtures, MockServer, TestEnv};
+
+// nothing resolves .invalid, only a proxy can answer it
+const REMOTE_URL: &str = "http://registry.snm.invalid/ping";
+
+fn get_text(url: &str) -> Result<String, String> {
+    let client = build_http_client(&SnmConfig::new()).map_err(|error| error.to_string())?;
+    block_on(async {
+        client
+            .get(url)
+            .send()
+            .await
+            .map_err(|error| error.to_string())?
+            .text()
+            .await
+            .map_err(|error| error.to_string())
+    })
+}
+
+#[test]
+fn snm_proxy_routes_requests_through_the_proxy() {
+    let env = TestEnv::new(Fixtures::new().route(REMOTE_URL, "pong"));
+    env.set_var("SNM_PROXY", env.server.url());
+
+    assert_eq!(get_text(REMOTE_URL).unwrap(), "pong");
+    assert_eq!(env.server.requests(), vec![REMOTE_URL.to_string()]);
+}
+
+#[test]
+fn no_proxy_bypasses_snm_proxy() {
+    let env = TestEnv::new(Fixtures::new().route("/ping", "direct"));
+    let proxy = MockServer::start(Fixtures::new());
+    env.set_var("SNM_PROXY", proxy.url());
+    env.set_var("NO_PROXY", "127.0.0.1");
+
+    let result = get_text(&format!("{}/ping", env.server.url()));
+    env.remove_var("NO_PROXY");
+
+    assert_eq!(result.unwrap(), "direct");
+    assert!(proxy.requests().is_empty());
+}
+
+#[test]
+fn invalid_snm_proxy_is_a_configuration_error() {
+    let env = TestEnv::new(Fixtures::new());
+    env.set_var("SNM_PROXY", "not a url");
+
+    let result = build_http_client(&SnmConfig::new());
+
+    assert!(
+        matches!(&result, Err(SnmError::Error(message)) if message.contains("SNM_PROXY")),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn invalid_client_certificate_is_reported() {
+    let env = TestEnv::new(Fixtures::new());
+    let cert_path = env.write_project_file("client.pem", "not a certificate");
+    let key_path = env.write_project_file("client.key", "not a key");
+    env.set_var("SNM_CLIENT_CERT", &cert_path.display().to_string());
+    env.set_var("SNM_CLIENT_KEY", &key_path.display().to_string());
+
+    let result = build_http_client(&SnmConfig::new());
+
+    match result {
+        Err(SnmError::InvalidCertificate { file_path, .. }) => {
+            assert_eq!(file_path, cert_path.display().to_string())
+        }
+        other => panic!("expected InvalidCertificate, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn invalid_ca_bundle_is_reported() {
+    let env = TestEnv::new(Fixtures::new());
+    let bundle_path = env.write_project_file("ca.pem", "");
+    fs::write(
+        &bundle_path,
+        "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydA==\n-----END CERTIFICATE-----\n",
+    )
+    .unwrap();
+    env.set_var("SNM_CA_BUNDLE", &bundle_path.display().to_string());
+
+    let result = build_http_client(&SnmConfig::new());
+
+    assert!(
+        matches!(result, Err(SnmError::InvalidCertificate { .. })),
+        "{:?}",
+        result.err()
+    );
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+const PACKAGE_LOCK: &str = r#"{
+  "name": "demo",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "demo",
+      "version": "1.0.0",
+      "workspaces": ["packages/*"],
+      "dependencies": {
+        "ms": "^2.1.0"
+      }
+    },
+    "node_modules/@demo/core": {
+      "resolved": "packages/core",
+      "link": true
+    },
+    "node_modules/ms": {
+      "version": "2.1.3",
+      "resolved": "https://registry.npmjs.org/ms/-/ms-2.1.3.tgz",
+      "integrity": "sha512-6FlzubTLZG3J2a/NVCAleEhjzq5oxgHyaCU9yYXvcLsvoVaHJq/s5xXI6/XXP6tz7R9xAOtHnSO/tXtF3WRTlg=="
+    },
+    "packages/core": {
+      "name": "@demo/core",
+      "version": "0.1.0",
+      "dependencies": {
+        "ms": "^2.0.0"
+      }
+    }
+  }
+}
+"#;
+
+fn project() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("package.json"),
+        "{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\",\n  \"workspaces\": [\"packages/*\"],\n  \"dependencies\": {\n    \"ms\": \"^2.1.0\"\n  }\n}\n",
+    )
+    .unwrap();
+    fs::create_dir_all(dir.path().join("packages/core")).unwrap();
+    fs::write(
+        dir.path().join("packages/core/package.json"),
+        "{\n  \"name\": \"@demo/core\",\n  \"version\": \"0.1.0\",\n  \"dependencies\": {\n    \"ms\": \"^2.0.0\"\n  }\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("package-lock.json"), PACKAGE_LOCK).unwrap();
+    dir
+}
+
+fn migrate(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snm"))
+        .arg("migrate")
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("SNM_BASE_DIR", ".snm")
+        .output()
+        .unwrap()
+}
+
+fn read(dir: &Path, file: &str) -> String {
+    fs::read_to_string(dir.join(file)).unwrap()
+}
+
+#[test]
+fn npm_to_pnpm_and_back() {
+    let dir = project();
+
+    let output = migrate(dir.path(), &["--to", "pnpm"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    assert!(!dir.path().join("package-lock.json").exists());
+    let pnpm_lock = read(dir.path(), "pnpm-lock.yaml");
+    assert!(
+        pnpm_lock.contains("lockfileVersion: '9.0'"),
+        "{}",
+        pnpm_lock
+    );
+    assert!(pnpm_lock.contains("  packages/core:\n"), "{}", pnpm_lock);
+    assert!(pnpm_lock.contains("  ms@2.1.3:\n"), "{}", pnpm_lock);
+    assert_eq!(
+        read(dir.path(), "pnpm-workspace.yaml"),
+        "packages:\n  - 'packages/*'\n"
+    );
+    assert!(read(dir.path(), "package.json").contains("\"packageManager\": \"pnpm@9.15.4\""));
+
+    // packageManager 已经指向别的包管理器时会被改掉
+    let output = migrate(dir.path(), &["--to", "npm"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let package_lock = read(dir.path(), "package-lock.json");
+    assert!(
+        package_lock.contains("\"node_modules/ms\""),
+        "{}",
+        package_lock
+    );
+    assert!(
+        package_lock.contains("https://registry.npmjs.org/ms/-/ms-2.1.3.tgz"),
+        "{}",
+        package_lock
+    );
+    assert!(read(dir.path(), "package.json").contains("\"packageManager\": \"npm@10.9.2\""));
+}
+
+#[test]
+fn npm_to_yarn() {
+    let dir = project();
+
+    let output = migrate(dir.path(), &["--to", "yarn"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let yarn_lock = read(dir.path(), "yarn.lock");
+    assert!(
+        yarn_lock.contains("\nms@^2.0.0, ms@^2.1.0:\n  version \"2.1.3\"\n"),
+        "{}",
+        yarn_lock
+    );
+    assert!(read(dir.path(), "package.json").contains("\"packageManager\": \"yarn@1.22.22\""));
+}
+
+#[test]
+fn nothing_to_migrate() {
+    let dir = project();
+    fs::remove_file(dir.path().join("package-lock.json")).unwrap();
+
+    let output = migrate(dir.path(), &["--to", "pnpm"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No lock file to migrate"));
+}
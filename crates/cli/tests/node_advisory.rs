@@ -0,0 +1,89 @@
+use std::env;
+
+use snm_core::model::{trait_shim::ShimTrait, SnmError};
+use snm_node::snm_node::SnmNode;
+use snm_test_support::{Fixtures, TestEnv};
+
+const INDEX: &str = r#"[
+  {"version": "v16.20.2", "date": "2023-08-08", "files": [], "v8": "9.4", "lts": "Gallium", "security": true},
+  {"version": "v20.11.1", "date": "2024-02-14", "files": [], "v8": "11.3", "lts": "Iron", "security": true},
+  {"version": "v20.11.0", "date": "2024-01-09", "files": [], "v8": "11.3", "lts": "Iron", "security": false}
+]"#;
+
+const SCHEDULE: &str = r#"{
+  "v16": {"start": "2021-04-20", "lts": "2021-10-26", "maintenance": "2022-10-18", "end": "2023-09-11", "codename": "Gallium"},
+  "v20": {"start": "2023-04-18", "lts": "2023-10-24", "maintenance": "2098-01-01", "end": "2099-01-01", "codename": "Iron"}
+}"#;
+
+fn advisory_env() -> TestEnv {
+    TestEnv::new(
+        Fixtures::new()
+            .route("/dist/index.json", INDEX)
+            .route("/nodejs/Release/main/schedule.json", SCHEDULE),
+    )
+}
+
+fn error_message(result: Result<(), SnmError>) -> String {
+    match result {
+        Err(SnmError::Error(message)) => message,
+        other => panic!("expected an advisory error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn error_mode_fails_on_end_of_life_and_security_releases() {
+    let env = advisory_env();
+    env::set_var("SNM_NODE_ADVISORY", "error");
+
+    let message = error_message(SnmNode::new().check_advisory("16.20.2"));
+    assert!(message.contains("end-of-life on 2023-09-11"), "{}", message);
+
+    let message = error_message(SnmNode::new().check_advisory("20.11.0"));
+    assert!(message.contains("20.11.1"), "{}", message);
+
+    assert!(SnmNode::new().check_advisory("20.11.1").is_ok());
+    assert!(env.base_dir().join("node_advisory.json").exists());
+}
+
+#[test]
+fn warn_and_off_modes_never_fail() {
+    let env = advisory_env();
+
+    // warn 模式下 shim 自己不访问网络，只在后台刷新
+    assert!(SnmNode::new().check_advisory("16.20.2").is_ok());
+    let cache = std::fs::read_to_string(env.base_dir().join("node_advisory.json")).unwrap();
+    assert!(cache.contains("\"release_vec\":[]"), "{}", cache);
+
+    env::set_var("SNM_NODE_ADVISORY", "off");
+    assert!(SnmNode::new().check_advisory("16.20.2").is_ok());
+}
+
+#[test]
+fn cached_data_is_used_within_the_interval() {
+    let env = advisory_env();
+    env::set_var("SNM_NODE_ADVISORY", "error");
+    assert!(SnmNode::new().check_advisory("16.20.2").is_err());
+
+    // 服务端不可用时仍然使用缓存
+    env::set_var("SNM_NODEJS_DIST_URL_KEY", "http://127.0.0.1:9/dist");
+    env::set_var("SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY", "http://127.0.0.1:9");
+    assert!(SnmNode::new().check_advisory("16.20.2").is_err());
+    assert!(env.base_dir().join("node_advisory.json").exists());
+}
+
+#[test]
+fn error_mode_without_data_fails_and_retries() {
+    let env = advisory_env();
+    env::set_var("SNM_NODE_ADVISORY", "error");
+    env::set_var("SNM_NODEJS_DIST_URL_KEY", "http://127.0.0.1:9/dist");
+
+    let message = error_message(SnmNode::new().check_advisory("20.11.1"));
+    assert!(message.contains("snm node sync-advisory"), "{}", message);
+
+    env::set_var(
+        "SNM_NODEJS_DIST_URL_KEY",
+        format!("{}/dist", env.server.url()),
+    );
+    assert!(SnmNode::new().check_advisory("16.20.2").is_err());
+    assert!(SnmNode::new().check_advisory("20.11.1").is_ok());
+}
@@ -0,0 +1,85 @@
+use std::{fs, process::Command};
+
+use snm_core::model::dispatch_manage::DispatchManage;
+use snm_node::snm_node::SnmNode;
+use snm_test_support::{block_on, Fixtures, TestEnv};
+
+fn node_dispatcher() -> DispatchManage {
+    DispatchManage::new(Box::new(SnmNode::new()))
+}
+
+#[cfg(unix)]
+#[test]
+fn upgrade_moves_default_globals_and_node_version() {
+    let env = TestEnv::new(
+        Fixtures::new()
+            .node("20.11.0")
+            .node("20.12.2")
+            .node("22.1.0"),
+    );
+
+    block_on(node_dispatcher().install("20.11.0")).unwrap();
+    block_on(node_dispatcher().set_default("20.11.0")).unwrap();
+
+    let old_dir = env.base_dir().join("node_bin").join("20.11.0");
+    let cowsay_dir = old_dir.join("lib").join("node_modules").join("cowsay");
+    fs::create_dir_all(&cowsay_dir).unwrap();
+    fs::write(cowsay_dir.join("cli.js"), "#!/usr/bin/env node\n").unwrap();
+    std::os::unix::fs::symlink(
+        "../lib/node_modules/cowsay/cli.js",
+        old_dir.join("bin").join("cowsay"),
+    )
+    .unwrap();
+
+    fs::write(env.project_dir().join(".node-version"), "v20.11.0\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snm"))
+        .args(["node", "upgrade", "--yes"])
+        .current_dir(env.project_dir())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let node_bin = env.base_dir().join("node_bin");
+    assert!(node_bin.join("20.12.2").join("bin").join("node").exists());
+    assert!(node_bin.join("20.12.2-default").exists());
+    assert!(!node_bin.join("20.11.0").exists());
+    assert!(!node_bin.join("22.1.0").exists());
+
+    let new_dir = node_bin.join("20.12.2");
+    assert!(new_dir.join("lib/node_modules/cowsay/cli.js").exists());
+    assert_eq!(
+        fs::read_link(new_dir.join("bin").join("cowsay")).unwrap(),
+        std::path::Path::new("../lib/node_modules/cowsay/cli.js")
+    );
+    assert_eq!(
+        fs::read_to_string(env.project_dir().join(".node-version")).unwrap(),
+        "v20.12.2\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn upgrade_without_terminal_keeps_the_old_version() {
+    let env = TestEnv::new(Fixtures::new().node("20.11.0").node("20.12.2"));
+
+    block_on(node_dispatcher().install("20.11.0")).unwrap();
+    fs::write(env.project_dir().join(".node-version"), "20.11.0\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_snm"))
+        .args(["node", "upgrade"])
+        .current_dir(env.project_dir())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--yes"));
+
+    let node_bin = env.base_dir().join("node_bin");
+    assert!(node_bin.join("20.12.2").join("bin").join("node").exists());
+    assert!(node_bin.join("20.11.0").join("bin").join("node").exists());
+    assert_eq!(
+        fs::read_to_string(env.project_dir().join(".node-version")).unwrap(),
+        "20.11.0\n"
+    );
+}
@@ -0,0 +1,100 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use snm_test_support::{
+    fixtures::{npm_tarball, sha512_hex},
+    Fixtures, MockServer,
+};
+use tempfile::TempDir;
+
+fn pnpm_fixtures() -> Fixtures {
+    Fixtures::new()
+        .npm_package("pnpm", "9.1.0")
+        .npm_package("pnpm", "9.2.0")
+        .npm_package("pnpm", "10.0.0")
+}
+
+fn project(package_json: &str) -> TempDir {
+    let home = TempDir::new().unwrap();
+    fs::write(home.path().join("package.json"), package_json).unwrap();
+    home
+}
+
+fn snm_pm(home: &Path, server: &MockServer, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snm"))
+        .arg("pm")
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("SNM_BASE_DIR", ".snm")
+        .env("SNM_NPM_REGISTRY_HOST", server.url())
+        .env_remove("SNM_NPM_SHASUM_REGISTRY_HOST")
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .output()
+        .unwrap()
+}
+
+fn read_package_json(home: &Path) -> String {
+    fs::read_to_string(home.join("package.json")).unwrap()
+}
+
+fn pinned(version: &str) -> String {
+    format!(
+        "pnpm@{}+sha512.{}",
+        version,
+        sha512_hex(&npm_tarball("pnpm", version))
+    )
+}
+
+#[test]
+fn pin_writes_the_newest_matching_version_with_its_hash() {
+    let server = MockServer::start(pnpm_fixtures());
+    let home = project("{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\"\n}\n");
+
+    let output = snm_pm(home.path(), &server, &["pin", "pnpm@^9"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read_package_json(home.path()),
+        format!(
+            "{{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\",\n  \"packageManager\": \"{}\"\n}}\n",
+            pinned("9.2.0")
+        )
+    );
+}
+
+#[test]
+fn upgrade_stays_within_the_current_major() {
+    let server = MockServer::start(pnpm_fixtures());
+    let home = project("{\n  \"name\": \"demo\",\n  \"packageManager\": \"pnpm@9.1.0\"\n}\n");
+
+    let output = snm_pm(home.path(), &server, &["upgrade"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read_package_json(home.path()),
+        format!(
+            "{{\n  \"name\": \"demo\",\n  \"packageManager\": \"{}\"\n}}\n",
+            pinned("9.2.0")
+        )
+    );
+
+    let output = snm_pm(home.path(), &server, &["upgrade", ">=10"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(read_package_json(home.path()).contains(&pinned("10.0.0")));
+}
+
+#[test]
+fn pin_refuses_a_tampered_tarball() {
+    let server =
+        MockServer::start(pnpm_fixtures().route("/pnpm/-/pnpm-9.2.0.tgz", b"tampered".to_vec()));
+    let package_json = "{\n  \"name\": \"demo\"\n}\n";
+    let home = project(package_json);
+
+    let output = snm_pm(home.path(), &server, &["pin", "pnpm@^9"]);
+
+    assert_eq!(output.status.code(), Some(65), "{:?}", output);
+    assert_eq!(read_package_json(home.path()), package_json);
+}
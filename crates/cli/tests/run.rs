@@ -0,0 +1,148 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+// yarn 不由 snm 下载，用一个记录调用的假 yarn 代替
+const FAKE_YARN: &str = r#"#!/bin/sh
+echo "$(basename "$PWD") $*" >> "$SNM_TEST_RUN_LOG"
+echo "hello from $(basename "$PWD")"
+[ "$(basename "$PWD")" != "broken" ]
+"#;
+
+fn write_package(root: &Path, relative_dir: &str, content: &str) {
+    let dir = root.join(relative_dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("package.json"), content).unwrap();
+}
+
+fn monorepo() -> TempDir {
+    let root = TempDir::new().unwrap();
+    write_package(
+        root.path(),
+        "",
+        r#"{"name":"root","packageManager":"yarn@4.1.0","workspaces":["packages/*"]}"#,
+    );
+    write_package(
+        root.path(),
+        "packages/app",
+        r#"{"name":"app","scripts":{"build":"x"},"dependencies":{"ui":"workspace:*"}}"#,
+    );
+    write_package(
+        root.path(),
+        "packages/ui",
+        r#"{"name":"ui","scripts":{"build":"x"}}"#,
+    );
+    write_package(root.path(), "packages/docs", r#"{"name":"docs"}"#);
+
+    let bin_dir = root.path().join(".bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::write(bin_dir.join("yarn"), FAKE_YARN).unwrap();
+    fs::set_permissions(bin_dir.join("yarn"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    root
+}
+
+fn snm_run(root: &Path, cwd: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snm"))
+        .arg("run")
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", root)
+        .env("SNM_BASE_DIR", root.join(".snm"))
+        .env("SNM_TEST_RUN_LOG", root.join("run.log"))
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                root.join(".bin").display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        )
+        .output()
+        .unwrap()
+}
+
+fn run_log(root: &Path) -> Vec<String> {
+    fs::read_to_string(root.join("run.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn recursive_run_follows_the_dependency_graph() {
+    let root = monorepo();
+
+    let output = snm_run(
+        root.path(),
+        &root.path().join("packages/app"),
+        &["build", "--recursive", "--topological"],
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(run_log(root.path()), vec!["ui run build", "app run build"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("hello from app"), "{}", stdout);
+    assert!(stdout.contains("app |"), "{}", stdout);
+}
+
+#[test]
+fn filter_selects_packages_by_name() {
+    let root = monorepo();
+
+    let output = snm_run(root.path(), root.path(), &["build", "--filter", "u*"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(run_log(root.path()), vec!["ui run build"]);
+}
+
+#[test]
+fn failed_package_fails_the_run() {
+    let root = monorepo();
+    write_package(
+        root.path(),
+        "packages/broken",
+        r#"{"name":"broken","scripts":{"build":"x"}}"#,
+    );
+
+    let output = snm_run(
+        root.path(),
+        root.path(),
+        &["build", "-r", "--parallel", "1"],
+    );
+
+    assert!(!output.status.success());
+    // broken 没有依赖，最先执行；失败之后不再启动新的包
+    assert_eq!(run_log(root.path()), vec!["broken run build"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("build failed in broken"));
+}
+
+#[test]
+fn run_without_script_lists_scripts_when_not_a_terminal() {
+    let root = monorepo();
+    write_package(
+        root.path(),
+        "",
+        r#"{"name":"root","workspaces":["packages/*"],"scripts":{"lint":"eslint ."}}"#,
+    );
+
+    let output = snm_run(root.path(), root.path(), &[]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line_vec: Vec<&str> = stdout.lines().map(str::trim_end).collect();
+    assert_eq!(
+        line_vec,
+        vec!["lint         eslint .", "app › build  x", "ui › build   x"]
+    );
+    assert!(run_log(root.path()).is_empty());
+}
@@ -0,0 +1,276 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde_json::json;
+use snm_test_support::{
+    fixtures::{sha256_hex, tar_gz},
+    Fixtures, MockServer,
+};
+use tempfile::TempDir;
+
+const TARGET: &str = env!("SNM_TARGET");
+
+fn release_key_pair() -> Ed25519KeyPair {
+    Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap()
+}
+
+struct Installation {
+    home: TempDir,
+    install_dir: PathBuf,
+}
+
+impl Installation {
+    // a copy of the freshly built snm plus an old shim, like install.sh leaves it
+    fn new() -> Self {
+        let home = TempDir::new().unwrap();
+        let install_dir = home.path().join("snm-bin");
+        fs::create_dir_all(&install_dir).unwrap();
+        fs::copy(env!("CARGO_BIN_EXE_snm"), install_dir.join("snm")).unwrap();
+        fs::write(install_dir.join("node"), "old node shim").unwrap();
+        Self { home, install_dir }
+    }
+
+    fn snm(&self, args: &[&str], manifest_url: &str) -> Output {
+        let public_key = hex(release_key_pair().public_key().as_ref());
+        self.snm_with_public_key(args, manifest_url, Some(&public_key))
+    }
+
+    fn snm_with_public_key(
+        &self,
+        args: &[&str],
+        manifest_url: &str,
+        public_key: Option<&str>,
+    ) -> Output {
+        Command::new(self.install_dir.join("snm"))
+            .args(args)
+            .env("HOME", self.home.path())
+            .env("SNM_BASE_DIR", self.home.path().join(".snm"))
+            .env("SNM_RELEASE_MANIFEST_URL", manifest_url)
+            .env("SNM_RELEASE_PUBLIC_KEY", public_key.unwrap_or(""))
+            .env_remove("SNM_RELEASE_CHANNEL")
+            .env_remove("ZDOTDIR")
+            .output()
+            .unwrap()
+    }
+
+    fn leftover_vec(&self) -> Vec<String> {
+        fs::read_dir(&self.install_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with('.'))
+            .collect()
+    }
+}
+
+fn release_archive(version: &str) -> Vec<u8> {
+    tar_gz(&[
+        (
+            "snm".to_string(),
+            format!("#!/bin/sh\necho snm {}\n", version),
+        ),
+        (
+            "node".to_string(),
+            format!("#!/bin/sh\necho node shim {}\n", version),
+        ),
+    ])
+}
+
+fn release_fixtures(archive: &[u8], sha256: &str) -> (Fixtures, String) {
+    let manifest = json!({
+        "channels": { "stable": "9.9.9", "beta": "10.0.0-beta.1" },
+        "releases": {
+            "9.9.9": {
+                TARGET: { "url": format!("v9.9.9/{}.tar.gz", TARGET), "sha256": sha256 }
+            },
+            "10.0.0-beta.1": {
+                TARGET: { "url": format!("v10.0.0-beta.1/{}.tar.gz", TARGET), "sha256": sha256_hex(&release_archive("10.0.0-beta.1")) }
+            }
+        }
+    })
+    .to_string();
+
+    let signature = hex(release_key_pair().sign(manifest.as_bytes()).as_ref());
+
+    let fixtures = Fixtures::new()
+        .route("/manifest.json", manifest.clone())
+        .route("/manifest.json.sig", signature)
+        .route(&format!("/v9.9.9/{}.tar.gz", TARGET), archive.to_vec())
+        .route(
+            &format!("/v10.0.0-beta.1/{}.tar.gz", TARGET),
+            release_archive("10.0.0-beta.1"),
+        );
+
+    (fixtures, manifest)
+}
+
+fn run(path: &Path) -> String {
+    let output = Command::new(path).output().unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn self_update_replaces_snm_and_shims() {
+    let installation = Installation::new();
+    let archive = release_archive("9.9.9");
+    let (fixtures, _) = release_fixtures(&archive, &sha256_hex(&archive));
+    let server = MockServer::start(fixtures);
+
+    let output = installation.snm(
+        &["self", "update"],
+        &format!("{}/manifest.json", server.url()),
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(run(&installation.install_dir.join("snm")), "snm 9.9.9");
+    assert_eq!(
+        run(&installation.install_dir.join("node")),
+        "node shim 9.9.9"
+    );
+    assert!(installation.leftover_vec().is_empty());
+}
+
+#[test]
+fn self_update_follows_the_channel() {
+    let installation = Installation::new();
+    let archive = release_archive("9.9.9");
+    let (fixtures, _) = release_fixtures(&archive, &sha256_hex(&archive));
+    let server = MockServer::start(fixtures);
+
+    let output = installation.snm(
+        &["self", "update", "--channel", "beta"],
+        &format!("{}/manifest.json", server.url()),
+    );
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        run(&installation.install_dir.join("snm")),
+        "snm 10.0.0-beta.1"
+    );
+}
+
+#[test]
+fn self_update_with_wrong_checksum_keeps_current_binaries() {
+    let installation = Installation::new();
+    let before = fs::read(installation.install_dir.join("snm")).unwrap();
+    let archive = release_archive("9.9.9");
+    let (fixtures, _) = release_fixtures(&archive, &"0".repeat(64));
+    let server = MockServer::start(fixtures);
+
+    let output = installation.snm(
+        &["self", "update"],
+        &format!("{}/manifest.json", server.url()),
+    );
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        fs::read(installation.install_dir.join("snm")).unwrap(),
+        before
+    );
+    assert_eq!(
+        fs::read_to_string(installation.install_dir.join("node")).unwrap(),
+        "old node shim"
+    );
+    assert!(installation.leftover_vec().is_empty());
+}
+
+#[test]
+fn self_update_checks_the_manifest_signature() {
+    let archive = release_archive("9.9.9");
+    let (fixtures, _) = release_fixtures(&archive, &sha256_hex(&archive));
+
+    let forged = MockServer::start(fixtures.route("/manifest.json.sig", "00".repeat(64)));
+    let installation = Installation::new();
+    let before = fs::read(installation.install_dir.join("snm")).unwrap();
+    let output = installation.snm(
+        &["self", "update"],
+        &format!("{}/manifest.json", forged.url()),
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        fs::read(installation.install_dir.join("snm")).unwrap(),
+        before
+    );
+}
+
+#[test]
+fn self_update_without_public_key_is_refused() {
+    let archive = release_archive("9.9.9");
+    let (fixtures, _) = release_fixtures(&archive, &sha256_hex(&archive));
+    let server = MockServer::start(fixtures);
+    let installation = Installation::new();
+    let before = fs::read(installation.install_dir.join("snm")).unwrap();
+
+    let output = installation.snm_with_public_key(
+        &["self", "update"],
+        &format!("{}/manifest.json", server.url()),
+        None,
+    );
+
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("SNM_RELEASE_PUBLIC_KEY"));
+    assert_eq!(
+        fs::read(installation.install_dir.join("snm")).unwrap(),
+        before
+    );
+    assert!(server
+        .requests()
+        .iter()
+        .all(|path| !path.ends_with(".tar.gz")));
+}
+
+#[test]
+fn self_uninstall_removes_shims_and_rc_lines() {
+    let installation = Installation::new();
+    let bashrc = installation.home.path().join(".bashrc");
+    fs::write(
+        &bashrc,
+        format!(
+            "alias ll='ls -l'\n\n# snm\nexport PATH=\"{}:$PATH\"\n",
+            installation.install_dir.canonicalize().unwrap().display()
+        ),
+    )
+    .unwrap();
+
+    let output = installation.snm(&["self", "uninstall", "--yes"], "http://127.0.0.1:1");
+
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!installation.install_dir.exists());
+    assert_eq!(fs::read_to_string(&bashrc).unwrap(), "alias ll='ls -l'\n");
+}
+
+#[test]
+fn self_uninstall_keeps_look_alike_path_lines() {
+    let installation = Installation::new();
+    let install_dir = installation.install_dir.canonicalize().unwrap();
+    let bashrc = installation.home.path().join(".bashrc");
+    let look_alike = format!(
+        "export PATH={}-tools/bin:$PATH\n# snm\nexport PATH=\"{}-tools:$PATH\"\n",
+        install_dir.display(),
+        install_dir.display()
+    );
+    fs::write(
+        &bashrc,
+        format!(
+            "{}export PATH=\"{}:$PATH\"\n",
+            look_alike,
+            install_dir.display()
+        ),
+    )
+    .unwrap();
+
+    let output = installation.snm(&["self", "uninstall", "--yes"], "http://127.0.0.1:1");
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read_to_string(&bashrc).unwrap(), look_alike);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,3 +1,5 @@
+pub use self::npmrc::Npmrc;
 pub use self::snm_config::SnmConfig;
 
+pub mod npmrc;
 pub mod snm_config;
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+static NPM_CONFIG_USERCONFIG: &str = "NPM_CONFIG_USERCONFIG";
+
+/// The subset of `.npmrc` snm needs to talk to a private registry: `registry`,
+/// `@scope:registry`, `_authToken`, `_auth` and `always-auth`, either global or
+/// scoped to a registry path like `//npm.example.com/repo/:_authToken`.
+///
+/// User config (`NPM_CONFIG_USERCONFIG` or `~/.npmrc`) is read first, the nearest
+/// `.npmrc` above the current dir overrides it.
+#[derive(Debug, Default, Clone)]
+pub struct Npmrc {
+    config_map: HashMap<String, String>,
+}
+
+impl Npmrc {
+    pub fn load() -> Self {
+        let mut npmrc = Self::default();
+
+        let user_config_path_buf = env::var(NPM_CONFIG_USERCONFIG)
+            .map(PathBuf::from)
+            .ok()
+            .or_else(|| dirs::home_dir().map(|home| home.join(".npmrc")));
+
+        if let Some(path_buf) = user_config_path_buf {
+            npmrc.merge_file(&path_buf);
+        }
+
+        if let Some(path_buf) = find_project_npmrc() {
+            npmrc.merge_file(&path_buf);
+        }
+
+        npmrc
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut npmrc = Self::default();
+        npmrc.merge(content);
+        npmrc
+    }
+
+    fn merge_file(&mut self, path: &Path) {
+        // 和 npm 一样，读不到的 .npmrc 直接忽略
+        if let Ok(content) = read_to_string(path) {
+            self.merge(&content);
+        }
+    }
+
+    fn merge(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim().trim_matches('"');
+                self.config_map
+                    .insert(key.trim().to_string(), expand_env(value));
+            }
+        }
+    }
+
+    /// `registry` for unscoped packages, `@scope:registry` for `@scope/name`.
+    pub fn get_registry(&self, package_name: &str) -> Option<String> {
+        let scoped = package_name
+            .split_once('/')
+            .filter(|(scope, _)| scope.starts_with('@'))
+            .and_then(|(scope, _)| self.config_map.get(&format!("{}:registry", scope)));
+
+        scoped
+            .or_else(|| self.config_map.get("registry"))
+            .map(|registry| registry.trim_end_matches('/').to_string())
+    }
+
+    /// Value of the `Authorization` header for `url`, if any credential applies.
+    ///
+    /// Credentials scoped to the longest matching registry path win. Global
+    /// credentials only go to the default registry, unless `always-auth` is set,
+    /// then they also go to tarball hosts other than the registry.
+    pub fn get_authorization(&self, url: &str) -> Option<String> {
+        let url_nerf_dart = nerf_dart(url)?;
+
+        let scoped = self
+            .config_map
+            .keys()
+            .filter_map(|key| {
+                key.strip_suffix(":_authToken")
+                    .or(key.strip_suffix(":_auth"))
+            })
+            .filter(|prefix| prefix.starts_with("//") && url_nerf_dart.starts_with(prefix))
+            .max_by_key(|prefix| prefix.len())
+            .and_then(|prefix| self.get_scoped_authorization(prefix));
+
+        if scoped.is_some() {
+            return scoped;
+        }
+
+        let to_default_registry = self
+            .get_registry("")
+            .and_then(|registry| nerf_dart(&registry))
+            .is_some_and(|registry| url_nerf_dart.starts_with(&registry));
+
+        if to_default_registry || self.get_always_auth(&url_nerf_dart) {
+            return self.get_scoped_authorization("");
+        }
+
+        None
+    }
+
+    fn get_scoped_authorization(&self, prefix: &str) -> Option<String> {
+        let key = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}:{}", prefix, name)
+            }
+        };
+
+        if let Some(token) = self.config_map.get(&key("_authToken")) {
+            return Some(format!("Bearer {}", token));
+        }
+
+        // _auth 已经是 base64(user:password)
+        self.config_map
+            .get(&key("_auth"))
+            .map(|auth| format!("Basic {}", auth))
+    }
+
+    fn get_always_auth(&self, url_nerf_dart: &str) -> bool {
+        let scoped = self
+            .config_map
+            .iter()
+            .filter_map(|(key, value)| Some((key.strip_suffix(":always-auth")?, value)))
+            .filter(|(prefix, _)| url_nerf_dart.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, value)| value);
+
+        scoped
+            .or_else(|| self.config_map.get("always-auth"))
+            .is_some_and(|value| value == "true")
+    }
+}
+
+fn find_project_npmrc() -> Option<PathBuf> {
+    let current_dir = env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(".npmrc"))
+        .find(|path_buf| path_buf.is_file())
+}
+
+/// `https://npm.example.com:8443/repo/pkg/-/pkg-1.0.0.tgz` -> `//npm.example.com:8443/repo/pkg/-/pkg-1.0.0.tgz`
+fn nerf_dart(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?;
+    let port = parsed
+        .port()
+        .map(|port| format!(":{}", port))
+        .unwrap_or_default();
+    let path = parsed.path();
+    let path = if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    };
+    Some(format!("//{}{}{}", host, port, path))
+}
+
+// `${NPM_TOKEN}` -> env value, missing vars expand to empty like npm does
+fn expand_env(value: &str) -> String {
+    let Ok(regex) = Regex::new(r"\$\{([^}]+)\}") else {
+        return value.to_string();
+    };
+    regex
+        .replace_all(value, |caps: &regex::Captures| {
+            env::var(&caps[1]).unwrap_or_default()
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_token_only_goes_to_its_registry() {
+        let npmrc = Npmrc::parse(
+            "registry=https://npm.example.com/repo/\n//npm.example.com/repo/:_authToken=secret\n",
+        );
+
+        assert_eq!(
+            npmrc.get_authorization("https://npm.example.com/repo/npm/-/npm-9.9.2.tgz"),
+            Some("Bearer secret".to_string())
+        );
+        assert_eq!(
+            npmrc.get_authorization("https://registry.npmjs.org/npm/-/npm-9.9.2.tgz"),
+            None
+        );
+    }
+
+    #[test]
+    fn longest_registry_path_wins() {
+        let npmrc = Npmrc::parse(
+            "//npm.example.com/:_authToken=outer\n//npm.example.com/private/:_authToken=inner\n",
+        );
+
+        assert_eq!(
+            npmrc.get_authorization("https://npm.example.com/private/npm"),
+            Some("Bearer inner".to_string())
+        );
+        assert_eq!(
+            npmrc.get_authorization("https://npm.example.com/public/npm"),
+            Some("Bearer outer".to_string())
+        );
+    }
+
+    #[test]
+    fn global_auth_needs_always_auth_outside_the_registry() {
+        let content = "registry=https://npm.example.com/\n_auth=dXNlcjpwYXNz\n";
+
+        let npmrc = Npmrc::parse(content);
+        assert_eq!(
+            npmrc.get_authorization("https://npm.example.com/npm"),
+            Some("Basic dXNlcjpwYXNz".to_string())
+        );
+        assert_eq!(
+            npmrc.get_authorization("https://cdn.example.com/npm.tgz"),
+            None
+        );
+
+        let npmrc = Npmrc::parse(&format!("{}always-auth=true\n", content));
+        assert_eq!(
+            npmrc.get_authorization("https://cdn.example.com/npm.tgz"),
+            Some("Basic dXNlcjpwYXNz".to_string())
+        );
+    }
+
+    #[test]
+    fn scoped_registry_and_env_expansion() {
+        env::set_var("SNM_NPMRC_TEST_TOKEN", "from-env");
+        let npmrc = Npmrc::parse(
+            "; comment\n@corp:registry=https://npm.corp.com/\n//npm.corp.com/:_authToken=${SNM_NPMRC_TEST_TOKEN}\n",
+        );
+
+        assert_eq!(
+            npmrc.get_registry("@corp/cli"),
+            Some("https://npm.corp.com".to_string())
+        );
+        assert_eq!(npmrc.get_registry("npm"), None);
+        assert_eq!(
+            npmrc.get_authorization("https://npm.corp.com/@corp/cli"),
+            Some("Bearer from-env".to_string())
+        );
+    }
+}
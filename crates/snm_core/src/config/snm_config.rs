@@ -1,5 +1,7 @@
-use crate::model::SnmError;
-use std::{env, fs::create_dir_all, path::PathBuf};
+use crate::config::npmrc::Npmrc;
+use crate::model::{output::OutputFormat, SnmError};
+use clap::ValueEnum;
+use std::{env, fs::create_dir_all, path::PathBuf, str::FromStr, sync::OnceLock, time::Duration};
 
 static SNM_BASE_DIR_KEY: &str = "SNM_BASE_DIR";
 
@@ -13,8 +15,47 @@ static SNM_YARN_REPO_HOST_KEY: &str = "SNM_YARN_REPO_HOST_KEY";
 static SNM_NODEJS_DIST_URL_KEY: &str = "SNM_NODEJS_DIST_URL_KEY";
 static SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY: &str = "SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY";
 
+// 校验和只从可信源获取，镜像不能削弱完整性校验
+static SNM_NODEJS_SHASUM_URL_KEY: &str = "SNM_NODEJS_SHASUM_URL_KEY";
+static SNM_NPM_SHASUM_REGISTRY_HOST_KEY: &str = "SNM_NPM_SHASUM_REGISTRY_HOST";
+
+static SNM_MIRROR_COOLDOWN: &str = "SNM_MIRROR_COOLDOWN";
+
+static SNM_AUDIT_DB_URL: &str = "SNM_AUDIT_DB_URL";
+
+static SNM_RELEASE_MANIFEST_URL: &str = "SNM_RELEASE_MANIFEST_URL";
+static SNM_RELEASE_CHANNEL: &str = "SNM_RELEASE_CHANNEL";
+static SNM_RELEASE_PUBLIC_KEY: &str = "SNM_RELEASE_PUBLIC_KEY";
+
+static SNM_PROXY: &str = "SNM_PROXY";
+static SNM_CA_BUNDLE: &str = "SNM_CA_BUNDLE";
+static SNM_CLIENT_CERT: &str = "SNM_CLIENT_CERT";
+static SNM_CLIENT_KEY: &str = "SNM_CLIENT_KEY";
+static SNM_USER_AGENT: &str = "SNM_USER_AGENT";
+
 static SNM_STRICT: &str = "SNM_STRICT";
 
+static SNM_WRITE_PACKAGE_MANAGER: &str = "SNM_WRITE_PACKAGE_MANAGER";
+
+static SNM_BUNDLED_NPM: &str = "SNM_BUNDLED_NPM";
+
+static SNM_BUMP_COMMIT_MESSAGE: &str = "SNM_BUMP_COMMIT_MESSAGE";
+static SNM_BUMP_WORKSPACE_COMMIT_MESSAGE: &str = "SNM_BUMP_WORKSPACE_COMMIT_MESSAGE";
+static SNM_BUMP_MINOR_TYPES: &str = "SNM_BUMP_MINOR_TYPES";
+static SNM_BUMP_PATCH_TYPES: &str = "SNM_BUMP_PATCH_TYPES";
+
+static SNM_OUTPUT: &str = "SNM_OUTPUT";
+// --output 只属于当前进程，不能写进环境变量泄漏给子进程
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+static SNM_INSTALL_LOCK_TIMEOUT: &str = "SNM_INSTALL_LOCK_TIMEOUT";
+
+static SNM_INSTALL_SMOKE_CHECK: &str = "SNM_INSTALL_SMOKE_CHECK";
+
+// advisory  off | warn | error
+static SNM_NODE_ADVISORY: &str = "SNM_NODE_ADVISORY";
+static SNM_NODE_ADVISORY_INTERVAL: &str = "SNM_NODE_ADVISORY_INTERVAL";
+
 // strategy  ask | panic | auto
 static SNM_NODE_INSTALL_STRATEGY: &str = "SNM_NODE_INSTALL_STRATEGY";
 
@@ -48,6 +89,25 @@ impl InstallStrategy {
     }
 }
 
+pub enum AdvisoryMode {
+    Off,
+    Warn,
+    Error,
+}
+
+impl FromStr for AdvisoryMode {
+    type Err = SnmError;
+
+    fn from_str(s: &str) -> Result<Self, SnmError> {
+        match s {
+            "off" => Ok(AdvisoryMode::Off),
+            "warn" => Ok(AdvisoryMode::Warn),
+            "error" => Ok(AdvisoryMode::Error),
+            _ => Err(SnmError::Error(format!("Unsupported advisory mode: {}", s))),
+        }
+    }
+}
+
 pub struct SnmConfig {}
 
 impl SnmConfig {
@@ -58,19 +118,18 @@ impl SnmConfig {
     pub fn init(&self) -> Result<(), SnmError> {
         self.init_strict();
 
-        self.create_dir_all(self.get_base_dir_path_buf());
-        self.create_dir_all(self.get_node_bin_dir_path_buf());
-        self.create_dir_all(self.get_download_dir_path_buf());
-        self.create_dir_all(self.get_node_modules_dir_path_buf());
+        self.create_dir_all(self.get_base_dir_path_buf())?;
+        self.create_dir_all(self.get_node_bin_dir_path_buf())?;
+        self.create_dir_all(self.get_download_dir_path_buf())?;
+        self.create_dir_all(self.get_node_modules_dir_path_buf())?;
 
         self.init_url_config();
 
         Ok(())
     }
 
-    fn create_dir_all(&self, path_buf: PathBuf) {
-        create_dir_all(&path_buf)
-            .expect(format!("create dir error {:?}", path_buf.display().to_string()).as_str())
+    fn create_dir_all(&self, path_buf: PathBuf) -> Result<(), SnmError> {
+        create_dir_all(&path_buf).map_err(SnmError::io("create dir", &path_buf))
     }
 
     pub fn get_strict(&self) -> bool {
@@ -78,6 +137,18 @@ impl SnmConfig {
         value.parse::<bool>().unwrap_or(false)
     }
 
+    /// Write the `packageManager` inferred from a lock file back into package.json.
+    pub fn get_write_package_manager(&self) -> bool {
+        let value = env::var(SNM_WRITE_PACKAGE_MANAGER).unwrap_or(false.to_string());
+        value.parse::<bool>().unwrap_or(false)
+    }
+
+    /// Run the npm that ships with node when packageManager does not pin npm.
+    pub fn get_bundled_npm(&self) -> bool {
+        let value = env::var(SNM_BUNDLED_NPM).unwrap_or(false.to_string());
+        value.parse::<bool>().unwrap_or(false)
+    }
+
     pub fn get_base_dir_path_buf(&self) -> PathBuf {
         let home_dir = dirs::home_dir().expect("get home dir error.");
         let base_dir_name = env::var(SNM_BASE_DIR_KEY).unwrap_or(".snm".to_string());
@@ -103,8 +174,34 @@ impl SnmConfig {
         base_dir.join(node_modules_dir_name)
     }
 
+    /// Primary npm registry, the first entry of `SNM_NPM_REGISTRY_HOST`.
     pub fn get_npm_registry_host(&self) -> String {
-        env::var(SNM_NPM_REGISTRY_HOST_KEY).unwrap_or("https://registry.npmjs.org".to_string())
+        self.first(self.get_npm_registry_host_vec())
+    }
+
+    pub fn get_npm_registry_host_vec(&self) -> Vec<String> {
+        self.get_package_registry_host_vec("")
+    }
+
+    /// Registries of `package_name`, in order of precedence: the `@scope:registry` of
+    /// `.npmrc`, `SNM_NPM_REGISTRY_HOST`, the `registry` of `.npmrc`, npmjs.org.
+    pub fn get_package_registry_host_vec(&self, package_name: &str) -> Vec<String> {
+        let npmrc = Npmrc::load();
+
+        let is_scoped = package_name.starts_with('@');
+        if is_scoped || env::var(SNM_NPM_REGISTRY_HOST_KEY).is_err() {
+            if let Some(registry) = npmrc.get_registry(package_name) {
+                return vec![registry];
+            }
+        }
+
+        self.url_vec(SNM_NPM_REGISTRY_HOST_KEY, "https://registry.npmjs.org")
+    }
+
+    pub fn get_npm_shasum_registry_host(&self, package_name: &str) -> String {
+        env::var(SNM_NPM_SHASUM_REGISTRY_HOST_KEY)
+            .map(|value| value.trim().trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| self.first(self.get_package_registry_host_vec(package_name)))
     }
 
     pub fn get_yarn_registry_host(&self) -> String {
@@ -115,13 +212,163 @@ impl SnmConfig {
         env::var(SNM_YARN_REPO_HOST_KEY).unwrap_or("https://repo.yarnpkg.com".to_string())
     }
 
+    /// Primary node dist url, the first entry of `SNM_NODEJS_DIST_URL_KEY`.
     pub fn get_nodejs_dist_url_prefix(&self) -> String {
-        env::var(SNM_NODEJS_DIST_URL_KEY).unwrap_or("https://nodejs.org/dist".to_string())
+        self.first(self.get_nodejs_dist_url_vec())
+    }
+
+    pub fn get_nodejs_dist_url_vec(&self) -> Vec<String> {
+        self.url_vec(SNM_NODEJS_DIST_URL_KEY, "https://nodejs.org/dist")
+    }
+
+    pub fn get_nodejs_shasum_url_prefix(&self) -> String {
+        env::var(SNM_NODEJS_SHASUM_URL_KEY)
+            .map(|value| value.trim().trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| self.get_nodejs_dist_url_prefix())
     }
 
     pub fn get_nodejs_github_resource_host(&self) -> String {
-        env::var(SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY)
-            .unwrap_or("https://raw.githubusercontent.com".to_string())
+        self.first(self.get_nodejs_github_resource_host_vec())
+    }
+
+    pub fn get_nodejs_github_resource_host_vec(&self) -> Vec<String> {
+        self.url_vec(
+            SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY,
+            "https://raw.githubusercontent.com",
+        )
+    }
+
+    pub fn get_mirror_health_file_path_buf(&self) -> PathBuf {
+        self.get_base_dir_path_buf().join("mirror_health.json")
+    }
+
+    pub fn get_run_history_file_path_buf(&self) -> PathBuf {
+        self.get_base_dir_path_buf().join("run_history.json")
+    }
+
+    /// node release index and schedule cached for the node shim advisories.
+    pub fn get_node_advisory_file_path_buf(&self) -> PathBuf {
+        self.get_base_dir_path_buf().join("node_advisory.json")
+    }
+
+    /// npm advisories synced by `snm audit --sync`.
+    pub fn get_audit_db_file_path_buf(&self) -> PathBuf {
+        self.get_base_dir_path_buf().join("advisory_db.json")
+    }
+
+    /// OSV export of the npm ecosystem, a zip of OSV JSON records or a JSON array.
+    pub fn get_audit_db_url(&self) -> String {
+        env::var(SNM_AUDIT_DB_URL)
+            .unwrap_or("https://osv-vulnerabilities.storage.googleapis.com/npm/all.zip".to_string())
+    }
+
+    /// Commit and tag message of `snm bump --commit`, `{version}` is replaced.
+    pub fn get_bump_commit_message(&self) -> String {
+        env::var(SNM_BUMP_COMMIT_MESSAGE).unwrap_or("chore(release): v{version}".to_string())
+    }
+
+    /// Commit and tag message of `snm bump --workspace --commit`, `{packages}` is
+    /// replaced with the bumped `name@version` list.
+    pub fn get_bump_workspace_commit_message(&self) -> String {
+        env::var(SNM_BUMP_WORKSPACE_COMMIT_MESSAGE)
+            .unwrap_or("chore(release): publish {packages}".to_string())
+    }
+
+    /// Conventional commit types that make `snm bump --auto` release a minor version.
+    pub fn get_bump_minor_type_vec(&self) -> Vec<String> {
+        split_type_vec(env::var(SNM_BUMP_MINOR_TYPES).unwrap_or("feat".to_string()))
+    }
+
+    /// Conventional commit types that make `snm bump --auto` release a patch version.
+    pub fn get_bump_patch_type_vec(&self) -> Vec<String> {
+        split_type_vec(env::var(SNM_BUMP_PATCH_TYPES).unwrap_or("fix,perf".to_string()))
+    }
+
+    pub fn get_mirror_cooldown(&self) -> Duration {
+        let value = env::var(SNM_MIRROR_COOLDOWN).unwrap_or("600".to_string());
+        Duration::from_secs(value.parse::<u64>().unwrap_or(600))
+    }
+
+    pub fn get_release_manifest_url(&self) -> String {
+        env::var(SNM_RELEASE_MANIFEST_URL).unwrap_or(
+            "https://github.com/sheinsight/snm/releases/latest/download/manifest.json".to_string(),
+        )
+    }
+
+    pub fn get_release_channel(&self) -> String {
+        env::var(SNM_RELEASE_CHANNEL).unwrap_or("stable".to_string())
+    }
+
+    /// Hex encoded ed25519 public key the release manifest is signed with, the one
+    /// baked in by release builds unless overridden.
+    pub fn get_release_public_key(&self) -> Option<String> {
+        env::var(SNM_RELEASE_PUBLIC_KEY)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .or(option_env!("SNM_RELEASE_PUBLIC_KEY").map(str::to_string))
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn get_proxy(&self) -> Option<String> {
+        env::var(SNM_PROXY).ok().filter(|value| !value.is_empty())
+    }
+
+    pub fn get_ca_bundle_path_buf(&self) -> Option<PathBuf> {
+        env::var(SNM_CA_BUNDLE)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// `(cert, key)`, only when both `SNM_CLIENT_CERT` and `SNM_CLIENT_KEY` are set.
+    pub fn get_client_cert_path_buf(&self) -> Option<(PathBuf, PathBuf)> {
+        let cert = env::var(SNM_CLIENT_CERT)
+            .ok()
+            .filter(|value| !value.is_empty())?;
+        let key = env::var(SNM_CLIENT_KEY)
+            .ok()
+            .filter(|value| !value.is_empty())?;
+        Some((PathBuf::from(cert), PathBuf::from(key)))
+    }
+
+    pub fn get_user_agent(&self) -> String {
+        env::var(SNM_USER_AGENT).unwrap_or_else(|_| format!("snm/{}", env!("CARGO_PKG_VERSION")))
+    }
+
+    pub fn get_output_format(&self) -> OutputFormat {
+        if let Some(format) = OUTPUT_FORMAT.get() {
+            return *format;
+        }
+        env::var(SNM_OUTPUT)
+            .ok()
+            .and_then(|value| OutputFormat::from_str(&value, true).ok())
+            .unwrap_or(OutputFormat::Table)
+    }
+
+    /// Overrides `SNM_OUTPUT` for this process, only the first call counts.
+    pub fn set_output_format(&self, format: OutputFormat) {
+        let _ = OUTPUT_FORMAT.set(format);
+    }
+
+    pub fn get_install_lock_timeout(&self) -> Duration {
+        let value = env::var(SNM_INSTALL_LOCK_TIMEOUT).unwrap_or("300".to_string());
+        Duration::from_secs(value.parse::<u64>().unwrap_or(300))
+    }
+
+    pub fn get_install_smoke_check(&self) -> bool {
+        let value = env::var(SNM_INSTALL_SMOKE_CHECK).unwrap_or(false.to_string());
+        value.parse::<bool>().unwrap_or(false)
+    }
+
+    pub fn get_node_advisory_mode(&self) -> Result<AdvisoryMode, SnmError> {
+        let value = env::var(SNM_NODE_ADVISORY).unwrap_or("warn".to_string());
+        value.parse::<AdvisoryMode>()
+    }
+
+    /// How often the node shim refreshes its advisory data and repeats a warning.
+    pub fn get_node_advisory_interval(&self) -> Duration {
+        let value = env::var(SNM_NODE_ADVISORY_INTERVAL).unwrap_or("86400".to_string());
+        Duration::from_secs(value.parse::<u64>().unwrap_or(86400))
     }
 
     pub fn get_node_install_strategy(&self) -> Result<InstallStrategy, SnmError> {
@@ -141,19 +388,46 @@ impl SnmConfig {
     }
 
     fn init_url_config(&self) {
-        self.var(SNM_NPM_REGISTRY_HOST_KEY, "https://registry.npmjs.org");
         self.var(SNM_YARN_REGISTRY_HOST_KEY, "https://registry.yarnpkg.com");
         self.var(SNM_YARN_REPO_HOST_KEY, "https://repo.yarnpkg.com");
-        self.var(SNM_NODEJS_DIST_URL_KEY, "https://nodejs.org");
+        self.var(SNM_NODEJS_DIST_URL_KEY, "https://nodejs.org/dist");
         self.var(
             SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY,
             "https://raw.githubusercontent.com",
         );
     }
 
+    // `https://a.com,https://b.com` -> 按配置顺序的镜像列表，第一个是主源
+    fn url_vec(&self, key: &str, default: &str) -> Vec<String> {
+        let url_vec: Vec<String> = env::var(key)
+            .unwrap_or(default.to_string())
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+
+        if url_vec.is_empty() {
+            vec![default.to_string()]
+        } else {
+            url_vec
+        }
+    }
+
+    fn first(&self, url_vec: Vec<String>) -> String {
+        url_vec.into_iter().next().unwrap_or_default()
+    }
+
     fn var(&self, key: &str, val: &str) {
         if let Err(_) = env::var(key) {
             env::set_var(key, val);
         }
     }
 }
+
+fn split_type_vec(value: String) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
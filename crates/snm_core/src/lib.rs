@@ -1,4 +1,5 @@
 pub mod config;
+pub mod lockfile;
 pub mod macros;
 pub mod model;
 pub mod utils;
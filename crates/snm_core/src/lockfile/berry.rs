@@ -0,0 +1,167 @@
+//! yarn berry ( 2+ ) `yarn.lock`, YAML with a `__metadata` entry. Keys are
+//! descriptors like `ms@npm:^2.1.0` and workspaces are entries resolved to
+//! `name@workspace:path`. Only read, migrating to yarn writes the classic format.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    model::PackageJson,
+    utils::yaml::{self, Yaml},
+};
+
+use super::{
+    normalize_path, Dependency, Importer, Lockfile, LockfileKind, Package, PackageId, Target,
+};
+
+pub fn parse(text: &str, manifest_vec: &[(String, PackageJson)]) -> Result<Lockfile, String> {
+    let root = yaml::parse(text)?;
+
+    let mut descriptor_map: BTreeMap<&str, Target> = BTreeMap::new();
+    for (key, entry) in root.entries() {
+        let Some(target) = resolution_target(entry) else {
+            continue;
+        };
+        for descriptor in key.split(", ") {
+            descriptor_map.insert(descriptor.trim(), target.clone());
+        }
+    }
+
+    let resolve = |name: &str, descriptor_range: &str| -> Option<Dependency> {
+        let target = descriptor_map
+            .get(format!("{}@{}", name, descriptor_range).as_str())?
+            .clone();
+        Some(Dependency {
+            range: plain_range(descriptor_range).to_string(),
+            target,
+        })
+    };
+
+    let mut lockfile = Lockfile {
+        kind: LockfileKind::Yarn,
+        importers: BTreeMap::new(),
+        packages: BTreeMap::new(),
+    };
+
+    for (_, entry) in root.entries() {
+        let optional_name_vec: Vec<String> = entry
+            .get("dependenciesMeta")
+            .map(Yaml::entries)
+            .unwrap_or_default()
+            .iter()
+            .filter(|(_, meta)| meta.bool_of("optional"))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut dependencies = BTreeMap::new();
+        let mut optional_dependencies = BTreeMap::new();
+        for (name, range) in entry.str_map_of("dependencies") {
+            let Some(dependency) = resolve(&name, &range) else {
+                continue;
+            };
+            if optional_name_vec.contains(&name) {
+                optional_dependencies.insert(name, dependency);
+            } else {
+                dependencies.insert(name, dependency);
+            }
+        }
+
+        match resolution_target(entry) {
+            Some(Target::Importer(path)) => {
+                let manifest = manifest_vec
+                    .iter()
+                    .find(|(manifest_path, _)| *manifest_path == path)
+                    .map(|(_, package_json)| package_json);
+                // berry 把 devDependencies 合并进了 dependencies，只能靠 package.json 区分
+                let (dev_dependencies, dependencies) =
+                    dependencies.into_iter().partition(|(name, _)| {
+                        manifest
+                            .and_then(|package_json| package_json.dev_dependencies.as_ref())
+                            .is_some_and(|dev_map| dev_map.contains_key(name))
+                    });
+                lockfile.importers.insert(
+                    path,
+                    Importer {
+                        name: manifest.and_then(|package_json| package_json.name.clone()),
+                        version: manifest.and_then(|package_json| package_json.version.clone()),
+                        dependencies,
+                        dev_dependencies,
+                        optional_dependencies,
+                    },
+                );
+            }
+            Some(Target::Package(id)) => {
+                let (os, cpu) = parse_conditions(entry.str_of("conditions").unwrap_or_default());
+                lockfile.packages.insert(
+                    id,
+                    Package {
+                        dependencies,
+                        optional_dependencies,
+                        peer_dependencies: entry
+                            .str_map_of("peerDependencies")
+                            .into_iter()
+                            .map(|(name, range)| (name, plain_range(&range).to_string()))
+                            .collect(),
+                        os,
+                        cpu,
+                        has_bin: entry.get("bin").is_some(),
+                        ..Default::default()
+                    },
+                );
+            }
+            None => {}
+        }
+    }
+
+    Ok(lockfile)
+}
+
+/// `demo@workspace:.` is the root importer, `ms@npm:2.1.3` a package named by the
+/// resolution ( the real name for aliases ) at its `version`.
+fn resolution_target(entry: &Yaml) -> Option<Target> {
+    let resolution = PackageId::parse(entry.str_of("resolution")?)?;
+    match resolution.version.strip_prefix("workspace:") {
+        Some(path) => Some(Target::Importer(normalize_path(path))),
+        None => Some(Target::Package(PackageId::new(
+            &resolution.name,
+            entry.str_of("version")?,
+        ))),
+    }
+}
+
+/// `npm:^2.1.0` is `^2.1.0` elsewhere, aliases like `npm:string-width@^4` are kept.
+fn plain_range(range: &str) -> &str {
+    match range.strip_prefix("npm:") {
+        Some(rest) if PackageId::parse(rest).is_none() => rest,
+        _ => range,
+    }
+}
+
+/// `os=darwin & cpu=arm64`.
+fn parse_conditions(conditions: &str) -> (Vec<String>, Vec<String>) {
+    let mut os = vec![];
+    let mut cpu = vec![];
+    for condition in conditions.split('&') {
+        match condition.trim().split_once('=') {
+            Some(("os", value)) => os.push(value.to_string()),
+            Some(("cpu", value)) => cpu.push(value.to_string()),
+            _ => {}
+        }
+    }
+    (os, cpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_conditions() {
+        assert_eq!(plain_range("npm:^2.1.0"), "^2.1.0");
+        assert_eq!(plain_range("npm:string-width@^4"), "npm:string-width@^4");
+        assert_eq!(plain_range("workspace:^"), "workspace:^");
+        assert_eq!(
+            parse_conditions("os=darwin & cpu=arm64"),
+            (vec!["darwin".to_string()], vec!["arm64".to_string()])
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    fs::read_to_string,
+    path::Path,
+};
+
+use crate::model::{PackageJson, SnmError, Workspace};
+
+pub mod berry;
+pub mod npm;
+pub mod pnpm;
+pub mod yarn;
+
+static NPM_REGISTRY: &str = "https://registry.npmjs.org";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockfileKind {
+    Npm,
+    Pnpm,
+    Yarn,
+}
+
+impl LockfileKind {
+    pub fn from_package_manager(name: &str) -> Option<Self> {
+        match name {
+            "npm" => Some(Self::Npm),
+            "pnpm" => Some(Self::Pnpm),
+            "yarn" => Some(Self::Yarn),
+            _ => None,
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        match file_name {
+            "package-lock.json" | "npm-shrinkwrap.json" => Some(Self::Npm),
+            "pnpm-lock.yaml" => Some(Self::Pnpm),
+            "yarn.lock" => Some(Self::Yarn),
+            _ => None,
+        }
+    }
+
+    pub fn package_manager(&self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Pnpm => "pnpm",
+            Self::Yarn => "yarn",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Self::Npm => "package-lock.json",
+            Self::Pnpm => "pnpm-lock.yaml",
+            Self::Yarn => "yarn.lock",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PackageId {
+    pub name: String,
+    pub version: String,
+}
+
+impl PackageId {
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    /// `lodash@4.17.21` or `@babel/core@7.24.0`, the first `@` of a scope is part
+    /// of the name.
+    pub fn parse(key: &str) -> Option<Self> {
+        let position = key.get(1..)?.find('@')? + 1;
+        Some(Self::new(&key[..position], &key[position + 1..]))
+    }
+
+    /// The registry tarball url, `@scope/name/-/name-1.0.0.tgz`.
+    pub fn tarball_url(&self, registry: &str) -> String {
+        let base_name = self.name.rsplit('/').next().unwrap_or(&self.name);
+        format!(
+            "{}/{}/-/{}-{}.tgz",
+            registry.trim_end_matches('/'),
+            self.name,
+            base_name,
+            self.version
+        )
+    }
+}
+
+impl fmt::Display for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// What a dependency resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Package(PackageId),
+    /// A workspace package, by its dir relative to the lock file.
+    Importer(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// As written by the dependent, e.g. `^4.17.0`, `npm:string-width@^4` or
+    /// `workspace:^`. Formats without ranges ( pnpm 9 snapshots ) use the version.
+    pub range: String,
+    pub target: Target,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Package {
+    pub resolved: Option<String>,
+    pub integrity: Option<String>,
+    /// Keyed by the name the dependent uses, which differs from the package name
+    /// for aliases.
+    pub dependencies: BTreeMap<String, Dependency>,
+    pub optional_dependencies: BTreeMap<String, Dependency>,
+    pub peer_dependencies: BTreeMap<String, String>,
+    pub os: Vec<String>,
+    pub cpu: Vec<String>,
+    pub has_bin: bool,
+    pub optional: bool,
+}
+
+/// The root project or a workspace package.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Importer {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: BTreeMap<String, Dependency>,
+    pub dev_dependencies: BTreeMap<String, Dependency>,
+    pub optional_dependencies: BTreeMap<String, Dependency>,
+}
+
+impl Importer {
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &Dependency)> {
+        self.dependencies
+            .iter()
+            .chain(&self.optional_dependencies)
+            .chain(&self.dev_dependencies)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependent<'a> {
+    /// The path of an importer, `""` for the root.
+    Importer(&'a str),
+    Package(&'a PackageId),
+}
+
+/// A lock file of any supported package manager in one model: importers ( the
+/// root is `""` ) and the resolved packages with their dependency edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lockfile {
+    pub kind: LockfileKind,
+    pub importers: BTreeMap<String, Importer>,
+    pub packages: BTreeMap<PackageId, Package>,
+}
+
+impl Lockfile {
+    /// Reads a lock file, yarn.lock also needs the package.json files next to it
+    /// to know the importers.
+    pub fn read(lock_file_path: &Path) -> Result<Self, SnmError> {
+        let file_name = lock_file_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let kind = LockfileKind::from_file_name(&file_name).ok_or(SnmError::Error(format!(
+            "Unsupported lock file {}",
+            lock_file_path.display()
+        )))?;
+
+        let text = read_to_string(lock_file_path).map_err(SnmError::io("read", lock_file_path))?;
+        let dir = lock_file_path.parent().unwrap_or(Path::new("."));
+
+        let result = match kind {
+            LockfileKind::Npm => npm::parse(&text),
+            LockfileKind::Pnpm => pnpm::parse(&text),
+            LockfileKind::Yarn => yarn::parse(&text, &read_manifests(dir)?),
+        };
+        result.map_err(|message| {
+            SnmError::Error(format!(
+                "Can not parse {}: {}",
+                lock_file_path.display(),
+                message
+            ))
+        })
+    }
+
+    pub fn write(&self, kind: LockfileKind) -> String {
+        match kind {
+            LockfileKind::Npm => npm::write(self),
+            LockfileKind::Pnpm => pnpm::write(self),
+            LockfileKind::Yarn => yarn::write(self),
+        }
+    }
+
+    /// Packages only reachable through devDependencies of the importers.
+    pub fn dev_only(&self) -> BTreeSet<&PackageId> {
+        let prod_set = self.reachable(self.importers.values().flat_map(|importer| {
+            importer
+                .dependencies
+                .values()
+                .chain(importer.optional_dependencies.values())
+        }));
+        self.packages
+            .keys()
+            .filter(|id| !prod_set.contains(id))
+            .collect()
+    }
+
+    /// Every package reachable from `dependency_iter`.
+    pub fn reachable<'a>(
+        &'a self,
+        dependency_iter: impl Iterator<Item = &'a Dependency>,
+    ) -> BTreeSet<&'a PackageId> {
+        let mut seen = BTreeSet::new();
+        let mut queue: VecDeque<&Dependency> = dependency_iter.collect();
+
+        while let Some(dependency) = queue.pop_front() {
+            let Target::Package(id) = &dependency.target else {
+                continue;
+            };
+            let Some((id, package)) = self.packages.get_key_value(id) else {
+                continue;
+            };
+            if seen.insert(id) {
+                queue.extend(package.dependencies.values());
+                queue.extend(package.optional_dependencies.values());
+            }
+        }
+        seen
+    }
+
+    /// Every locked version of `name`.
+    pub fn versions_of(&self, name: &str) -> Vec<&PackageId> {
+        self.packages.keys().filter(|id| id.name == name).collect()
+    }
+
+    /// Who depends on each package, importers by their path.
+    pub fn dependents(&self) -> BTreeMap<&PackageId, Vec<Dependent<'_>>> {
+        let mut dependent_map: BTreeMap<&PackageId, Vec<Dependent>> = BTreeMap::new();
+        let edge_iter = self
+            .importers
+            .iter()
+            .flat_map(|(path, importer)| {
+                importer
+                    .all_dependencies()
+                    .map(move |(_, dependency)| (Dependent::Importer(path), dependency))
+            })
+            .chain(self.packages.iter().flat_map(|(id, package)| {
+                package
+                    .dependencies
+                    .values()
+                    .chain(package.optional_dependencies.values())
+                    .map(move |dependency| (Dependent::Package(id), dependency))
+            }));
+
+        for (dependent, dependency) in edge_iter {
+            let Target::Package(id) = &dependency.target else {
+                continue;
+            };
+            if let Some((id, _)) = self.packages.get_key_value(id) {
+                let dependent_vec = dependent_map.entry(id).or_default();
+                if !dependent_vec.contains(&dependent) {
+                    dependent_vec.push(dependent);
+                }
+            }
+        }
+        dependent_map
+    }
+
+    /// Up to `limit` dependency chains from an importer down to `id`, e.g.
+    /// `["demo", "debug@4.3.4", "ms@2.1.2"]`, shortest first.
+    pub fn paths_to(&self, id: &PackageId, limit: usize) -> Vec<Vec<String>> {
+        let dependent_map = self.dependents();
+        let mut path_vec: Vec<Vec<String>> = vec![];
+        // 从目标包反向走到 importer，广度优先所以短的路径在前
+        let mut queue: VecDeque<Vec<&PackageId>> = VecDeque::from([vec![id]]);
+
+        while let Some(chain) = queue.pop_front() {
+            if path_vec.len() >= limit {
+                break;
+            }
+            let Some(dependent_vec) = chain.last().and_then(|last| dependent_map.get(last)) else {
+                continue;
+            };
+            for dependent in dependent_vec {
+                match dependent {
+                    Dependent::Importer(path) => {
+                        let importer_name = self.importers[*path]
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| path.to_string());
+                        path_vec.push(
+                            std::iter::once(importer_name)
+                                .chain(chain.iter().rev().map(|id| id.to_string()))
+                                .collect(),
+                        );
+                    }
+                    Dependent::Package(parent) if !chain.contains(parent) => {
+                        let mut next = chain.clone();
+                        next.push(parent);
+                        queue.push_back(next);
+                    }
+                    Dependent::Package(_) => {}
+                }
+            }
+        }
+
+        path_vec.truncate(limit);
+        path_vec
+    }
+
+    /// Fills importer names and versions from their package.json files, lock files
+    /// like pnpm-lock.yaml don't record them.
+    pub fn fill_importer_manifests(&mut self, dir: &Path) {
+        for (path, importer) in self.importers.iter_mut() {
+            let Ok(package_json) =
+                PackageJson::from_file_path(&dir.join(path).join("package.json"))
+            else {
+                continue;
+            };
+            importer.name = importer.name.take().or(package_json.name);
+            importer.version = importer.version.take().or(package_json.version);
+        }
+    }
+}
+
+/// The root package.json and the workspace ones, keyed by their relative dir.
+fn read_manifests(dir: &Path) -> Result<Vec<(String, PackageJson)>, SnmError> {
+    let mut manifest_vec = vec![(
+        String::new(),
+        PackageJson::from_file_path(&dir.join("package.json"))?,
+    )];
+    if let Some(workspace) = Workspace::find(dir)?.filter(|workspace| workspace.root_dir == dir) {
+        manifest_vec.extend(
+            workspace
+                .package_vec
+                .into_iter()
+                .map(|package| (package.relative_dir, package.package_json)),
+        );
+    }
+    Ok(manifest_vec)
+}
+
+/// `to` relative to `from`, both relative to the same root with `/`, e.g.
+/// `packages/app` -> `packages/core` is `../core`.
+fn relative_path(from: &str, to: &str) -> String {
+    let from_vec: Vec<&str> = from.split('/').filter(|part| !part.is_empty()).collect();
+    let to_vec: Vec<&str> = to.split('/').filter(|part| !part.is_empty()).collect();
+    let common = from_vec
+        .iter()
+        .zip(&to_vec)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let part_vec: Vec<&str> = std::iter::repeat_n("..", from_vec.len() - common)
+        .chain(to_vec[common..].iter().copied())
+        .collect();
+    if part_vec.is_empty() {
+        ".".to_string()
+    } else {
+        part_vec.join("/")
+    }
+}
+
+/// Resolves `..` and `.` of a relative dir joined with `/`.
+fn normalize_path(path: &str) -> String {
+    let mut part_vec: Vec<&str> = vec![];
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                part_vec.pop();
+            }
+            _ => part_vec.push(part),
+        }
+    }
+    part_vec.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("lockfile")
+    }
+
+    /// The same project locked by each package manager.
+    fn fixture(file_name: &str) -> Lockfile {
+        let dir = fixture_dir();
+        let text = read_to_string(dir.join(file_name)).unwrap();
+        let mut lockfile = match file_name {
+            "package-lock.json" => npm::parse(&text),
+            "pnpm-lock.yaml" => pnpm::parse(&text),
+            _ => yarn::parse(&text, &read_manifests(&dir).unwrap()),
+        }
+        .unwrap();
+        lockfile.fill_importer_manifests(&dir);
+        lockfile
+    }
+
+    fn edges(lockfile: &Lockfile) -> BTreeSet<String> {
+        let importer_iter = lockfile.importers.iter().flat_map(|(path, importer)| {
+            importer.all_dependencies().map(move |(name, dependency)| {
+                format!("{:?} {} {:?}", path, name, dependency.target)
+            })
+        });
+        let package_iter = lockfile.packages.iter().flat_map(|(id, package)| {
+            package
+                .dependencies
+                .iter()
+                .map(move |(name, dependency)| format!("{} {} {:?}", id, name, dependency.target))
+        });
+        importer_iter.chain(package_iter).collect()
+    }
+
+    #[test]
+    fn every_format_gives_the_same_graph() {
+        let npm = fixture("package-lock.json");
+
+        assert_eq!(
+            npm.packages
+                .keys()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>(),
+            ["debug@4.3.4", "ms@2.1.2", "ms@2.1.3"]
+        );
+        assert_eq!(
+            npm.importers["packages/core"].name.as_deref(),
+            Some("@demo/core")
+        );
+        assert_eq!(
+            npm.importers[""].dependencies["@demo/core"].target,
+            Target::Importer("packages/core".to_string())
+        );
+
+        for file_name in ["pnpm-lock.yaml", "yarn-v1.lock", "yarn-berry.lock"] {
+            let lockfile = fixture(file_name);
+            assert_eq!(
+                lockfile.packages.keys().collect::<Vec<_>>(),
+                npm.packages.keys().collect::<Vec<_>>(),
+                "{}",
+                file_name
+            );
+            assert_eq!(edges(&lockfile), edges(&npm), "{}", file_name);
+            assert_eq!(
+                lockfile.importers[""]
+                    .dev_dependencies
+                    .keys()
+                    .collect::<Vec<_>>(),
+                ["debug"],
+                "{}",
+                file_name
+            );
+        }
+    }
+
+    #[test]
+    fn resolved_and_integrity() {
+        let ms = PackageId::new("ms", "2.1.3");
+        let integrity = "sha512-6FlzubTLZG3J2a/NVCAleEhjzq5oxgHyaCU9yYXvcLsvoVaHJq/s5xXI6/XXP6tz7R9xAOtHnSO/tXtF3WRTlg==";
+
+        for file_name in ["package-lock.json", "pnpm-lock.yaml", "yarn-v1.lock"] {
+            assert_eq!(
+                fixture(file_name).packages[&ms].integrity.as_deref(),
+                Some(integrity),
+                "{}",
+                file_name
+            );
+        }
+        assert!(fixture("yarn-v1.lock").packages[&ms]
+            .resolved
+            .as_deref()
+            .is_some_and(
+                |resolved| resolved.starts_with("https://registry.yarnpkg.com/ms/-/ms-2.1.3.tgz")
+            ));
+        // berry 的 checksum 是 zip 的哈希，不是 npm 的 integrity
+        assert_eq!(fixture("yarn-berry.lock").packages[&ms].integrity, None);
+    }
+
+    #[test]
+    fn graph_queries() {
+        let lockfile = fixture("pnpm-lock.yaml");
+        let old_ms = PackageId::new("ms", "2.1.2");
+
+        assert_eq!(lockfile.versions_of("ms").len(), 2);
+        assert_eq!(
+            lockfile.dev_only().into_iter().collect::<Vec<_>>(),
+            [&PackageId::new("debug", "4.3.4"), &old_ms]
+        );
+        assert_eq!(
+            lockfile.paths_to(&old_ms, 10),
+            [["demo", "debug@4.3.4", "ms@2.1.2"]]
+        );
+        assert_eq!(
+            lockfile.paths_to(&PackageId::new("ms", "2.1.3"), 10),
+            [["demo", "ms@2.1.3"], ["@demo/core", "ms@2.1.3"]]
+        );
+        assert_eq!(
+            lockfile.paths_to(&PackageId::new("ms", "2.1.3"), 1).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn package_id_and_paths() {
+        assert_eq!(
+            PackageId::parse("@babel/core@7.24.0"),
+            Some(PackageId::new("@babel/core", "7.24.0"))
+        );
+        assert_eq!(
+            PackageId::new("@babel/core", "7.24.0").tarball_url("https://registry.npmjs.org/"),
+            "https://registry.npmjs.org/@babel/core/-/core-7.24.0.tgz"
+        );
+        assert_eq!(relative_path("packages/app", "packages/core"), "../core");
+        assert_eq!(relative_path("", "packages/core"), "packages/core");
+        assert_eq!(relative_path("packages/app", ""), "../..");
+        assert_eq!(normalize_path("packages/app/../core"), "packages/core");
+    }
+}
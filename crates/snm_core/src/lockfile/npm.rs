@@ -0,0 +1,444 @@
+//! `package-lock.json` / `npm-shrinkwrap.json` with `lockfileVersion` 2 or 3. Packages
+//! are keyed by their install location, a dependency resolves to the nearest
+//! `node_modules/<name>` walking up from the dependent like node does.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{
+    normalize_path, Dependency, Importer, Lockfile, LockfileKind, Package, PackageId, Target,
+    NPM_REGISTRY,
+};
+
+pub fn parse(text: &str) -> Result<Lockfile, String> {
+    let json: Value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+
+    let lockfile_version = json.get("lockfileVersion").and_then(Value::as_u64);
+    if !matches!(lockfile_version, Some(2) | Some(3)) {
+        return Err(format!(
+            "lockfileVersion {} is not supported, run npm install with npm 7 or newer first",
+            lockfile_version.map_or("1".to_string(), |version| version.to_string())
+        ));
+    }
+
+    let entry_map = json
+        .get("packages")
+        .and_then(Value::as_object)
+        .ok_or("missing packages")?;
+
+    let mut lockfile = Lockfile {
+        kind: LockfileKind::Npm,
+        importers: BTreeMap::new(),
+        packages: BTreeMap::new(),
+    };
+    let mut id_map: BTreeMap<&str, PackageId> = BTreeMap::new();
+
+    for (location, entry) in entry_map {
+        if entry.get("link").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+        if !is_node_modules(location) {
+            lockfile.importers.insert(
+                location.clone(),
+                Importer {
+                    name: str_of(entry, "name"),
+                    version: str_of(entry, "version"),
+                    ..Default::default()
+                },
+            );
+            continue;
+        }
+
+        let name = str_of(entry, "name").unwrap_or_else(|| name_of_location(location).to_string());
+        let Some(version) = str_of(entry, "version") else {
+            continue;
+        };
+        let id = PackageId::new(&name, &version);
+        id_map.insert(location, id.clone());
+
+        lockfile.packages.entry(id).or_insert_with(|| Package {
+            resolved: str_of(entry, "resolved"),
+            integrity: str_of(entry, "integrity"),
+            peer_dependencies: string_map(entry, "peerDependencies"),
+            os: string_vec(entry, "os"),
+            cpu: string_vec(entry, "cpu"),
+            has_bin: entry.get("bin").is_some(),
+            optional: entry.get("optional").and_then(Value::as_bool) == Some(true),
+            ..Default::default()
+        });
+    }
+
+    let resolve = |location: &str, name: &str, range: &str| -> Option<Dependency> {
+        let mut current = Some(location);
+        while let Some(dir) = current {
+            let candidate = child_location(dir, name);
+            if let Some(entry) = entry_map.get(&candidate) {
+                let target = if entry.get("link").and_then(Value::as_bool) == Some(true) {
+                    Target::Importer(normalize_path(&str_of(entry, "resolved")?))
+                } else {
+                    Target::Package(id_map.get(candidate.as_str())?.clone())
+                };
+                return Some(Dependency {
+                    range: range.to_string(),
+                    target,
+                });
+            }
+            current = parent_location(dir);
+        }
+        None
+    };
+
+    for (location, entry) in entry_map {
+        let dependencies_of = |field: &str| -> BTreeMap<String, Dependency> {
+            string_map(entry, field)
+                .into_iter()
+                .filter_map(|(name, range)| {
+                    resolve(location, &name, &range).map(|dependency| (name, dependency))
+                })
+                .collect()
+        };
+
+        if let Some(importer) = lockfile.importers.get_mut(location) {
+            importer.dependencies = dependencies_of("dependencies");
+            importer.dev_dependencies = dependencies_of("devDependencies");
+            importer.optional_dependencies = dependencies_of("optionalDependencies");
+        } else if let Some(id) = id_map.get(location.as_str()) {
+            let dependencies = dependencies_of("dependencies");
+            let optional_dependencies = dependencies_of("optionalDependencies");
+            let package = lockfile.packages.get_mut(id).expect("package of id");
+            // 同一个版本装在多处时依赖可能解析到不同位置，以第一处为准
+            if package.dependencies.is_empty() && package.optional_dependencies.is_empty() {
+                package.dependencies = dependencies;
+                package.optional_dependencies = optional_dependencies;
+            }
+        }
+    }
+
+    Ok(lockfile)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NpmLock<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+    lockfile_version: u8,
+    requires: bool,
+    packages: BTreeMap<String, Entry<'a>>,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Entry<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    link: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dev: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    optional: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dev_dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    optional_dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    peer_dependencies: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cpu: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    os: Vec<&'a str>,
+}
+
+#[derive(Clone, PartialEq)]
+enum Placed<'a> {
+    Package(&'a PackageId),
+    Link(&'a str),
+}
+
+/// Lays the packages out in `node_modules` ( hoisted as far up as the versions
+/// allow ) and writes a `lockfileVersion: 3` lock file.
+pub fn write(lockfile: &Lockfile) -> String {
+    let mut tree: BTreeMap<String, Placed> = BTreeMap::new();
+    let mut queue: VecDeque<(String, &BTreeMap<String, Dependency>)> = VecDeque::new();
+
+    for (path, importer) in &lockfile.importers {
+        if !path.is_empty() {
+            if let Some(name) = &importer.name {
+                tree.insert(child_location("", name), Placed::Link(path));
+            }
+        }
+        queue.push_back((path.clone(), &importer.dependencies));
+        queue.push_back((path.clone(), &importer.optional_dependencies));
+        queue.push_back((path.clone(), &importer.dev_dependencies));
+    }
+
+    while let Some((parent, dependency_map)) = queue.pop_front() {
+        for (name, dependency) in dependency_map {
+            let placed = match &dependency.target {
+                Target::Package(id) => match lockfile.packages.get_key_value(id) {
+                    Some((id, _)) => Placed::Package(id),
+                    None => continue,
+                },
+                Target::Importer(path) => Placed::Link(path),
+            };
+            let Some(location) = place(&mut tree, &parent, name, placed.clone()) else {
+                continue;
+            };
+            if let Placed::Package(id) = placed {
+                let package = &lockfile.packages[id];
+                queue.push_back((location.clone(), &package.dependencies));
+                queue.push_back((location, &package.optional_dependencies));
+            }
+        }
+    }
+
+    let dev_set = lockfile.dev_only();
+    let mut entry_map: BTreeMap<String, Entry> = BTreeMap::new();
+
+    for (path, importer) in &lockfile.importers {
+        entry_map.insert(
+            path.clone(),
+            Entry {
+                name: importer.name.as_deref(),
+                version: importer.version.as_deref(),
+                dependencies: range_map(&importer.dependencies),
+                dev_dependencies: range_map(&importer.dev_dependencies),
+                optional_dependencies: range_map(&importer.optional_dependencies),
+                ..Default::default()
+            },
+        );
+    }
+
+    for (location, placed) in &tree {
+        let entry = match placed {
+            Placed::Link(path) => Entry {
+                resolved: Some(path.to_string()),
+                link: true,
+                ..Default::default()
+            },
+            Placed::Package(id) => {
+                let package = &lockfile.packages[*id];
+                Entry {
+                    name: (name_of_location(location) != id.name).then_some(id.name.as_str()),
+                    version: Some(&id.version),
+                    resolved: Some(
+                        package
+                            .resolved
+                            .clone()
+                            .unwrap_or_else(|| id.tarball_url(NPM_REGISTRY)),
+                    ),
+                    integrity: package.integrity.as_deref(),
+                    dev: dev_set.contains(id),
+                    optional: package.optional,
+                    dependencies: range_map(&package.dependencies),
+                    optional_dependencies: range_map(&package.optional_dependencies),
+                    peer_dependencies: package
+                        .peer_dependencies
+                        .iter()
+                        .map(|(name, range)| (name.as_str(), range.as_str()))
+                        .collect(),
+                    cpu: package.cpu.iter().map(String::as_str).collect(),
+                    os: package.os.iter().map(String::as_str).collect(),
+                    ..Default::default()
+                }
+            }
+        };
+        entry_map.insert(location.clone(), entry);
+    }
+
+    let root = lockfile.importers.get("");
+    let npm_lock = NpmLock {
+        name: root.and_then(|root| root.name.as_deref()),
+        version: root.and_then(|root| root.version.as_deref()),
+        lockfile_version: 3,
+        requires: true,
+        packages: entry_map,
+    };
+
+    let mut text = serde_json::to_string_pretty(&npm_lock).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// Puts `placed` where `parent` resolves `name` to it, as high as possible. `None`
+/// when it already resolves there.
+fn place<'a>(
+    tree: &mut BTreeMap<String, Placed<'a>>,
+    parent: &str,
+    name: &str,
+    placed: Placed<'a>,
+) -> Option<String> {
+    let mut current = Some(parent);
+    while let Some(dir) = current {
+        match tree.get(&child_location(dir, name)) {
+            Some(existing) if *existing == placed => return None,
+            Some(_) => {
+                // 上层已经有别的版本，装到依赖方自己的 node_modules 下
+                let location = child_location(parent, name);
+                if tree.contains_key(&location) {
+                    return None;
+                }
+                tree.insert(location.clone(), placed);
+                return Some(location);
+            }
+            None => current = parent_location(dir),
+        }
+    }
+    let location = child_location("", name);
+    tree.insert(location.clone(), placed);
+    Some(location)
+}
+
+fn range_map(dependency_map: &BTreeMap<String, Dependency>) -> BTreeMap<&str, &str> {
+    dependency_map
+        .iter()
+        .map(|(name, dependency)| (name.as_str(), dependency.range.as_str()))
+        .collect()
+}
+
+fn is_node_modules(location: &str) -> bool {
+    location.starts_with("node_modules/") || location.contains("/node_modules/")
+}
+
+fn child_location(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        format!("node_modules/{}", name)
+    } else {
+        format!("{}/node_modules/{}", dir, name)
+    }
+}
+
+fn parent_location(location: &str) -> Option<&str> {
+    if location.is_empty() {
+        return None;
+    }
+    match location.rfind("/node_modules/") {
+        Some(position) => Some(&location[..position]),
+        None => Some(""),
+    }
+}
+
+fn name_of_location(location: &str) -> &str {
+    location
+        .rsplit_once("node_modules/")
+        .map_or(location, |(_, name)| name)
+}
+
+fn str_of(entry: &Value, key: &str) -> Option<String> {
+    entry.get(key).and_then(Value::as_str).map(String::from)
+}
+
+fn string_map(entry: &Value, key: &str) -> BTreeMap<String, String> {
+    entry
+        .get(key)
+        .and_then(Value::as_object)
+        .map(|object: &Map<String, Value>| {
+            object
+                .iter()
+                .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string_vec(entry: &Value, key: &str) -> Vec<String> {
+    entry
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE_LOCK: &str = r#"{
+  "name": "demo",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "demo",
+      "version": "1.0.0",
+      "dependencies": { "a": "^1.0.0", "b": "^1.0.0" },
+      "devDependencies": { "c": "^2.0.0" }
+    },
+    "node_modules/a": {
+      "version": "1.0.0",
+      "resolved": "https://registry.npmjs.org/a/-/a-1.0.0.tgz",
+      "integrity": "sha512-a",
+      "dependencies": { "c": "^1.0.0" }
+    },
+    "node_modules/a/node_modules/c": {
+      "version": "1.5.0",
+      "resolved": "https://registry.npmjs.org/c/-/c-1.5.0.tgz",
+      "integrity": "sha512-c1"
+    },
+    "node_modules/b": {
+      "version": "1.1.0",
+      "resolved": "https://registry.npmjs.org/b/-/b-1.1.0.tgz",
+      "integrity": "sha512-b",
+      "dependencies": { "c": "^2.0.0" }
+    },
+    "node_modules/c": {
+      "version": "2.0.0",
+      "resolved": "https://registry.npmjs.org/c/-/c-2.0.0.tgz",
+      "integrity": "sha512-c2"
+    }
+  }
+}
+"#;
+
+    #[test]
+    fn resolve_nearest_node_modules() {
+        let lockfile = parse(PACKAGE_LOCK).unwrap();
+
+        assert_eq!(lockfile.packages.len(), 4);
+        let a = &lockfile.packages[&PackageId::new("a", "1.0.0")];
+        assert_eq!(
+            a.dependencies["c"].target,
+            Target::Package(PackageId::new("c", "1.5.0"))
+        );
+        let b = &lockfile.packages[&PackageId::new("b", "1.1.0")];
+        assert_eq!(
+            b.dependencies["c"].target,
+            Target::Package(PackageId::new("c", "2.0.0"))
+        );
+        assert_eq!(lockfile.importers[""].dev_dependencies["c"].range, "^2.0.0");
+    }
+
+    #[test]
+    fn write_parse_round_trip() {
+        let lockfile = parse(PACKAGE_LOCK).unwrap();
+        let text = write(&lockfile);
+
+        assert_eq!(parse(&text).unwrap(), lockfile);
+        assert!(text.contains("\"node_modules/a/node_modules/c\""));
+        assert!(!text.contains("\"dev\": true"));
+    }
+
+    #[test]
+    fn lockfile_version_1_is_rejected() {
+        assert!(parse(r#"{"lockfileVersion": 1, "dependencies": {}}"#).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! `pnpm-lock.yaml`. Reads `lockfileVersion` 5.x, 6.0 and 9.0, writes 9.0 where
+//! `packages` holds the metadata and `snapshots` the dependency edges.
+
+use std::collections::BTreeMap;
+
+use crate::utils::yaml::{self, quote, Yaml};
+
+use super::{
+    normalize_path, relative_path, Dependency, Importer, Lockfile, LockfileKind, Package,
+    PackageId, Target,
+};
+
+pub fn parse(text: &str) -> Result<Lockfile, String> {
+    let document = yaml::parse(text)?;
+    let lockfile_version = document
+        .str_of("lockfileVersion")
+        .ok_or("missing lockfileVersion")?;
+    let major = lockfile_version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())
+        .ok_or(format!("invalid lockfileVersion {}", lockfile_version))?;
+    if !(5..=9).contains(&major) {
+        return Err(format!(
+            "lockfileVersion {} is not supported",
+            lockfile_version
+        ));
+    }
+
+    let mut lockfile = Lockfile {
+        kind: LockfileKind::Pnpm,
+        importers: BTreeMap::new(),
+        packages: BTreeMap::new(),
+    };
+
+    for (key, entry) in document
+        .get("packages")
+        .map(Yaml::entries)
+        .unwrap_or_default()
+    {
+        let Some(id) = parse_package_key(key, major) else {
+            continue;
+        };
+        let resolution = entry.get("resolution");
+        let package = lockfile.packages.entry(id.clone()).or_default();
+
+        if package.integrity.is_none() {
+            package.integrity = resolution
+                .and_then(|resolution| resolution.str_of("integrity"))
+                .map(String::from);
+            package.resolved = resolution
+                .and_then(|resolution| resolution.str_of("tarball"))
+                .map(String::from);
+            package.peer_dependencies = scalar_map(entry.get("peerDependencies"));
+            package.os = scalar_vec(entry.get("os"));
+            package.cpu = scalar_vec(entry.get("cpu"));
+            package.has_bin = entry.bool_of("hasBin");
+            package.optional = entry.bool_of("optional");
+        }
+        // 9.0 之前依赖关系也写在 packages 里
+        if major < 9 {
+            merge_edges(package, entry, major);
+        }
+    }
+
+    for (key, entry) in document
+        .get("snapshots")
+        .map(Yaml::entries)
+        .unwrap_or_default()
+    {
+        let Some(id) = parse_package_key(key, major) else {
+            continue;
+        };
+        let package = lockfile.packages.entry(id).or_default();
+        package.optional |= entry.bool_of("optional");
+        merge_edges(package, entry, major);
+    }
+
+    match document.get("importers") {
+        Some(importers) => {
+            for (path, entry) in importers.entries() {
+                let path = normalize_path(path);
+                let importer = parse_importer(entry, &path, major);
+                lockfile.importers.insert(path, importer);
+            }
+        }
+        None => {
+            lockfile
+                .importers
+                .insert(String::new(), parse_importer(&document, "", major));
+        }
+    }
+
+    Ok(lockfile)
+}
+
+fn parse_importer(entry: &Yaml, path: &str, major: u32) -> Importer {
+    // 5.x 的 specifier 单独放在 specifiers 里
+    let specifier_map = scalar_map(entry.get("specifiers"));
+
+    let dependencies_of = |field: &str| -> BTreeMap<String, Dependency> {
+        entry
+            .get(field)
+            .map(Yaml::entries)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(name, value)| {
+                let (range, version) = match value {
+                    Yaml::Scalar(version) => (specifier_map.get(name).cloned(), version.as_str()),
+                    _ => (
+                        value.str_of("specifier").map(String::from),
+                        value.str_of("version")?,
+                    ),
+                };
+                let target = parse_target(name, version, path, major)?;
+                let range = range.unwrap_or_else(|| version.to_string());
+                Some((name.clone(), Dependency { range, target }))
+            })
+            .collect()
+    };
+
+    Importer {
+        dependencies: dependencies_of("dependencies"),
+        dev_dependencies: dependencies_of("devDependencies"),
+        optional_dependencies: dependencies_of("optionalDependencies"),
+        ..Default::default()
+    }
+}
+
+fn merge_edges(package: &mut Package, entry: &Yaml, major: u32) {
+    let edges_of = |field: &str| -> BTreeMap<String, Dependency> {
+        entry
+            .get(field)
+            .map(Yaml::entries)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(name, version)| {
+                let version = version.as_str()?;
+                let target = parse_target(name, version, "", major)?;
+                let range = match &target {
+                    Target::Package(id) if id.name != *name => {
+                        format!("npm:{}@{}", id.name, id.version)
+                    }
+                    Target::Package(id) => id.version.clone(),
+                    Target::Importer(_) => version.to_string(),
+                };
+                Some((name.clone(), Dependency { range, target }))
+            })
+            .collect()
+    };
+
+    // 同一版本因 peer 不同会有多个快照，以第一个为准
+    if package.dependencies.is_empty() && package.optional_dependencies.is_empty() {
+        package.dependencies = edges_of("dependencies");
+        package.optional_dependencies = edges_of("optionalDependencies");
+    }
+}
+
+/// `4.17.21`, `18.2.0(react@18.2.0)`, `string-width@4.2.3` for an alias, `/string-width@4.2.3`
+/// or `/string-width/4.2.3` before 9.0, `link:../core` for a workspace package.
+fn parse_target(name: &str, version: &str, importer_path: &str, major: u32) -> Option<Target> {
+    if let Some(link) = version.strip_prefix("link:") {
+        return Some(Target::Importer(normalize_path(&format!(
+            "{}/{}",
+            importer_path, link
+        ))));
+    }
+    let version = strip_peer_suffix(version, major);
+    if version.starts_with('/') || (version.contains('@') && !version.starts_with("file:")) {
+        return parse_package_key(version, major).map(Target::Package);
+    }
+    Some(Target::Package(PackageId::new(name, version)))
+}
+
+/// `lodash@4.17.21`, `/lodash@4.17.21` ( 6.0 ) or `/lodash/4.17.21` ( 5.x ), with
+/// any peer suffix.
+fn parse_package_key(key: &str, major: u32) -> Option<PackageId> {
+    let key = strip_peer_suffix(key, major);
+    let key = key.strip_prefix('/').unwrap_or(key);
+    if major >= 6 {
+        return PackageId::parse(key);
+    }
+    let (name, version) = key.rsplit_once('/')?;
+    Some(PackageId::new(name, version))
+}
+
+fn strip_peer_suffix(value: &str, major: u32) -> &str {
+    let end = if major >= 6 {
+        value.find('(')
+    } else {
+        value.find('_')
+    };
+    end.map_or(value, |end| &value[..end])
+}
+
+fn scalar_map(value: Option<&Yaml>) -> BTreeMap<String, String> {
+    value
+        .map(Yaml::entries)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+fn scalar_vec(value: Option<&Yaml>) -> Vec<String> {
+    value
+        .map(Yaml::items)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|item| item.as_str().map(String::from))
+        .collect()
+}
+
+pub fn write(lockfile: &Lockfile) -> String {
+    let mut out = String::from(
+        "lockfileVersion: '9.0'\n\nsettings:\n  autoInstallPeers: true\n  excludeLinksFromLockfile: false\n\nimporters:\n",
+    );
+
+    for (path, importer) in &lockfile.importers {
+        let key = if path.is_empty() { "." } else { path };
+        out.push_str(&format!("\n  {}:", quote(key)));
+        let field_vec = [
+            ("dependencies", &importer.dependencies),
+            ("devDependencies", &importer.dev_dependencies),
+            ("optionalDependencies", &importer.optional_dependencies),
+        ];
+        if field_vec.iter().all(|(_, map)| map.is_empty()) {
+            out.push_str(" {}\n");
+            continue;
+        }
+        out.push('\n');
+        for (field, dependency_map) in field_vec {
+            if dependency_map.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("    {}:\n", field));
+            for (name, dependency) in dependency_map {
+                out.push_str(&format!(
+                    "      {}:\n        specifier: {}\n        version: {}\n",
+                    quote(name),
+                    quote(&dependency.range),
+                    quote(&version_of(name, &dependency.target, path))
+                ));
+            }
+        }
+    }
+
+    if !lockfile.packages.is_empty() {
+        out.push_str("\npackages:\n");
+    }
+    for (id, package) in &lockfile.packages {
+        out.push_str(&format!("\n  {}:\n", quote(&id.to_string())));
+
+        let mut resolution = vec![];
+        if let Some(integrity) = &package.integrity {
+            resolution.push(format!("integrity: {}", quote(integrity)));
+        }
+        if let Some(resolved) = &package.resolved {
+            // registry 上的包只记 integrity，其他来源需要 tarball
+            if package.integrity.is_none() || !resolved.contains("/-/") {
+                resolution.push(format!("tarball: {}", quote(resolved)));
+            }
+        }
+        out.push_str(&format!("    resolution: {{{}}}\n", resolution.join(", ")));
+
+        if !package.cpu.is_empty() {
+            out.push_str(&format!("    cpu: [{}]\n", flow_vec(&package.cpu)));
+        }
+        if !package.os.is_empty() {
+            out.push_str(&format!("    os: [{}]\n", flow_vec(&package.os)));
+        }
+        if package.has_bin {
+            out.push_str("    hasBin: true\n");
+        }
+        if !package.peer_dependencies.is_empty() {
+            out.push_str("    peerDependencies:\n");
+            for (name, range) in &package.peer_dependencies {
+                out.push_str(&format!("      {}: {}\n", quote(name), quote(range)));
+            }
+        }
+    }
+
+    if !lockfile.packages.is_empty() {
+        out.push_str("\nsnapshots:\n");
+    }
+    for (id, package) in &lockfile.packages {
+        out.push_str(&format!("\n  {}:", quote(&id.to_string())));
+        if package.dependencies.is_empty()
+            && package.optional_dependencies.is_empty()
+            && !package.optional
+        {
+            out.push_str(" {}\n");
+            continue;
+        }
+        out.push('\n');
+        for (field, dependency_map) in [
+            ("dependencies", &package.dependencies),
+            ("optionalDependencies", &package.optional_dependencies),
+        ] {
+            if dependency_map.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("    {}:\n", field));
+            for (name, dependency) in dependency_map {
+                out.push_str(&format!(
+                    "      {}: {}\n",
+                    quote(name),
+                    quote(&version_of(name, &dependency.target, ""))
+                ));
+            }
+        }
+        if package.optional {
+            out.push_str("    optional: true\n");
+        }
+    }
+
+    out
+}
+
+fn version_of(name: &str, target: &Target, importer_path: &str) -> String {
+    match target {
+        Target::Package(id) if id.name != name => id.to_string(),
+        Target::Package(id) => id.version.clone(),
+        Target::Importer(path) => format!("link:{}", relative_path(importer_path, path)),
+    }
+}
+
+fn flow_vec(value_vec: &[String]) -> String {
+    value_vec
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNPM_LOCK_V9: &str = r#"lockfileVersion: '9.0'
+
+settings:
+  autoInstallPeers: true
+  excludeLinksFromLockfile: false
+
+importers:
+
+  .:
+    dependencies:
+      react-dom:
+        specifier: ^18.2.0
+        version: 18.2.0(react@18.2.0)
+      sw:
+        specifier: npm:string-width@^4
+        version: string-width@4.2.3
+    devDependencies:
+      '@org/core':
+        specifier: workspace:^
+        version: link:packages/core
+
+  packages/core: {}
+
+packages:
+
+  react-dom@18.2.0:
+    resolution: {integrity: sha512-rd}
+    peerDependencies:
+      react: ^18.2.0
+
+  react@18.2.0:
+    resolution: {integrity: sha512-r}
+
+  string-width@4.2.3:
+    resolution: {integrity: sha512-sw}
+    engines: {node: '>=8'}
+
+snapshots:
+
+  react-dom@18.2.0(react@18.2.0):
+    dependencies:
+      react: 18.2.0
+
+  react@18.2.0: {}
+
+  string-width@4.2.3: {}
+"#;
+
+    #[test]
+    fn parse_v9() {
+        let lockfile = parse(PNPM_LOCK_V9).unwrap();
+        let root = &lockfile.importers[""];
+
+        assert_eq!(
+            root.dependencies["react-dom"].target,
+            Target::Package(PackageId::new("react-dom", "18.2.0"))
+        );
+        assert_eq!(
+            root.dependencies["sw"].target,
+            Target::Package(PackageId::new("string-width", "4.2.3"))
+        );
+        assert_eq!(
+            root.dev_dependencies["@org/core"].target,
+            Target::Importer("packages/core".to_string())
+        );
+        let react_dom = &lockfile.packages[&PackageId::new("react-dom", "18.2.0")];
+        assert_eq!(react_dom.integrity.as_deref(), Some("sha512-rd"));
+        assert_eq!(react_dom.peer_dependencies["react"], "^18.2.0");
+        assert_eq!(
+            react_dom.dependencies["react"].target,
+            Target::Package(PackageId::new("react", "18.2.0"))
+        );
+    }
+
+    #[test]
+    fn write_parse_round_trip() {
+        let lockfile = parse(PNPM_LOCK_V9).unwrap();
+        let text = write(&lockfile);
+
+        assert_eq!(parse(&text).unwrap(), lockfile);
+        assert!(text.contains(
+            "  '@org/core':\n        specifier: workspace:^\n        version: link:packages/core\n"
+        ));
+        assert!(text.contains("  react-dom@18.2.0:\n    dependencies:\n      react: 18.2.0\n"));
+    }
+
+    #[test]
+    fn parse_v6_and_v5_keys() {
+        let lockfile = parse(
+            r#"lockfileVersion: '6.0'
+
+dependencies:
+  a:
+    specifier: ^1.0.0
+    version: 1.0.0
+
+packages:
+
+  /a@1.0.0:
+    resolution: {integrity: sha512-a}
+    dependencies:
+      '@scope/b': 2.0.0
+    dev: false
+
+  /@scope/b@2.0.0:
+    resolution: {integrity: sha512-b}
+    dev: false
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lockfile.packages[&PackageId::new("a", "1.0.0")].dependencies["@scope/b"].target,
+            Target::Package(PackageId::new("@scope/b", "2.0.0"))
+        );
+        assert_eq!(lockfile.importers[""].dependencies["a"].range, "^1.0.0");
+
+        let lockfile = parse(
+            "lockfileVersion: 5.4\n\nspecifiers:\n  a: ^1.0.0\n\ndependencies:\n  a: 1.0.0_react@18.2.0\n\npackages:\n\n  /a/1.0.0_react@18.2.0:\n    resolution: {integrity: sha512-a}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            lockfile.importers[""].dependencies["a"].target,
+            Target::Package(PackageId::new("a", "1.0.0"))
+        );
+        assert!(lockfile
+            .packages
+            .contains_key(&PackageId::new("a", "1.0.0")));
+    }
+}
@@ -0,0 +1,357 @@
+//! yarn classic `yarn.lock` ( `# yarn lockfile v1` ). Entries are keyed by the
+//! `name@range` pairs that resolved to them, workspace packages are not listed so
+//! the importers come from the package.json files. yarn berry lock files are
+//! handed to [`super::berry`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::model::PackageJson;
+
+use super::{berry, Dependency, Importer, Lockfile, LockfileKind, Package, PackageId, Target};
+
+static YARN_REGISTRY: &str = "https://registry.yarnpkg.com";
+
+#[derive(Default)]
+struct Entry {
+    key_vec: Vec<String>,
+    field_map: BTreeMap<String, String>,
+    dependencies: BTreeMap<String, String>,
+    optional_dependencies: BTreeMap<String, String>,
+}
+
+/// `manifest_vec` are the package.json files of the root ( `""` ) and of the
+/// workspace packages by relative dir.
+pub fn parse(text: &str, manifest_vec: &[(String, PackageJson)]) -> Result<Lockfile, String> {
+    if text.contains("\n__metadata:") || text.starts_with("__metadata:") {
+        return berry::parse(text, manifest_vec);
+    }
+
+    let entry_vec = parse_entries(text)?;
+
+    let mut id_map: BTreeMap<&str, PackageId> = BTreeMap::new();
+    for entry in &entry_vec {
+        let Some(version) = entry.field_map.get("version") else {
+            continue;
+        };
+        let Some(first_key) = entry.key_vec.first() else {
+            continue;
+        };
+        let id = PackageId::new(&real_name(first_key), version);
+        for key in &entry.key_vec {
+            id_map.insert(key, id.clone());
+        }
+    }
+
+    let workspace_map: BTreeMap<&str, &str> = manifest_vec
+        .iter()
+        .filter(|(path, _)| !path.is_empty())
+        .filter_map(|(path, package_json)| Some((package_json.name.as_deref()?, path.as_str())))
+        .collect();
+
+    let resolve = |name: &str, range: &str| -> Option<Dependency> {
+        let target = match id_map.get(format!("{}@{}", name, range).as_str()) {
+            Some(id) => Target::Package(id.clone()),
+            None => Target::Importer(workspace_map.get(name)?.to_string()),
+        };
+        Some(Dependency {
+            range: range.to_string(),
+            target,
+        })
+    };
+    let resolve_map = |range_map: &BTreeMap<String, String>| -> BTreeMap<String, Dependency> {
+        range_map
+            .iter()
+            .filter_map(|(name, range)| Some((name.clone(), resolve(name, range)?)))
+            .collect()
+    };
+
+    let mut lockfile = Lockfile {
+        kind: LockfileKind::Yarn,
+        importers: BTreeMap::new(),
+        packages: BTreeMap::new(),
+    };
+
+    for entry in &entry_vec {
+        let Some(id) = entry
+            .key_vec
+            .first()
+            .and_then(|key| id_map.get(key.as_str()))
+        else {
+            continue;
+        };
+        lockfile.packages.insert(
+            id.clone(),
+            Package {
+                resolved: entry.field_map.get("resolved").cloned(),
+                integrity: entry.field_map.get("integrity").cloned(),
+                dependencies: resolve_map(&entry.dependencies),
+                optional_dependencies: resolve_map(&entry.optional_dependencies),
+                ..Default::default()
+            },
+        );
+    }
+
+    for (path, package_json) in manifest_vec {
+        let to_map = |field: &Option<std::collections::HashMap<String, String>>| {
+            resolve_map(
+                &field
+                    .iter()
+                    .flatten()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            )
+        };
+        lockfile.importers.insert(
+            path.clone(),
+            Importer {
+                name: package_json.name.clone(),
+                version: package_json.version.clone(),
+                dependencies: to_map(&package_json.dependencies),
+                dev_dependencies: to_map(&package_json.dev_dependencies),
+                optional_dependencies: to_map(&package_json.optional_dependencies),
+            },
+        );
+    }
+
+    Ok(lockfile)
+}
+
+fn parse_entries(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entry_vec: Vec<Entry> = vec![];
+    let mut section: Option<&str> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        if indent == 0 {
+            let key_text = content
+                .strip_suffix(':')
+                .ok_or(format!("line {}: expected an entry", index + 1))?;
+            entry_vec.push(Entry {
+                key_vec: key_text.split(", ").map(unquote).collect(),
+                ..Default::default()
+            });
+            section = None;
+            continue;
+        }
+
+        let entry = entry_vec
+            .last_mut()
+            .ok_or(format!("line {}: field outside of an entry", index + 1))?;
+
+        if let Some(field) = content.strip_suffix(':') {
+            section = Some(match field {
+                "dependencies" => "dependencies",
+                "optionalDependencies" => "optionalDependencies",
+                _ => "",
+            });
+            continue;
+        }
+
+        let (key, value) =
+            split_pair(content).ok_or(format!("line {}: expected `key value`", index + 1))?;
+        match (indent > 2, section) {
+            (true, Some("dependencies")) => {
+                entry.dependencies.insert(key, value);
+            }
+            (true, Some("optionalDependencies")) => {
+                entry.optional_dependencies.insert(key, value);
+            }
+            (true, _) => {}
+            (false, _) => {
+                section = None;
+                entry.field_map.insert(key, value);
+            }
+        }
+    }
+
+    Ok(entry_vec)
+}
+
+/// `"@babel/core" "^7.0.0"` or `version "1.0.0"`.
+fn split_pair(content: &str) -> Option<(String, String)> {
+    if let Some(rest) = content.strip_prefix('"') {
+        let (key, value) = rest.split_once('"')?;
+        return Some((key.to_string(), unquote(value.trim())));
+    }
+    let (key, value) = content.split_once(' ')?;
+    Some((key.to_string(), unquote(value.trim())))
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// The package name of a `name@range` key, the aliased one for `alias@npm:name@range`.
+fn real_name(key: &str) -> String {
+    let Some(id) = PackageId::parse(key) else {
+        return key.to_string();
+    };
+    match id
+        .version
+        .strip_prefix("npm:")
+        .and_then(|aliased| PackageId::parse(aliased).map(|aliased| aliased.name))
+    {
+        Some(aliased_name) => aliased_name,
+        None => id.name,
+    }
+}
+
+pub fn write(lockfile: &Lockfile) -> String {
+    let mut key_map: BTreeMap<&PackageId, BTreeSet<String>> = BTreeMap::new();
+    let dependency_iter = lockfile
+        .importers
+        .values()
+        .flat_map(Importer::all_dependencies)
+        .chain(lockfile.packages.values().flat_map(|package| {
+            package
+                .dependencies
+                .iter()
+                .chain(&package.optional_dependencies)
+        }));
+    for (name, dependency) in dependency_iter {
+        if let Target::Package(id) = &dependency.target {
+            if let Some((id, _)) = lockfile.packages.get_key_value(id) {
+                key_map
+                    .entry(id)
+                    .or_default()
+                    .insert(format!("{}@{}", name, dependency.range));
+            }
+        }
+    }
+
+    let mut block_vec: Vec<(String, String)> = vec![];
+    for (id, package) in &lockfile.packages {
+        let key_vec: Vec<String> = match key_map.get(id) {
+            Some(key_set) => key_set.iter().map(|key| wrap(key)).collect(),
+            None => vec![wrap(&id.to_string())],
+        };
+        let key_line = key_vec.join(", ");
+
+        let mut block = format!("{}:\n", key_line);
+        block.push_str(&format!("  version {}\n", wrap(&id.version)));
+        let resolved = package
+            .resolved
+            .clone()
+            .unwrap_or_else(|| id.tarball_url(YARN_REGISTRY));
+        block.push_str(&format!("  resolved {}\n", wrap(&resolved)));
+        if let Some(integrity) = &package.integrity {
+            block.push_str(&format!("  integrity {}\n", wrap(integrity)));
+        }
+        for (field, dependency_map) in [
+            ("dependencies", &package.dependencies),
+            ("optionalDependencies", &package.optional_dependencies),
+        ] {
+            let range_vec: Vec<(&String, &String)> = dependency_map
+                .iter()
+                .filter(|(_, dependency)| matches!(dependency.target, Target::Package(_)))
+                .map(|(name, dependency)| (name, &dependency.range))
+                .collect();
+            if range_vec.is_empty() {
+                continue;
+            }
+            block.push_str(&format!("  {}:\n", field));
+            for (name, range) in range_vec {
+                block.push_str(&format!("    {} {}\n", wrap(name), wrap(range)));
+            }
+        }
+        block_vec.push((key_line.trim_matches('"').to_string(), block));
+    }
+    block_vec.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = String::from(
+        "# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.\n# yarn lockfile v1\n\n",
+    );
+    for (_, block) in block_vec {
+        out.push('\n');
+        out.push_str(&block);
+    }
+    out
+}
+
+/// Quotes like yarn does: anything not starting with a letter or containing `:`,
+/// spaces, quotes or brackets.
+fn wrap(value: &str) -> String {
+    let needs_quote = value.starts_with("true")
+        || value.starts_with("false")
+        || !value.starts_with(|c: char| c.is_ascii_alphabetic())
+        || value.contains([':', ' ', '\n', '\\', '"', ',', '[', ']']);
+    if needs_quote {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YARN_LOCK: &str = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.10.4":
+  version "7.12.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.12.13.tgz#dcfc826beef65e75c50e21d3837d7d95798dd658"
+  integrity sha512-HV1Cm0Q3ZrpCR93tkWOYiuYIgLxZXZFVG2VgK+MBWjUqZTundupbfx2aXarXuw5Ko5aMcjtJgbSs4vUGBS5v6g==
+  dependencies:
+    "@babel/highlight" "^7.12.13"
+
+"@babel/highlight@^7.12.13":
+  version "7.13.10"
+  resolved "https://registry.yarnpkg.com/@babel/highlight/-/highlight-7.13.10.tgz"
+  integrity sha512-5aPpe5XQPzflQrFwL1/QoeHkP2MsA4JCntcXHRhEsdsfPVkvPi2w7Qix4iV7t5S/oC9OodGrggd8aco1g3SZFg==
+
+sw@npm:string-width@^4:
+  version "4.2.3"
+  resolved "https://registry.yarnpkg.com/string-width/-/string-width-4.2.3.tgz"
+"#;
+
+    fn manifest_vec() -> Vec<(String, PackageJson)> {
+        vec![(
+            String::new(),
+            serde_json::from_str(
+                r#"{"name": "demo", "dependencies": {"@babel/code-frame": "^7.10.4", "sw": "npm:string-width@^4"}}"#,
+            )
+            .unwrap(),
+        )]
+    }
+
+    #[test]
+    fn parse_classic() {
+        let lockfile = parse(YARN_LOCK, &manifest_vec()).unwrap();
+
+        let code_frame = PackageId::new("@babel/code-frame", "7.12.13");
+        assert_eq!(
+            lockfile.packages[&code_frame].dependencies["@babel/highlight"].target,
+            Target::Package(PackageId::new("@babel/highlight", "7.13.10"))
+        );
+        assert_eq!(
+            lockfile.importers[""].dependencies["sw"].target,
+            Target::Package(PackageId::new("string-width", "4.2.3"))
+        );
+        assert!(lockfile.packages[&code_frame]
+            .integrity
+            .as_deref()
+            .is_some_and(|integrity| integrity.starts_with("sha512-")));
+    }
+
+    #[test]
+    fn write_parse_round_trip() {
+        let lockfile = parse(YARN_LOCK, &manifest_vec()).unwrap();
+        let text = write(&lockfile);
+
+        assert_eq!(parse(&text, &manifest_vec()).unwrap(), lockfile);
+        assert!(text.contains("\"@babel/code-frame@^7.10.4\":\n  version \"7.12.13\"\n"));
+        assert!(text.contains("\n\"sw@npm:string-width@^4\":\n"));
+    }
+}
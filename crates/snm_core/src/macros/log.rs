@@ -1,3 +1,17 @@
+use std::io::{stderr, stdout, Write};
+
+use crate::{config::SnmConfig, model::output::OutputFormat};
+
+// `--output json` owns stdout, log lines move to stderr.
+#[doc(hidden)]
+pub fn log_writer() -> Box<dyn Write> {
+    if SnmConfig::new().get_output_format() == OutputFormat::Json {
+        Box::new(stderr())
+    } else {
+        Box::new(stdout())
+    }
+}
+
 #[macro_export]
 macro_rules! println_error {
     ($($arg:tt)*) => {{
@@ -7,9 +21,8 @@ macro_rules! println_error {
             cursor::MoveToColumn,
             terminal::{Clear, ClearType},
         };
-        use std::io::stdout;
 
-        let mut stdout = stdout();
+        let mut stdout = $crate::macros::log::log_writer();
         execute!(stdout, Clear(ClearType::CurrentLine), MoveToColumn(0)).ok();
         let prefix = format!("\x1B[96m[SNM]\x1B[0m");
         writeln!(stdout, "{} 🔴 {}", prefix, format_args!($($arg)*)).ok();
@@ -26,9 +39,8 @@ macro_rules! println_success {
             cursor::MoveToColumn,
             terminal::{Clear, ClearType},
         };
-        use std::io::stdout;
 
-        let mut stdout = stdout();
+        let mut stdout = $crate::macros::log::log_writer();
         execute!(stdout, Clear(ClearType::CurrentLine), MoveToColumn(0)).ok();
         let prefix = format!("\x1B[96m[SNM]\x1B[0m");
         writeln!(stdout, "{} 🟢 {}", prefix, format_args!($($arg)*)).ok();
@@ -45,9 +57,8 @@ macro_rules! print_warning {
             cursor::MoveToColumn,
             terminal::{Clear, ClearType},
         };
-        use std::io::stdout;
 
-        let mut stdout = stdout();
+        let mut stdout = $crate::macros::log::log_writer();
         // 假设你想在打印警告之前清除当前行并将光标移动到行首
         // 这需要crossterm或类似库的支持
         execute!(stdout, Clear(ClearType::CurrentLine), MoveToColumn(0)).ok();
@@ -5,14 +5,27 @@ use dialoguer::Confirm;
 use crate::{
     config::SnmConfig,
     print_warning,
-    utils::download::{DownloadBuilder, WriteStrategy},
+    utils::{
+        download::{DownloadBuilder, WriteStrategy},
+        lock::InstallLock,
+        runtime::block_on,
+    },
 };
 #[cfg(unix)]
 use std::os::unix::fs as unix_fs;
 #[cfg(windows)]
 use std::os::windows::fs as windows_fs;
 
-use super::{trait_manage::ManageTrait, SnmError};
+use super::{
+    output::{print_install_item, print_version_list, InstallItem, InstallStatus},
+    trait_manage::ManageTrait,
+    SnmError,
+};
+
+enum ProxyResolution {
+    Ready(String, PathBuf),
+    Missing(String),
+}
 
 pub struct DispatchManage {
     manager: Box<dyn ManageTrait>,
@@ -43,7 +56,7 @@ impl DispatchManage {
 
         if anchor_file_path_buf.exists().not() {
             if shim_trait.download_condition(&version)? {
-                self.download(&version).await?;
+                self.download_if_missing(&version).await?;
             } else {
                 return Err(SnmError::SilentExit);
             }
@@ -54,53 +67,84 @@ impl DispatchManage {
         return Ok((version.to_string(), binary_path_buf));
     }
 
-    pub async fn proxy_process(&self, bin_name: &str) -> Result<(String, PathBuf), SnmError> {
+    // Every shim invocation goes through here, so the common path stays sync and only
+    // touches the disk. A runtime is only started when a download is required.
+    pub fn proxy_process(&self, bin_name: &str) -> Result<(String, PathBuf), SnmError> {
         let shim_trait = self.manager.get_shim_trait();
+
+        match self.resolve_from_disk(bin_name)? {
+            ProxyResolution::Ready(v, binary_path_buf) => Ok((v, binary_path_buf)),
+            ProxyResolution::Missing(v) => {
+                if shim_trait.download_condition(&v)?.not() {
+                    return Err(SnmError::SilentExit);
+                }
+
+                block_on(self.download_if_missing(&v))?;
+
+                let binary_path_buf = shim_trait.get_strict_shim_binary_path_buf(bin_name, &v)?;
+
+                Ok((v, binary_path_buf))
+            }
+        }
+    }
+
+    fn resolve_from_disk(&self, bin_name: &str) -> Result<ProxyResolution, SnmError> {
+        let shim_trait = self.manager.get_shim_trait();
+
         if self.snm_config.get_strict() {
             let version = shim_trait.get_strict_shim_version()?;
 
             let anchor_file_path_buf = shim_trait.get_anchor_file_path_buf(&version);
 
             if anchor_file_path_buf.exists().not() {
-                if shim_trait.download_condition(&version)? {
-                    self.download(&version).await?;
-                } else {
-                    return Err(SnmError::SilentExit);
-                }
+                return Ok(ProxyResolution::Missing(version));
             }
 
-            let binary_path_buf = shim_trait.get_strict_shim_binary_path_buf(&version, bin_name)?;
+            let binary_path_buf = shim_trait.get_strict_shim_binary_path_buf(bin_name, &version)?;
 
-            return Ok((version, binary_path_buf));
+            Ok(ProxyResolution::Ready(version, binary_path_buf))
         } else {
             let tuple = self.read_runtime_dir_name_vec()?;
 
             let v = shim_trait.check_default_version(&tuple)?;
 
-            let binary_path_buf = shim_trait.get_runtime_binary_file_path_buf(&bin_name, &v)?;
+            let binary_path_buf = shim_trait.get_runtime_binary_file_path_buf(bin_name, &v)?;
 
-            return Ok((v, binary_path_buf));
+            Ok(ProxyResolution::Ready(v, binary_path_buf))
         }
     }
 
     pub async fn list(&self) -> Result<(), SnmError> {
         let dir_tuple = self.read_runtime_dir_name_vec()?;
-        self.manager.show_list(&dir_tuple).await?;
+        let item_vec = self.manager.get_list(&dir_tuple).await?;
+        print_version_list(self.snm_config.get_output_format(), &item_vec, false);
         Ok(())
     }
 
     pub async fn list_remote(&self, all: bool) -> Result<(), SnmError> {
         let dir_tuple = self.read_runtime_dir_name_vec()?;
-        self.manager.show_list_remote(&dir_tuple, all).await?;
+        let item_vec = self.manager.get_list_remote(&dir_tuple, all).await?;
+        print_version_list(self.snm_config.get_output_format(), &item_vec, true);
         Ok(())
     }
 
     pub async fn install(&self, v: &str) -> Result<(), SnmError> {
+        let status = self.install_version(v).await?;
+        let item = InstallItem {
+            version: v.to_string(),
+            status,
+            path: self.manager.get_runtime_dir_path_buf(v),
+        };
+        print_install_item(self.snm_config.get_output_format(), &item);
+        Ok(())
+    }
+
+    async fn install_version(&self, v: &str) -> Result<InstallStatus, SnmError> {
         let anchor_file_path_buf = self.manager.get_anchor_file_path_buf(&v);
 
         if anchor_file_path_buf.exists().not() {
             self.download(v).await?;
-            return Ok(());
+            return Ok(InstallStatus::Installed);
         }
 
         if Confirm::new()
@@ -109,15 +153,15 @@ impl DispatchManage {
                 &v
             ))
             .interact()
-            .expect("install Confirm error")
+            .map_err(SnmError::prompt)?
             .not()
         {
-            return Ok(());
+            return Ok(InstallStatus::Skipped);
         }
 
         self.download(v).await?;
 
-        Ok(())
+        Ok(InstallStatus::Reinstalled)
     }
 
     pub async fn un_install(&self, v: &str) -> Result<(), SnmError> {
@@ -136,19 +180,14 @@ impl DispatchManage {
                         &d_v
                     ))
                     .interact()
-                    .expect("un_install Confirm error")
+                    .map_err(SnmError::prompt)?
                 {
                     let default_path_buf = self
                         .manager
                         .get_runtime_dir_path_buf(format!("{}-default", &v).as_str());
 
-                    fs::remove_dir_all(&default_path_buf).expect(
-                        format!(
-                            "un_install remove_dir_all error {:?}",
-                            &default_path_buf.display()
-                        )
-                        .as_str(),
-                    );
+                    fs::remove_dir_all(&default_path_buf)
+                        .map_err(SnmError::io("remove dir", &default_path_buf))?;
                 } else {
                     return Ok(());
                 }
@@ -156,13 +195,8 @@ impl DispatchManage {
         }
 
         let runtime_dir_path_buf = self.manager.get_runtime_dir_path_buf(&v);
-        fs::remove_dir_all(&runtime_dir_path_buf).expect(
-            format!(
-                "un_install remove_dir_all error {:?}",
-                &runtime_dir_path_buf.display()
-            )
-            .as_str(),
-        );
+        fs::remove_dir_all(&runtime_dir_path_buf)
+            .map_err(SnmError::io("remove dir", &runtime_dir_path_buf))?;
 
         Ok(())
     }
@@ -179,9 +213,9 @@ impl DispatchManage {
                     &v
                 ))
                 .interact()
-                .expect("set_default Confirm error")
+                .map_err(SnmError::prompt)?
             {
-                self.install(&v).await?;
+                self.install_version(v).await?;
             } else {
                 return Ok(());
             }
@@ -189,59 +223,40 @@ impl DispatchManage {
 
         if let Some(d_v) = default_v {
             let default_dir_path_buf = self.manager.get_runtime_dir_for_default_path_buf(&d_v);
-            fs::remove_dir_all(&default_dir_path_buf).expect(
-                format!(
-                    "set_default remove_dir_all error {:?}",
-                    &default_dir_path_buf.display()
-                )
-                .as_str(),
-            );
+            fs::remove_dir_all(&default_dir_path_buf)
+                .map_err(SnmError::io("remove dir", &default_dir_path_buf))?;
         }
 
         let from_dir_path_buf = self.manager.get_runtime_dir_path_buf(&v);
         let to_dir_path_buf = self.manager.get_runtime_dir_for_default_path_buf(&v);
 
-        create_symlink(&from_dir_path_buf, &to_dir_path_buf).expect(
-            format!(
-                "set_default create_symlink error from: {:?} to: {:?}",
-                &from_dir_path_buf.display(),
-                &to_dir_path_buf.display()
-            )
-            .as_str(),
-        );
+        create_symlink(&from_dir_path_buf, &to_dir_path_buf)
+            .map_err(SnmError::io("create symlink", &to_dir_path_buf))?;
 
         Ok(())
     }
 
-    fn read_runtime_dir_name_vec(&self) -> Result<(Vec<String>, Option<String>), SnmError> {
+    pub fn read_runtime_dir_name_vec(&self) -> Result<(Vec<String>, Option<String>), SnmError> {
         let runtime_dir_path_buf = self.manager.get_runtime_base_dir_path_buf();
 
         let mut default_dir = None;
 
+        // Nothing installed yet. Don't create the directory here, shims hit this on every run.
         if runtime_dir_path_buf.exists().not() {
-            // TODO here create not suitable , should be find a better way
-            fs::create_dir_all(&runtime_dir_path_buf).expect(
-                format!(
-                    "read_runtime_dir_name_vec create_dir_all error {:?}",
-                    &runtime_dir_path_buf.display()
-                )
-                .as_str(),
-            );
+            return Ok((vec![], default_dir));
         }
 
         let dir_name_vec = runtime_dir_path_buf
             .read_dir()
-            .expect(
-                format!(
-                    "read_runtime_dir_name_vec read_dir error {:?}",
-                    &runtime_dir_path_buf.display()
-                )
-                .as_str(),
-            )
+            .map_err(SnmError::io("read dir", &runtime_dir_path_buf))?
             .filter_map(|dir_entry| dir_entry.ok())
             .filter(|dir_entry| dir_entry.path().is_dir())
             .filter_map(|dir_entry| {
                 let file_name = dir_entry.file_name().into_string().ok()?;
+                // staging and backup dirs of in-flight installs
+                if file_name.starts_with('.') {
+                    return None;
+                }
                 if file_name.ends_with("-default") {
                     default_dir = Some(file_name.trim_end_matches("-default").to_string());
                     return None;
@@ -254,15 +269,44 @@ impl DispatchManage {
         Ok((dir_name_vec, default_dir))
     }
 
+    async fn lock(&self, v: &str) -> Result<InstallLock, SnmError> {
+        InstallLock::acquire(
+            self.manager.get_lock_file_path_buf(v),
+            self.snm_config.get_install_lock_timeout(),
+        )
+        .await
+    }
+
     async fn download(&self, v: &str) -> Result<(), SnmError> {
-        let download_url = self.manager.get_download_url(v);
+        let _lock = self.lock(v).await?;
+        self.download_unlocked(v).await
+    }
+
+    async fn download_if_missing(&self, v: &str) -> Result<(), SnmError> {
+        let _lock = self.lock(v).await?;
+
+        // another process may have finished the same install while we were waiting
+        if self.manager.get_anchor_file_path_buf(v).exists() {
+            return Ok(());
+        }
+
+        self.download_unlocked(v).await
+    }
+
+    async fn download_unlocked(&self, v: &str) -> Result<(), SnmError> {
+        self.clean_stale_staging_dirs(v)?;
+
+        let download_url_vec = self.manager.get_download_url_vec(v);
         let downloaded_file_path_buf = self.manager.get_downloaded_file_path_buf(v);
+        // node 的包有几十 MB，慢网络下整体超时会把正常的镜像误判为不健康
         DownloadBuilder::new()
             .retries(3)
+            .timeout(None)
             .write_strategy(WriteStrategy::Nothing)
-            .download(&download_url, &downloaded_file_path_buf)
+            .download_from_mirrors(&download_url_vec, &downloaded_file_path_buf)
             .await?;
 
+        // 无论文件来自哪个镜像，期望值都只取主源或者固定的可信源
         let expect_sha256 = self.manager.get_expect_shasum(v).await?;
 
         let actual_sha256 = self
@@ -271,25 +315,123 @@ impl DispatchManage {
             .await?;
 
         if expect_sha256 != actual_sha256 {
-            return Err(SnmError::Error(format!(
-                "File {} Sha256 verification failed, expected {} but received {}.",
-                downloaded_file_path_buf.display(),
-                expect_sha256,
-                actual_sha256
-            )));
+            // don't let the next attempt reuse the corrupted file
+            let _ = fs::remove_file(&downloaded_file_path_buf);
+            return Err(SnmError::ChecksumMismatch {
+                file_path: downloaded_file_path_buf.display().to_string(),
+                expect: expect_sha256,
+                actual: actual_sha256,
+            });
         }
 
         let runtime_dir_path_buf = self.manager.get_runtime_dir_path_buf(v);
+        let staging_dir_path_buf = self.get_staging_dir_path_buf(v, "staging");
+
         self.manager
-            .decompress_download_file(&downloaded_file_path_buf, &runtime_dir_path_buf)?;
-
-        fs::remove_file(&downloaded_file_path_buf).expect(
-            format!(
-                "download remove_file error {:?}",
-                &downloaded_file_path_buf.display()
-            )
-            .as_str(),
-        );
+            .decompress_download_file(&downloaded_file_path_buf, &staging_dir_path_buf)
+            .and_then(|_| self.check_staging_dir(v, &staging_dir_path_buf))
+            .inspect_err(|_| {
+                let _ = fs::remove_dir_all(&staging_dir_path_buf);
+            })?;
+
+        self.swap_in_staging_dir(v, &staging_dir_path_buf, &runtime_dir_path_buf)?;
+
+        fs::remove_file(&downloaded_file_path_buf)
+            .map_err(SnmError::io("remove", &downloaded_file_path_buf))?;
+
+        Ok(())
+    }
+
+    // Staging dirs live next to the runtime dirs so the final rename never crosses a filesystem.
+    fn get_staging_dir_path_buf(&self, v: &str, kind: &str) -> PathBuf {
+        self.manager.get_runtime_base_dir_path_buf().join(format!(
+            ".{}.{}-{}",
+            v,
+            kind,
+            std::process::id()
+        ))
+    }
+
+    // Only called while holding the version lock, so every staging dir of `v` is left over
+    // from an interrupted install.
+    fn clean_stale_staging_dirs(&self, v: &str) -> Result<(), SnmError> {
+        let runtime_base_dir_path_buf = self.manager.get_runtime_base_dir_path_buf();
+
+        if runtime_base_dir_path_buf.exists().not() {
+            return Ok(());
+        }
+
+        let staging_prefix = format!(".{}.staging-", v);
+        let old_prefix = format!(".{}.old-", v);
+
+        let stale_dir_vec = runtime_base_dir_path_buf
+            .read_dir()
+            .map_err(SnmError::io("read dir", &runtime_base_dir_path_buf))?
+            .filter_map(|dir_entry| dir_entry.ok())
+            .filter(|dir_entry| {
+                let file_name = dir_entry.file_name().to_string_lossy().to_string();
+                file_name.starts_with(&staging_prefix) || file_name.starts_with(&old_prefix)
+            })
+            .map(|dir_entry| dir_entry.path());
+
+        for stale_dir_path_buf in stale_dir_vec {
+            print_warning!(
+                "Removing unfinished install {}\n",
+                stale_dir_path_buf.display()
+            );
+            fs::remove_dir_all(&stale_dir_path_buf)
+                .map_err(SnmError::io("remove dir", &stale_dir_path_buf))?;
+        }
+
+        Ok(())
+    }
+
+    fn check_staging_dir(&self, v: &str, staging_dir_path_buf: &PathBuf) -> Result<(), SnmError> {
+        let anchor_file_path_buf = self.manager.get_anchor_file_path_buf(v);
+        let runtime_dir_path_buf = self.manager.get_runtime_dir_path_buf(v);
+
+        let staged_anchor_file_path_buf = anchor_file_path_buf
+            .strip_prefix(&runtime_dir_path_buf)
+            .map(|relative| staging_dir_path_buf.join(relative))
+            .unwrap_or(anchor_file_path_buf);
+
+        if staged_anchor_file_path_buf.exists().not() {
+            return Err(SnmError::Error(format!(
+                "Install of {} is incomplete, {} not found.",
+                v,
+                staged_anchor_file_path_buf.display()
+            )));
+        }
+
+        self.manager.check_staging_dir(v, staging_dir_path_buf)
+    }
+
+    fn swap_in_staging_dir(
+        &self,
+        v: &str,
+        staging_dir_path_buf: &PathBuf,
+        runtime_dir_path_buf: &PathBuf,
+    ) -> Result<(), SnmError> {
+        let old_dir_path_buf = self.get_staging_dir_path_buf(v, "old");
+
+        // reinstall: move the previous version aside first, rename can't replace a non-empty dir
+        let has_old = runtime_dir_path_buf.exists();
+        if has_old {
+            fs::rename(runtime_dir_path_buf, &old_dir_path_buf)
+                .map_err(SnmError::io("move aside", runtime_dir_path_buf))?;
+        }
+
+        if let Err(error) = fs::rename(staging_dir_path_buf, runtime_dir_path_buf) {
+            if has_old {
+                let _ = fs::rename(&old_dir_path_buf, runtime_dir_path_buf);
+            }
+            let _ = fs::remove_dir_all(staging_dir_path_buf);
+            return Err(SnmError::io("activate", runtime_dir_path_buf)(error));
+        }
+
+        if has_old {
+            let _ = fs::remove_dir_all(&old_dir_path_buf);
+        }
 
         Ok(())
     }
@@ -0,0 +1,462 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// Lossless syntax tree of a JSON document: every whitespace run is kept, so
+/// printing an unmodified tree gives back the exact input.
+///
+/// Whitespace is stored in front of the token it precedes, the whitespace before
+/// a closing `}` / `]` belongs to the container.
+#[derive(Debug, Clone)]
+pub struct JsonDocument {
+    leading: String,
+    pub root: JsonNode,
+    trailing: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsonNode {
+    Object(JsonObject),
+    Array(JsonArray),
+    /// Raw text of a string, number, `true`, `false` or `null`.
+    Scalar(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JsonObject {
+    member_vec: Vec<JsonMember>,
+    trailing: String,
+}
+
+#[derive(Debug, Clone)]
+struct JsonMember {
+    leading: String,
+    raw_key: String,
+    key: String,
+    before_colon: String,
+    after_colon: String,
+    value: JsonNode,
+    after_value: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JsonArray {
+    item_vec: Vec<JsonItem>,
+    trailing: String,
+}
+
+#[derive(Debug, Clone)]
+struct JsonItem {
+    leading: String,
+    value: JsonNode,
+    after_value: String,
+}
+
+/// How new nodes are laid out, taken from the existing document.
+#[derive(Debug, Clone)]
+pub struct JsonStyle {
+    pub indent_unit: String,
+    pub newline: String,
+}
+
+impl JsonDocument {
+    /// Parses `text`, which must already be valid JSON ( check it with serde_json
+    /// first to get a precise error position ).
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser { text, pos: 0 };
+        let leading = parser.whitespace();
+        let root = parser.value()?;
+        let trailing = parser.whitespace();
+        if parser.pos != text.len() {
+            return Err(format!("unexpected content at byte {}", parser.pos));
+        }
+        Ok(Self {
+            leading,
+            root,
+            trailing,
+        })
+    }
+
+    /// Indent unit of the first indented member and the newline used by the file.
+    pub fn detect_style(text: &str) -> JsonStyle {
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let indent_unit = text
+            .lines()
+            .skip(1)
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+        JsonStyle {
+            indent_unit: indent_unit.to_string(),
+            newline: newline.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for JsonDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.root, self.trailing)
+    }
+}
+
+impl fmt::Display for JsonNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonNode::Object(object) => {
+                f.write_str("{")?;
+                for (index, member) in object.member_vec.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(
+                        f,
+                        "{}{}{}:{}{}{}",
+                        member.leading,
+                        member.raw_key,
+                        member.before_colon,
+                        member.after_colon,
+                        member.value,
+                        member.after_value
+                    )?;
+                }
+                write!(f, "{}}}", object.trailing)
+            }
+            JsonNode::Array(array) => {
+                f.write_str("[")?;
+                for (index, item) in array.item_vec.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}{}{}", item.leading, item.value, item.after_value)?;
+                }
+                write!(f, "{}]", array.trailing)
+            }
+            JsonNode::Scalar(raw) => f.write_str(raw),
+        }
+    }
+}
+
+impl JsonNode {
+    pub fn to_value(&self) -> Value {
+        serde_json::from_str(&self.to_string()).unwrap_or(Value::Null)
+    }
+
+    /// Lays `value` out like `JSON.stringify(value, null, indent)` would, nested
+    /// lines start with `indent` followed by one more `indent_unit` per level.
+    pub fn from_value(value: &Value, indent: &str, style: &JsonStyle) -> Self {
+        let inner_indent = format!("{}{}", indent, style.indent_unit);
+        let leading = format!("{}{}", style.newline, inner_indent);
+        let trailing = format!("{}{}", style.newline, indent);
+
+        match value {
+            Value::Object(map) if !map.is_empty() => JsonNode::Object(JsonObject {
+                member_vec: map
+                    .iter()
+                    .map(|(key, value)| {
+                        JsonMember::new(key, leading.clone(), value, &inner_indent, style)
+                    })
+                    .collect(),
+                trailing,
+            }),
+            Value::Array(vec) if !vec.is_empty() => JsonNode::Array(JsonArray {
+                item_vec: vec
+                    .iter()
+                    .map(|value| JsonItem {
+                        leading: leading.clone(),
+                        value: JsonNode::from_value(value, &inner_indent, style),
+                        after_value: String::new(),
+                    })
+                    .collect(),
+                trailing,
+            }),
+            Value::Object(_) => JsonNode::Object(JsonObject::default()),
+            Value::Array(_) => JsonNode::Array(JsonArray::default()),
+            scalar => JsonNode::Scalar(scalar.to_string()),
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonObject> {
+        match self {
+            JsonNode::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonObject> {
+        match self {
+            JsonNode::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+}
+
+impl JsonMember {
+    fn new(key: &str, leading: String, value: &Value, indent: &str, style: &JsonStyle) -> Self {
+        Self {
+            leading,
+            raw_key: Value::String(key.to_string()).to_string(),
+            key: key.to_string(),
+            before_colon: String::new(),
+            after_colon: " ".to_string(),
+            value: JsonNode::from_value(value, indent, style),
+            after_value: String::new(),
+        }
+    }
+
+    /// Whitespace in front of the key after its last newline.
+    fn indent(&self) -> &str {
+        self.leading
+            .rsplit_once('\n')
+            .map(|(_, indent)| indent)
+            .unwrap_or("")
+    }
+}
+
+impl JsonObject {
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.member_vec.iter().map(|member| member.key.as_str())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonNode> {
+        self.member_vec
+            .iter()
+            .find(|member| member.key == key)
+            .map(|member| &member.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonNode> {
+        self.member_vec
+            .iter_mut()
+            .find(|member| member.key == key)
+            .map(|member| &mut member.value)
+    }
+
+    /// Indent of the members, `parent_indent` + one unit when the object is empty
+    /// or written on one line.
+    pub fn member_indent(&self, parent_indent: &str, style: &JsonStyle) -> String {
+        self.member_vec
+            .first()
+            .filter(|member| member.leading.contains('\n'))
+            .map(|member| member.indent().to_string())
+            .unwrap_or_else(|| format!("{}{}", parent_indent, style.indent_unit))
+    }
+
+    /// Replaces the value of `key` in place, or appends a member laid out like its
+    /// siblings. `indent` is the indent of the object's own line.
+    pub fn set(&mut self, key: &str, value: &Value, indent: &str, style: &JsonStyle) {
+        let member_indent = self.member_indent(indent, style);
+        let multiline = self.member_vec.is_empty()
+            || self
+                .member_vec
+                .iter()
+                .any(|member| member.leading.contains('\n'));
+
+        let node = if multiline {
+            JsonNode::from_value(value, &member_indent, style)
+        } else {
+            JsonDocument::parse(&value.to_string())
+                .map(|document| document.root)
+                .unwrap_or(JsonNode::Scalar(value.to_string()))
+        };
+
+        if let Some(existing) = self.get_mut(key) {
+            *existing = node;
+            return;
+        }
+
+        let mut member = JsonMember::new(key, String::new(), &Value::Null, indent, style);
+        member.value = node;
+
+        match self.member_vec.last() {
+            // `{"a": 1}` 只有一个成员时看不出逗号后面的空白，按冒号后面的来
+            Some(last) if !multiline && self.member_vec.len() == 1 => {
+                member.leading = if last.after_colon.is_empty() { "" } else { " " }.to_string();
+                member.before_colon = last.before_colon.clone();
+                member.after_colon = last.after_colon.clone();
+            }
+            Some(last) => {
+                member.leading = last.leading.clone();
+                member.before_colon = last.before_colon.clone();
+                member.after_colon = last.after_colon.clone();
+            }
+            None => {
+                member.leading = format!("{}{}", style.newline, member_indent);
+                self.trailing = format!("{}{}", style.newline, indent);
+            }
+        }
+
+        self.member_vec.push(member);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<JsonNode> {
+        let index = self
+            .member_vec
+            .iter()
+            .position(|member| member.key == key)?;
+        let member = self.member_vec.remove(index);
+
+        if self.member_vec.is_empty() {
+            self.trailing.clear();
+        } else if index == self.member_vec.len() {
+            // 删掉的是最后一个，前一个成员后面不再有逗号
+            if let Some(last) = self.member_vec.last_mut() {
+                last.after_value.clear();
+            }
+        }
+
+        Some(member.value)
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at byte {}", byte as char, self.pos))
+        }
+    }
+
+    fn whitespace(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+        self.text[start..self.pos].to_string()
+    }
+
+    fn value(&mut self) -> Result<JsonNode, String> {
+        match self.peek() {
+            Some(b'{') => self.object().map(JsonNode::Object),
+            Some(b'[') => self.array().map(JsonNode::Array),
+            Some(b'"') => self.string().map(JsonNode::Scalar),
+            Some(_) => {
+                let start = self.pos;
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err(format!("expected a value at byte {}", self.pos));
+                }
+                Ok(JsonNode::Scalar(self.text[start..self.pos].to_string()))
+            }
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.pos += 2,
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(self.text[start..self.pos].to_string());
+                }
+                Some(_) => self.pos += 1,
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonObject, String> {
+        self.expect(b'{')?;
+        let mut object = JsonObject::default();
+        let mut leading = self.whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            object.trailing = leading;
+            return Ok(object);
+        }
+
+        loop {
+            let raw_key = self.string()?;
+            let key =
+                serde_json::from_str::<String>(&raw_key).map_err(|error| error.to_string())?;
+            let before_colon = self.whitespace();
+            self.expect(b':')?;
+            let after_colon = self.whitespace();
+            let value = self.value()?;
+            let after = self.whitespace();
+
+            let mut member = JsonMember {
+                leading,
+                raw_key,
+                key,
+                before_colon,
+                after_colon,
+                value,
+                after_value: String::new(),
+            };
+
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    member.after_value = after;
+                    object.member_vec.push(member);
+                    leading = self.whitespace();
+                }
+                Some(b'}') => {
+                    self.pos += 1;
+                    object.member_vec.push(member);
+                    object.trailing = after;
+                    return Ok(object);
+                }
+                _ => return Err(format!("expected `,` or `}}` at byte {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonArray, String> {
+        self.expect(b'[')?;
+        let mut array = JsonArray::default();
+        let mut leading = self.whitespace();
+
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            array.trailing = leading;
+            return Ok(array);
+        }
+
+        loop {
+            let value = self.value()?;
+            let after = self.whitespace();
+            let mut item = JsonItem {
+                leading,
+                value,
+                after_value: String::new(),
+            };
+
+            match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    item.after_value = after;
+                    array.item_vec.push(item);
+                    leading = self.whitespace();
+                }
+                Some(b']') => {
+                    self.pos += 1;
+                    array.item_vec.push(item);
+                    array.trailing = after;
+                    return Ok(array);
+                }
+                _ => return Err(format!("expected `,` or `]` at byte {}", self.pos)),
+            }
+        }
+    }
+}
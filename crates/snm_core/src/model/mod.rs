@@ -1,11 +1,17 @@
 pub use self::package_json::Bin;
 pub use self::package_json::PackageJson;
+pub use self::package_json_document::PackageJsonDocument;
 pub use self::snm_error::SnmError;
+pub use self::workspace::{Workspace, WorkspacePackage};
 
 pub mod dispatch_manage;
+pub mod json_cst;
+pub mod output;
 pub mod package_json;
+pub mod package_json_document;
 pub mod snm_error;
 // pub mod trait_command_args_creator;
 pub mod trait_manage;
 pub mod trait_shared_behavior;
 pub mod trait_shim;
+pub mod workspace;
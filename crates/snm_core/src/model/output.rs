@@ -0,0 +1,157 @@
+use std::{ops::Not, path::PathBuf};
+
+use clap::ValueEnum;
+use colored::*;
+use serde::Serialize;
+
+use crate::println_success;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Plain,
+    Json,
+}
+
+/// One row of `snm <node|npm|pnpm> list` and `list-remote`.
+///
+/// With `--output json` the field names below are a stable contract, only add new
+/// optional fields, never rename or remove one.
+#[derive(Debug, Default, Serialize)]
+pub struct VersionItem {
+    /// Version without the `v` prefix, e.g. `20.11.1`.
+    pub version: String,
+    /// Installed under the snm base dir.
+    pub installed: bool,
+    /// Currently the default version.
+    pub default: bool,
+    /// LTS codename, `null` for non LTS releases or when unknown.
+    pub lts: Option<String>,
+    /// End of life date `YYYY-MM-DD` of the release line.
+    pub end: Option<String>,
+    /// Past its end of life.
+    pub deprecated: Option<bool>,
+    /// Release contains security fixes.
+    pub security: Option<bool>,
+    /// Bundled npm version.
+    pub npm: Option<String>,
+    /// Bundled openssl version.
+    pub openssl: Option<String>,
+    /// For `snm npm list`, the installed node version this npm ships with, `null`
+    /// for an npm installed by snm.
+    pub bundled_node: Option<String>,
+}
+
+/// Result of `snm <node|npm|pnpm> install`.
+#[derive(Debug, Serialize)]
+pub struct InstallItem {
+    pub version: String,
+    /// `installed`, `reinstalled` or `skipped`.
+    pub status: InstallStatus,
+    /// Runtime directory of the version.
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallStatus {
+    Installed,
+    Reinstalled,
+    Skipped,
+}
+
+pub fn print_version_list(format: OutputFormat, item_vec: &[VersionItem], remote: bool) {
+    match format {
+        OutputFormat::Json => print_json(&item_vec),
+        OutputFormat::Plain => {
+            for item in item_vec {
+                if item.default {
+                    println!("{}\tdefault", item.version);
+                } else if let Some(node) = &item.bundled_node {
+                    println!("{}\tbundled {}", item.version, node);
+                } else {
+                    println!("{}", item.version);
+                }
+            }
+        }
+        OutputFormat::Table => {
+            let desc_width = 22;
+            let has_detail = item_vec
+                .iter()
+                .any(|item| item.end.is_some() || item.npm.is_some() || item.openssl.is_some());
+
+            for item in item_vec {
+                let tag = if item.default {
+                    "⛳️"
+                } else if remote && item.installed {
+                    "🫐"
+                } else {
+                    ""
+                };
+
+                let lts = item.lts.as_deref().unwrap_or("");
+
+                let version = if item.deprecated.unwrap_or(false) {
+                    format!(
+                        "{:<10} {:<10}",
+                        item.version.bright_black(),
+                        lts.bright_black()
+                    )
+                } else {
+                    format!(
+                        "{:<10} {:<10}",
+                        item.version.bright_green(),
+                        lts.bright_green()
+                    )
+                };
+
+                if let Some(node) = &item.bundled_node {
+                    let bundled = format!("bundled with node {}", node).bright_black();
+                    println!("{:<2} {} {}", tag, version, bundled);
+                    continue;
+                }
+
+                if has_detail.not() {
+                    println!("{:<2} {}", tag, version);
+                    continue;
+                }
+
+                let died =
+                    format!("died on {}", item.end.as_deref().unwrap_or("None")).bright_black();
+
+                let npm = format!("npm {}", item.npm.as_deref().unwrap_or("None")).bright_black();
+
+                let openssl =
+                    format!("openssl {}", item.openssl.as_deref().unwrap_or("None")).bright_black();
+
+                println!(
+                    "{:<2} {} {:<desc_width$} {:<desc_width$} {:<desc_width$}",
+                    tag, version, died, openssl, npm,
+                );
+            }
+        }
+    }
+}
+
+pub fn print_install_item(format: OutputFormat, item: &InstallItem) {
+    match format {
+        OutputFormat::Json => print_json(item),
+        OutputFormat::Plain => println!("{}", item.path.display()),
+        OutputFormat::Table => {
+            if item.status != InstallStatus::Skipped {
+                println_success!(
+                    "Installed {} {}",
+                    item.version.bright_green(),
+                    format!("at {}", item.path.display()).bright_black()
+                );
+            }
+        }
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(error) => crate::println_error!("serialize output error {}", error),
+    }
+}
@@ -1,10 +1,19 @@
 use regex::{Captures, Regex};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
 
 use super::SnmError;
+use crate::{
+    config::SnmConfig,
+    utils::detect_package_manager::{detect_package_manager, write_package_manager},
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct PackageManager {
     pub raw: String,
     pub name: String,
@@ -14,6 +23,8 @@ pub struct PackageManager {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PackageJson {
+    pub name: Option<String>,
+
     #[serde(rename = "packageManager")]
     pub package_manager: Option<String>,
 
@@ -21,6 +32,21 @@ pub struct PackageJson {
 
     pub bin: Option<Bin>,
 
+    pub scripts: Option<HashMap<String, String>>,
+
+    pub workspaces: Option<Workspaces>,
+
+    pub dependencies: Option<HashMap<String, String>>,
+
+    #[serde(rename = "devDependencies")]
+    pub dev_dependencies: Option<HashMap<String, String>>,
+
+    #[serde(rename = "optionalDependencies")]
+    pub optional_dependencies: Option<HashMap<String, String>>,
+
+    #[serde(rename = "peerDependencies")]
+    pub peer_dependencies: Option<HashMap<String, String>>,
+
     #[serde(skip_serializing)]
     pub _raw_file_path: Option<PathBuf>,
     #[serde(skip_serializing)]
@@ -34,13 +60,30 @@ pub enum Bin {
     Map(HashMap<String, String>),
 }
 
+/// npm / yarn `workspaces`, either `["packages/*"]` or `{ "packages": ["packages/*"] }`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Workspaces {
+    Vec(Vec<String>),
+    Object { packages: Vec<String> },
+}
+
+impl Workspaces {
+    pub fn patterns(&self) -> &[String] {
+        match self {
+            Workspaces::Vec(patterns) => patterns,
+            Workspaces::Object { packages } => packages,
+        }
+    }
+}
+
 impl PackageJson {
     pub fn from_here() -> Result<Self, SnmError> {
-        let workspace = std::env::current_dir().expect("get current dir error.");
+        let workspace = std::env::current_dir().map_err(SnmError::io("get current dir", "."))?;
         let package_json_file_path = workspace.join("package.json");
 
         if package_json_file_path.exists() {
-            let mut pkg = read_to_json::<PackageJson>(&package_json_file_path);
+            let mut pkg = read_to_json::<PackageJson>(&package_json_file_path)?;
 
             pkg._raw_file_path = Some(package_json_file_path);
             pkg._raw_workspace = Some(workspace);
@@ -57,13 +100,13 @@ impl PackageJson {
         let wk = if let Some(wk) = workspace {
             wk
         } else {
-            std::env::current_dir().expect("get current dir error.")
+            std::env::current_dir().map_err(SnmError::io("get current dir", "."))?
         };
 
         let pkg_file_path = wk.join("package.json");
 
         if pkg_file_path.exists() {
-            let mut pkg = read_to_json::<PackageJson>(&pkg_file_path);
+            let mut pkg = read_to_json::<PackageJson>(&pkg_file_path)?;
 
             pkg._raw_file_path = Some(pkg_file_path);
             pkg._raw_workspace = Some(wk);
@@ -77,7 +120,7 @@ impl PackageJson {
     }
 
     pub fn from_file_path(file_path: &PathBuf) -> Result<Self, SnmError> {
-        let mut pkg = read_to_json::<PackageJson>(&file_path);
+        let mut pkg = read_to_json::<PackageJson>(file_path)?;
         pkg._raw_file_path = Some(file_path.to_path_buf());
 
         pkg._raw_workspace = file_path.parent().map(|x| x.to_path_buf());
@@ -103,21 +146,96 @@ impl PackageJson {
             let res = regex
                 .captures(raw_package_manager.as_str())
                 .map(map_to_struct)
-                .expect("parse_package_manager error");
+                .ok_or(SnmError::Error(format!(
+                    "Invalid packageManager {}, expected <name>@<version>[+<hash>]",
+                    raw_package_manager
+                )))?;
 
             return Ok(res);
         }
         return Err(SnmError::NotFoundPackageJsonBinProperty {
-            file_path: self._raw_file_path.clone().unwrap().display().to_string(),
+            file_path: self
+                ._raw_file_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
         });
     }
 
+    /// `packageManager` when set, otherwise the one inferred from the lock file next
+    /// to package.json. With `SNM_WRITE_PACKAGE_MANAGER=true` the inferred one is
+    /// written back into package.json. The lock file is only read once per process.
+    pub fn resolve_package_manager(&self) -> Result<Option<PackageManager>, SnmError> {
+        // 一次 shim 调用里会解析好几次，每个目录只推断一次
+        static DETECTED: OnceLock<Mutex<HashMap<PathBuf, Option<PackageManager>>>> =
+            OnceLock::new();
+
+        if self.package_manager.is_some() {
+            return self.parse_package_manager().map(Some);
+        }
+
+        let Some(raw_workspace) = &self._raw_workspace else {
+            return Ok(None);
+        };
+
+        let mut detected_map = DETECTED
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(package_manager) = detected_map.get(raw_workspace) {
+            return Ok(package_manager.clone());
+        }
+
+        let package_manager = detect_package_manager(raw_workspace)?;
+
+        if let (Some(package_manager), Some(raw_file_path)) =
+            (&package_manager, &self._raw_file_path)
+        {
+            if SnmConfig::new().get_write_package_manager() {
+                write_package_manager(raw_file_path, &package_manager.raw)?;
+                crate::println_success!(
+                    "Wrote packageManager {} to {}",
+                    package_manager.raw,
+                    raw_file_path.display()
+                );
+            }
+        }
+
+        detected_map.insert(raw_workspace.clone(), package_manager.clone());
+        Ok(package_manager)
+    }
+
+    /// Names of every dependency, whatever the kind.
+    pub fn dependency_names(&self) -> Vec<&String> {
+        [
+            &self.dependencies,
+            &self.dev_dependencies,
+            &self.optional_dependencies,
+            &self.peer_dependencies,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|map| map.keys())
+        .collect()
+    }
+
     pub fn bin_to_hashmap(&self) -> Result<HashMap<String, PathBuf>, SnmError> {
-        let raw_workspace = self._raw_workspace.as_ref().unwrap();
+        let raw_workspace = self._raw_workspace.as_ref().ok_or(SnmError::Error(
+            "package.json was not read from disk, its bin paths can not be resolved".to_string(),
+        ))?;
         if let Some(bin) = &self.bin {
             match bin {
-                Bin::Str(_) => {
-                    unimplemented!("parse_package_json_bin_to_hashmap   if bin value is str")
+                Bin::Str(v) => {
+                    // `"bin": "cli.js"` is a shorthand for `"bin": { "<name>": "cli.js" }`
+                    let name = self.name.as_ref().ok_or(SnmError::Error(format!(
+                        "package.json bin is a string but name is missing, {}",
+                        raw_workspace.display()
+                    )))?;
+                    let absolute_file_path = raw_workspace
+                        .join(v)
+                        .canonicalize()
+                        .map_err(SnmError::io("resolve bin", raw_workspace.join(v)))?;
+                    Ok(HashMap::from([(name.to_string(), absolute_file_path)]))
                 }
                 Bin::Map(map) => {
                     let new_map = map
@@ -143,9 +261,7 @@ impl PackageJson {
     }
 }
 
-fn read_to_json<T: DeserializeOwned>(file_path: &PathBuf) -> T {
-    let content =
-        read_to_string(&file_path).expect(format!("read {:?} error", &file_path).as_str());
-    serde_json::from_str::<T>(&content)
-        .expect(format!("parse {:?} json error", &file_path).as_str())
+fn read_to_json<T: DeserializeOwned>(file_path: &PathBuf) -> Result<T, SnmError> {
+    let content = read_to_string(file_path).map_err(SnmError::io("read", file_path))?;
+    serde_json::from_str::<T>(&content).map_err(SnmError::json(file_path))
 }
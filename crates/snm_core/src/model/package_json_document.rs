@@ -0,0 +1,292 @@
+use std::{
+    fmt,
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::{
+    json_cst::{JsonDocument, JsonNode, JsonStyle},
+    SnmError,
+};
+
+/// package.json opened for editing. Unlike [`super::PackageJson`] it keeps every
+/// field, and writing it back only touches the edited values: indentation, key
+/// order, CRLF line endings and the trailing newline stay as they were.
+///
+/// ```ignore
+/// let mut document = PackageJsonDocument::from_file_path(&path)?;
+/// let node = document.get::<String>(&["engines", "node"])?;
+/// document.set(&["scripts", "build"], &"tsc -p .")?;
+/// document.remove(&["devDependencies", "left-pad"]);
+/// document.save()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PackageJsonDocument {
+    file_path: PathBuf,
+    document: JsonDocument,
+    style: JsonStyle,
+}
+
+impl PackageJsonDocument {
+    pub fn from_file_path(file_path: &Path) -> Result<Self, SnmError> {
+        let text = read_to_string(file_path).map_err(SnmError::io("read", file_path))?;
+        Self::parse(file_path, &text)
+    }
+
+    /// `file_path` is where [`PackageJsonDocument::save`] writes and what errors report.
+    pub fn parse(file_path: &Path, text: &str) -> Result<Self, SnmError> {
+        serde_json::from_str::<Value>(text).map_err(SnmError::json(file_path))?;
+
+        let document = JsonDocument::parse(text).map_err(|message| SnmError::JsonParse {
+            file_path: file_path.display().to_string(),
+            line: 0,
+            column: 0,
+            message,
+        })?;
+
+        if document.root.as_object().is_none() {
+            return Err(SnmError::Error(format!(
+                "{} is not a json object",
+                file_path.display()
+            )));
+        }
+
+        Ok(Self {
+            file_path: file_path.to_path_buf(),
+            style: JsonDocument::detect_style(text),
+            document,
+        })
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// The value at `path`, `None` when a field of the path is missing.
+    pub fn get<T: DeserializeOwned>(&self, path: &[&str]) -> Result<Option<T>, SnmError> {
+        let Some(node) = self.node(path) else {
+            return Ok(None);
+        };
+        serde_json::from_value(node.to_value())
+            .map(Some)
+            .map_err(|error| {
+                SnmError::Error(format!(
+                    "Invalid {} in {}: {}",
+                    path.join("."),
+                    self.file_path.display(),
+                    error
+                ))
+            })
+    }
+
+    /// Field names of the object at `path` in file order.
+    pub fn keys(&self, path: &[&str]) -> Vec<String> {
+        self.node(path)
+            .and_then(JsonNode::as_object)
+            .map(|object| object.keys().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the value at `path` in place or appends it to its object, missing
+    /// parent objects are created.
+    pub fn set<T: Serialize>(&mut self, path: &[&str], value: &T) -> Result<(), SnmError> {
+        let Some((key, parent_path)) = path.split_last() else {
+            return Err(SnmError::Error(
+                "Can not replace the whole package.json".to_string(),
+            ));
+        };
+        let value = serde_json::to_value(value).map_err(|error| {
+            SnmError::Error(format!("Can not serialize {}: {}", path.join("."), error))
+        })?;
+
+        let style = &self.style;
+        let mut indent = String::new();
+        let mut node = &mut self.document.root;
+
+        for (depth, parent_key) in parent_path.iter().enumerate() {
+            let object = node.as_object_mut().ok_or_else(|| {
+                SnmError::Error(format!(
+                    "{} is not an object",
+                    parent_path[..depth].join(".")
+                ))
+            })?;
+            let member_indent = object.member_indent(&indent, style);
+            if object.get(parent_key).is_none() {
+                object.set(
+                    parent_key,
+                    &Value::Object(Default::default()),
+                    &indent,
+                    style,
+                );
+            }
+            indent = member_indent;
+            node = object
+                .get_mut(parent_key)
+                .expect("member was just inserted");
+        }
+
+        let object = node.as_object_mut().ok_or_else(|| {
+            SnmError::Error(format!("{} is not an object", parent_path.join(".")))
+        })?;
+        object.set(key, &value, &indent, style);
+        Ok(())
+    }
+
+    /// Removes the field at `path` and returns its value.
+    pub fn remove(&mut self, path: &[&str]) -> Option<Value> {
+        let (key, parent_path) = path.split_last()?;
+        let mut node = &mut self.document.root;
+        for parent_key in parent_path {
+            node = node.as_object_mut()?.get_mut(parent_key)?;
+        }
+        node.as_object_mut()?
+            .remove(key)
+            .map(|node| node.to_value())
+    }
+
+    pub fn save(&self) -> Result<(), SnmError> {
+        write(&self.file_path, self.to_string()).map_err(SnmError::io("write", &self.file_path))
+    }
+
+    fn node(&self, path: &[&str]) -> Option<&JsonNode> {
+        path.iter()
+            .try_fold(&self.document.root, |node, key| node.as_object()?.get(key))
+    }
+}
+
+impl fmt::Display for PackageJsonDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.document.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn parse(text: &str) -> PackageJsonDocument {
+        PackageJsonDocument::parse(Path::new("package.json"), text).unwrap()
+    }
+
+    const PACKAGE_JSON: &str = r#"{
+    "name": "demo",
+    "version" : "1.0.0",
+    "scripts": {
+        "build": "tsc"
+    },
+    "files": ["dist", "README.md"],
+    "engines": {"node": ">=18"}
+}
+"#;
+
+    #[test]
+    fn unmodified_document_round_trips() {
+        for text in [
+            PACKAGE_JSON,
+            "{}",
+            "{\"a\":[1, 2.5e3,true,null, {\"b\":\"\\\"\"}]}\n\n",
+            "{\r\n\t\"name\": \"crlf\"\r\n}\r\n",
+        ] {
+            assert_eq!(parse(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn typed_get() {
+        let document = parse(PACKAGE_JSON);
+
+        assert_eq!(
+            document.get::<String>(&["engines", "node"]).unwrap(),
+            Some(">=18".to_string())
+        );
+        assert_eq!(
+            document.get::<Vec<String>>(&["files"]).unwrap(),
+            Some(vec!["dist".to_string(), "README.md".to_string()])
+        );
+        assert_eq!(document.get::<String>(&["engines", "npm"]).unwrap(), None);
+        assert!(document.get::<u32>(&["name"]).is_err());
+        assert_eq!(document.keys(&[]).len(), 5);
+    }
+
+    #[test]
+    fn set_keeps_layout_and_order() {
+        let mut document = parse(PACKAGE_JSON);
+
+        document.set(&["version"], &"1.1.0").unwrap();
+        document.set(&["scripts", "test"], &"vitest").unwrap();
+        document.set(&["engines", "pnpm"], &">=9").unwrap();
+        document
+            .set(
+                &["dependencies"],
+                &HashMap::from([("react".to_string(), "^18".to_string())]),
+            )
+            .unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            r#"{
+    "name": "demo",
+    "version" : "1.1.0",
+    "scripts": {
+        "build": "tsc",
+        "test": "vitest"
+    },
+    "files": ["dist", "README.md"],
+    "engines": {"node": ">=18", "pnpm": ">=9"},
+    "dependencies": {
+        "react": "^18"
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn set_creates_missing_parents_with_crlf() {
+        let mut document = parse("{\r\n\t\"name\": \"crlf\"\r\n}\r\n");
+
+        document
+            .set(&["workspaces", "packages"], &["packages/*"])
+            .unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "{\r\n\t\"name\": \"crlf\",\r\n\t\"workspaces\": {\r\n\t\t\"packages\": [\r\n\t\t\t\"packages/*\"\r\n\t\t]\r\n\t}\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn remove_fixes_commas() {
+        let mut document = parse(PACKAGE_JSON);
+
+        assert_eq!(
+            document.remove(&["engines"]),
+            Some(serde_json::json!({ "node": ">=18" }))
+        );
+        document.remove(&["scripts", "build"]);
+        document.remove(&["name"]);
+        assert_eq!(document.remove(&["missing", "field"]), None);
+
+        assert_eq!(
+            document.to_string(),
+            r#"{
+    "version" : "1.0.0",
+    "scripts": {},
+    "files": ["dist", "README.md"]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn set_on_a_non_object_fails() {
+        let mut document = parse(PACKAGE_JSON);
+        assert!(document.set(&["name", "first"], &"x").is_err());
+        assert_eq!(document.to_string(), PACKAGE_JSON);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 
 use colored::*;
 use thiserror::Error;
@@ -9,6 +9,10 @@ pub enum SnmError {
     #[error("Silent exit")]
     SilentExit,
 
+    // 已经打印了审计结果，只需要退出码
+    #[error("Found {count} vulnerable packages")]
+    VulnerabilitiesFound { count: usize },
+
     #[error("Customer")]
     Error(String),
 
@@ -32,9 +36,144 @@ pub enum SnmError {
 
     #[error("Unsupported {name}@{version}")]
     UnsupportedPackageManager { name: String, version: String },
+
+    #[error("Timed out after {seconds}s waiting for install lock {lock_file_path}")]
+    InstallLockTimeout {
+        lock_file_path: String,
+        seconds: u64,
+    },
+
+    #[error("Failed to {action} {path}: {source}")]
+    Io {
+        action: String,
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Request {url} failed: {message}")]
+    Http {
+        url: String,
+        status: Option<u16>,
+        message: String,
+    },
+
+    #[error("Invalid JSON in {file_path} at line {line} column {column}: {message}")]
+    JsonParse {
+        file_path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("Invalid response from {url}: {message}")]
+    InvalidResponse { url: String, message: String },
+
+    #[error("Invalid archive {file_path}: {message}")]
+    Archive { file_path: String, message: String },
+
+    #[error("Invalid certificate {file_path}: {message}")]
+    InvalidCertificate { file_path: String, message: String },
+
+    #[error("Checksum of {file_path} expected {expect} but received {actual}")]
+    ChecksumMismatch {
+        file_path: String,
+        expect: String,
+        actual: String,
+    },
+}
+
+impl SnmError {
+    /// `fs::remove_file(&p).map_err(SnmError::io("remove", &p))?`
+    pub fn io<P: AsRef<Path>>(action: &str, path: P) -> impl FnOnce(std::io::Error) -> SnmError {
+        let action = action.to_string();
+        let path = path.as_ref().display().to_string();
+        move |source| SnmError::Io {
+            action,
+            path,
+            source,
+        }
+    }
+
+    pub fn http(url: &str) -> impl FnOnce(reqwest::Error) -> SnmError {
+        let url = url.to_string();
+        move |error| SnmError::Http {
+            url,
+            status: error.status().map(|status| status.as_u16()),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn json<P: AsRef<Path>>(file_path: P) -> impl FnOnce(serde_json::Error) -> SnmError {
+        let file_path = file_path.as_ref().display().to_string();
+        move |error| {
+            let (line, column) = (error.line(), error.column());
+            // serde_json appends the position to its message, it is rendered separately
+            let message = error.to_string();
+            let message = message
+                .trim_end_matches(&format!(" at line {} column {}", line, column))
+                .to_string();
+            SnmError::JsonParse {
+                file_path,
+                line,
+                column,
+                message,
+            }
+        }
+    }
+
+    /// A body that arrived fine but does not decode, e.g. an HTML error page.
+    pub fn invalid_response(url: &str) -> impl FnOnce(serde_json::Error) -> SnmError {
+        let url = url.to_string();
+        move |error| SnmError::InvalidResponse {
+            url,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn archive<P: AsRef<Path>>(file_path: P) -> impl FnOnce(std::io::Error) -> SnmError {
+        let file_path = file_path.as_ref().display().to_string();
+        move |error| SnmError::Archive {
+            file_path,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn prompt(error: dialoguer::Error) -> SnmError {
+        SnmError::Error(format!(
+            "Interactive prompt failed ({}). In a non-interactive shell set SNM_NODE_INSTALL_STRATEGY / SNM_PACKAGE_MANAGER_INSTALL_STRATEGY to auto or panic.",
+            error
+        ))
+    }
+
+    /// Process exit code. These are part of the CLI contract, scripts match on them,
+    /// so never renumber an existing variant.
+    ///
+    /// | code | meaning                                      |
+    /// |------|----------------------------------------------|
+    /// | 1    | generic / configuration error                |
+    /// | 3    | `snm audit` found vulnerable packages        |
+    /// | 65   | invalid data (json, response, archive, hash) |
+    /// | 69   | network or remote resource unavailable       |
+    /// | 74   | local filesystem error                       |
+    /// | 75   | temporary failure, retrying may succeed      |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SnmError::JsonParse { .. }
+            | SnmError::InvalidResponse { .. }
+            | SnmError::Archive { .. }
+            | SnmError::ChecksumMismatch { .. } => 65,
+            SnmError::Http { .. } | SnmError::ResourceNotFound { .. } => 69,
+            SnmError::Io { .. } => 74,
+            SnmError::InstallLockTimeout { .. } => 75,
+            SnmError::VulnerabilitiesFound { .. } => 3,
+            _ => 1,
+        }
+    }
 }
 
 pub fn handle_snm_error(error: SnmError) {
+    let exit_code = error.exit_code();
     match error {
         SnmError::NotFoundPackageJsonBinProperty { file_path } => {
             crate::println_error!(
@@ -78,10 +217,120 @@ pub fn handle_snm_error(error: SnmError) {
             )
         }
 
-        SnmError::SilentExit => {}
+        SnmError::InstallLockTimeout {
+            lock_file_path,
+            seconds,
+        } => {
+            crate::println_error!(
+                "Timed out after {}s waiting for another snm process to finish installing. If no other install is running, remove {} and retry.",
+                seconds,
+                lock_file_path.bright_red()
+            )
+        }
+
+        SnmError::Io {
+            action,
+            path,
+            source,
+        } => {
+            let hint = match source.kind() {
+                std::io::ErrorKind::PermissionDenied => {
+                    "Check the permissions of this path, or point SNM_BASE_DIR to a writable directory."
+                }
+                std::io::ErrorKind::NotFound => "Make sure the path exists.",
+                _ => "Check the disk space and that no other process is using this path.",
+            };
+            crate::println_error!(
+                "Failed to {} {}: {}. {}",
+                action,
+                path.bright_red(),
+                source,
+                hint.bright_black()
+            )
+        }
+
+        SnmError::Http {
+            url,
+            status,
+            message,
+        } => {
+            let hint = match status {
+                Some(401) | Some(403) => "The registry rejected the request, check your credentials.",
+                Some(_) => "The server answered with an error, retry later or use another mirror.",
+                None => "Check your network connection, proxy settings or configure a mirror, see SNM_NODEJS_DIST_URL_KEY and SNM_NPM_REGISTRY_HOST.",
+            };
+            crate::println_error!(
+                "Request {} failed{}: {}. {}",
+                url.bright_red(),
+                status.map_or(String::new(), |status| format!(" with status {}", status)),
+                message,
+                hint.bright_black()
+            )
+        }
+
+        SnmError::JsonParse {
+            file_path,
+            line,
+            column,
+            message,
+        } => {
+            crate::println_error!(
+                "Invalid JSON in {}:{}:{}: {}. {}",
+                file_path.bright_red(),
+                line,
+                column,
+                message,
+                "Fix the syntax error at the position above.".bright_black()
+            )
+        }
+
+        SnmError::InvalidResponse { url, message } => {
+            crate::println_error!(
+                "Invalid response from {}: {}. {}",
+                url.bright_red(),
+                message,
+                "The server or mirror did not answer with the expected data, check the url or use another mirror."
+                    .bright_black()
+            )
+        }
+
+        SnmError::Archive { file_path, message } => {
+            crate::println_error!(
+                "Invalid archive {}: {}. {}",
+                file_path.bright_red(),
+                message,
+                "The download may be corrupted, delete it and retry.".bright_black()
+            )
+        }
+
+        SnmError::ChecksumMismatch {
+            file_path,
+            expect,
+            actual,
+        } => {
+            crate::println_error!(
+                "File {} verification failed, expected {} but received {}. {}",
+                file_path.bright_red(),
+                expect,
+                actual,
+                "The corrupted download was removed, retry the command.".bright_black()
+            )
+        }
+
+        SnmError::InvalidCertificate { file_path, message } => {
+            crate::println_error!(
+                "Invalid certificate {}: {}. {}",
+                file_path.bright_red(),
+                message,
+                "Check SNM_CA_BUNDLE, SNM_CLIENT_CERT and SNM_CLIENT_KEY point to PEM files."
+                    .bright_black()
+            )
+        }
+
+        SnmError::SilentExit | SnmError::VulnerabilitiesFound { .. } => {}
         SnmError::Error(message) => {
             crate::println_error!("{}", message)
         }
     }
-    std::process::exit(1);
+    std::process::exit(exit_code);
 }
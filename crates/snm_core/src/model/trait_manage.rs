@@ -2,18 +2,26 @@ use std::path::PathBuf;
 
 use async_trait::async_trait;
 
-use super::{trait_shared_behavior::SharedBehaviorTrait, trait_shim::ShimTrait, SnmError};
+use super::{
+    output::VersionItem, trait_shared_behavior::SharedBehaviorTrait, trait_shim::ShimTrait,
+    SnmError,
+};
 
 #[async_trait(?Send)]
 pub trait ManageTrait: SharedBehaviorTrait {
     fn get_shim_trait(&self) -> Box<dyn ShimTrait>;
 
-    fn get_download_url(&self, v: &str) -> String;
+    /// Download urls of `v`, one per configured mirror, the primary first.
+    fn get_download_url_vec(&self, v: &str) -> Vec<String>;
 
     fn get_downloaded_file_path_buf(&self, v: &str) -> PathBuf;
 
     fn get_downloaded_dir_path_buf(&self, v: &str) -> PathBuf;
 
+    /// Lock held while `v` is installed, named after the tool so node, npm and
+    /// pnpm of the same version don't wait for each other.
+    fn get_lock_file_path_buf(&self, v: &str) -> PathBuf;
+
     fn get_runtime_dir_path_buf(&self, v: &str) -> PathBuf;
 
     fn get_runtime_dir_for_default_path_buf(&self, v: &str) -> PathBuf;
@@ -29,17 +37,22 @@ pub trait ManageTrait: SharedBehaviorTrait {
 
     fn get_host(&self) -> Option<String>;
 
-    async fn show_list(&self, dir_tuple: &(Vec<String>, Option<String>)) -> Result<(), SnmError>;
+    async fn get_list(
+        &self,
+        dir_tuple: &(Vec<String>, Option<String>),
+    ) -> Result<Vec<VersionItem>, SnmError>;
 
-    async fn show_list_remote(
+    async fn get_list_remote(
         &self,
         dir_tuple: &(Vec<String>, Option<String>),
         all: bool,
-    ) -> Result<(), SnmError>;
+    ) -> Result<Vec<VersionItem>, SnmError>;
 
     fn decompress_download_file(
         &self,
         input_file_path_buf: &PathBuf,
         output_dir_path_buf: &PathBuf,
     ) -> Result<(), SnmError>;
+
+    fn check_staging_dir(&self, v: &str, staging_dir_path_buf: &PathBuf) -> Result<(), SnmError>;
 }
@@ -23,4 +23,9 @@ pub trait ShimTrait: SharedBehaviorTrait {
         &self,
         tuple: &(Vec<String>, Option<String>),
     ) -> Result<String, SnmError>;
+
+    /// Runs before the shim hands over to `version`, an error stops the shim.
+    fn check_advisory(&self, _version: &str) -> Result<(), SnmError> {
+        Ok(())
+    }
 }
@@ -0,0 +1,368 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+
+use super::{PackageJson, SnmError};
+use crate::utils::yaml;
+
+/// A package of a monorepo, found through `pnpm-workspace.yaml` or the
+/// `workspaces` field of the root `package.json` (npm and yarn).
+#[derive(Debug)]
+pub struct WorkspacePackage {
+    /// `name` of its package.json, the relative dir when it has none.
+    pub name: String,
+    pub dir: PathBuf,
+    /// Dir relative to the workspace root, always with `/`.
+    pub relative_dir: String,
+    pub package_json: PackageJson,
+}
+
+#[derive(Debug)]
+pub struct Workspace {
+    pub root_dir: PathBuf,
+    /// Sorted by relative dir, the root package is not part of it.
+    pub package_vec: Vec<WorkspacePackage>,
+}
+
+impl Workspace {
+    /// The nearest workspace root at or above `start_dir`.
+    pub fn find(start_dir: &Path) -> Result<Option<Self>, SnmError> {
+        for dir in start_dir.ancestors() {
+            if let Some(pattern_vec) = read_workspace_patterns(dir)? {
+                return Self::from_patterns(dir, &pattern_vec).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn from_patterns(root_dir: &Path, pattern_vec: &[String]) -> Result<Self, SnmError> {
+        let (exclude_vec, include_vec): (Vec<&String>, Vec<&String>) = pattern_vec
+            .iter()
+            .partition(|pattern| pattern.starts_with('!'));
+
+        let exclude_vec = exclude_vec
+            .into_iter()
+            .filter_map(|pattern| Pattern::new(&normalize(&pattern[1..])).ok())
+            .collect::<Vec<_>>();
+
+        let mut dir_set = HashSet::new();
+
+        for pattern in include_vec {
+            let full_pattern = root_dir
+                .join(normalize(pattern))
+                .join("package.json")
+                .display()
+                .to_string();
+
+            let path_vec = glob::glob(&full_pattern).map_err(|error| {
+                SnmError::Error(format!("Invalid workspace pattern {}: {}", pattern, error))
+            })?;
+
+            for package_json_path_buf in path_vec.flatten() {
+                let Some(dir) = package_json_path_buf.parent() else {
+                    continue;
+                };
+                let Ok(relative) = dir.strip_prefix(root_dir) else {
+                    continue;
+                };
+                let relative_dir = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                let ignored = relative_dir.is_empty()
+                    || relative_dir.split('/').any(|part| part == "node_modules")
+                    || exclude_vec
+                        .iter()
+                        .any(|exclude| exclude.matches(&relative_dir));
+
+                if !ignored {
+                    dir_set.insert(relative_dir);
+                }
+            }
+        }
+
+        let mut relative_dir_vec: Vec<String> = dir_set.into_iter().collect();
+        relative_dir_vec.sort();
+
+        let package_vec = relative_dir_vec
+            .into_iter()
+            .map(|relative_dir| {
+                let dir = root_dir.join(&relative_dir);
+                let package_json = PackageJson::from_file_path(&dir.join("package.json"))?;
+                Ok(WorkspacePackage {
+                    name: package_json
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| relative_dir.to_string()),
+                    dir,
+                    relative_dir,
+                    package_json,
+                })
+            })
+            .collect::<Result<Vec<_>, SnmError>>()?;
+
+        Ok(Self {
+            root_dir: root_dir.to_path_buf(),
+            package_vec,
+        })
+    }
+
+    /// Packages whose name or relative dir matches one of `filter_vec`, e.g.
+    /// `@scope/*`, `app` or `./packages/*`. An empty filter selects every package.
+    pub fn filter(&self, filter_vec: &[String]) -> Result<Vec<&WorkspacePackage>, SnmError> {
+        let pattern_vec = filter_vec
+            .iter()
+            .map(|filter| {
+                Pattern::new(&normalize(filter)).map_err(|error| {
+                    SnmError::Error(format!("Invalid filter {}: {}", filter, error))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(self
+            .package_vec
+            .iter()
+            .filter(|package| {
+                pattern_vec.is_empty()
+                    || pattern_vec.iter().any(|pattern| {
+                        pattern.matches(&package.name) || pattern.matches(&package.relative_dir)
+                    })
+            })
+            .collect())
+    }
+
+    /// For each package of `package_vec`, the names of the packages of `package_vec`
+    /// it depends on.
+    pub fn internal_dependencies<'a>(
+        &self,
+        package_vec: &[&'a WorkspacePackage],
+    ) -> HashMap<&'a str, Vec<&'a str>> {
+        let name_set: HashSet<&str> = package_vec
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect();
+
+        package_vec
+            .iter()
+            .map(|package| {
+                let mut dependency_vec: Vec<&'a str> = package
+                    .package_json
+                    .dependency_names()
+                    .into_iter()
+                    .filter_map(|name| name_set.get(name.as_str()).copied())
+                    .filter(|name| *name != package.name)
+                    .collect();
+                dependency_vec.sort();
+                dependency_vec.dedup();
+                (package.name.as_str(), dependency_vec)
+            })
+            .collect()
+    }
+
+    /// `package_vec` with dependencies before their dependents, ties keep their
+    /// order. Fails on a dependency cycle.
+    pub fn topological_sort<'a>(
+        &self,
+        package_vec: &[&'a WorkspacePackage],
+    ) -> Result<Vec<&'a WorkspacePackage>, SnmError> {
+        let dependency_map = self.internal_dependencies(package_vec);
+        let mut done_set: HashSet<&str> = HashSet::new();
+        let mut pending_vec: Vec<&'a WorkspacePackage> = package_vec.to_vec();
+        let mut sorted_vec = vec![];
+
+        while !pending_vec.is_empty() {
+            let ready_index = pending_vec.iter().position(|package| {
+                dependency_map
+                    .get(package.name.as_str())
+                    .is_none_or(|dependency_vec| {
+                        dependency_vec.iter().all(|name| done_set.contains(name))
+                    })
+            });
+
+            let Some(ready_index) = ready_index else {
+                return Err(SnmError::Error(format!(
+                    "Workspace dependency cycle between {}",
+                    pending_vec
+                        .iter()
+                        .map(|package| package.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            };
+
+            let package = pending_vec.remove(ready_index);
+            done_set.insert(package.name.as_str());
+            sorted_vec.push(package);
+        }
+
+        Ok(sorted_vec)
+    }
+}
+
+fn normalize(pattern: &str) -> String {
+    pattern
+        .trim()
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn read_workspace_patterns(dir: &Path) -> Result<Option<Vec<String>>, SnmError> {
+    let pnpm_workspace_path_buf = dir.join("pnpm-workspace.yaml");
+    if pnpm_workspace_path_buf.is_file() {
+        return Ok(Some(
+            read_pnpm_workspace_patterns(&pnpm_workspace_path_buf)?.unwrap_or_default(),
+        ));
+    }
+
+    let package_json_path_buf = dir.join("package.json");
+    if package_json_path_buf.is_file() {
+        let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
+        if let Some(workspaces) = package_json.workspaces {
+            return Ok(Some(workspaces.patterns().to_vec()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// `packages` of a `pnpm-workspace.yaml`, `None` when the file has none.
+pub fn read_pnpm_workspace_patterns(path: &Path) -> Result<Option<Vec<String>>, SnmError> {
+    let content = read_to_string(path).map_err(SnmError::io("read", path))?;
+    let document = yaml::parse(&content).map_err(|message| {
+        SnmError::Error(format!("Can not parse {}: {}", path.display(), message))
+    })?;
+    Ok(document.get("packages").map(|packages| {
+        packages
+            .items()
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write_package(root: &Path, relative_dir: &str, content: &str) {
+        let dir = root.join(relative_dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("package.json"), content).unwrap();
+    }
+
+    fn names(package_vec: &[&WorkspacePackage]) -> Vec<String> {
+        package_vec
+            .iter()
+            .map(|package| package.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn find_npm_workspaces_from_a_nested_dir() {
+        let root = tempfile::tempdir().unwrap();
+        write_package(
+            root.path(),
+            "",
+            r#"{"name":"root","workspaces":["packages/*","!packages/ignored"]}"#,
+        );
+        write_package(root.path(), "packages/app", r#"{"name":"app"}"#);
+        write_package(root.path(), "packages/ignored", r#"{"name":"ignored"}"#);
+        write_package(root.path(), "packages/unnamed", "{}");
+        write_package(
+            root.path(),
+            "packages/app/node_modules/dep",
+            r#"{"name":"dep"}"#,
+        );
+
+        let workspace = Workspace::find(&root.path().join("packages/app"))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(workspace.root_dir, root.path());
+        assert_eq!(
+            names(&workspace.filter(&[]).unwrap()),
+            vec!["app", "packages/unnamed"]
+        );
+        assert_eq!(
+            names(&workspace.filter(&["./packages/un*".to_string()]).unwrap()),
+            vec!["packages/unnamed"]
+        );
+    }
+
+    #[test]
+    fn topological_sort_puts_dependencies_first() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("pnpm-workspace.yaml"),
+            "packages:\n  - 'packages/*'\n",
+        )
+        .unwrap();
+        write_package(
+            root.path(),
+            "packages/a-app",
+            r#"{"name":"@demo/app","dependencies":{"@demo/ui":"workspace:*","react":"18"}}"#,
+        );
+        write_package(
+            root.path(),
+            "packages/b-ui",
+            r#"{"name":"@demo/ui","devDependencies":{"@demo/utils":"workspace:^"}}"#,
+        );
+        write_package(root.path(), "packages/c-utils", r#"{"name":"@demo/utils"}"#);
+
+        let workspace = Workspace::find(root.path()).unwrap().unwrap();
+        let package_vec = workspace.filter(&["@demo/*".to_string()]).unwrap();
+
+        assert_eq!(
+            names(&workspace.topological_sort(&package_vec).unwrap()),
+            vec!["@demo/utils", "@demo/ui", "@demo/app"]
+        );
+    }
+
+    #[test]
+    fn topological_sort_fails_on_a_cycle() {
+        let root = tempfile::tempdir().unwrap();
+        write_package(root.path(), "", r#"{"workspaces":{"packages":["*"]}}"#);
+        write_package(root.path(), "a", r#"{"name":"a","dependencies":{"b":"*"}}"#);
+        write_package(root.path(), "b", r#"{"name":"b","dependencies":{"a":"*"}}"#);
+
+        let workspace = Workspace::find(root.path()).unwrap().unwrap();
+        let package_vec = workspace.filter(&[]).unwrap();
+
+        assert!(workspace.topological_sort(&package_vec).is_err());
+    }
+
+    #[test]
+    fn parse_pnpm_workspace_yaml() {
+        let content = r#"
+packages:
+  # all packages
+  - 'packages/*'
+  - "apps/**"
+  - '!**/test/**' # no tests
+catalog:
+  - not-a-package
+"#;
+
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("pnpm-workspace.yaml");
+        fs::write(&path, content).unwrap();
+
+        assert_eq!(
+            read_pnpm_workspace_patterns(&path).unwrap(),
+            Some(vec![
+                "packages/*".to_string(),
+                "apps/**".to_string(),
+                "!**/test/**".to_string()
+            ])
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::model::{package_json::PackageManager, PackageJsonDocument, SnmError};
+
+// bun 没有 snm 能安装的版本，它的 lock 文件不参与推断
+const INFERRED_LOCK_FILE_VEC: [&str; 4] = [
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+];
+
+// lockfileVersion 和 __metadata 都在文件开头，不需要读完整个 lock 文件
+const HEADER_LINE_COUNT: usize = 32;
+
+/// Infers the package manager of `dir` from its lock file when package.json has no
+/// `packageManager`. The version is the last release known to write that lock file
+/// format, so a project keeps installing with a compatible tool.
+pub fn detect_package_manager(dir: &Path) -> Result<Option<PackageManager>, SnmError> {
+    let mut detected_vec: Vec<(&str, PackageManager)> = vec![];
+
+    for lock_file in INFERRED_LOCK_FILE_VEC {
+        let lock_file_path_buf = dir.join(lock_file);
+        if !lock_file_path_buf.is_file() {
+            continue;
+        }
+        let package_manager = infer_from_lock_file(lock_file, &lock_file_path_buf)?;
+        if detected_vec
+            .iter()
+            .all(|(_, detected)| detected.name != package_manager.name)
+        {
+            detected_vec.push((lock_file, package_manager));
+        }
+    }
+
+    if detected_vec.len() > 1 {
+        return Err(SnmError::Error(format!(
+            "Multiple package manager lock files found in {}: {} , Please remove the unnecessary ones or set packageManager in package.json.",
+            dir.display(),
+            detected_vec
+                .iter()
+                .map(|(lock_file, _)| *lock_file)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    Ok(detected_vec
+        .pop()
+        .map(|(_, package_manager)| package_manager))
+}
+
+fn infer_from_lock_file(lock_file: &str, path: &Path) -> Result<PackageManager, SnmError> {
+    let header = read_header(path)?;
+    let (name, version) = match lock_file {
+        "pnpm-lock.yaml" => ("pnpm", pnpm_version(&header)),
+        "yarn.lock" => ("yarn", yarn_version(&header)),
+        _ => ("npm", npm_version(&header)),
+    };
+
+    Ok(PackageManager {
+        raw: format!("{}@{}", name, version),
+        name: name.to_string(),
+        version: version.to_string(),
+        hash: None,
+    })
+}
+
+fn read_header(path: &Path) -> Result<String, SnmError> {
+    let file = File::open(path).map_err(SnmError::io("open", path))?;
+    let mut header = String::new();
+    for line in BufReader::new(file).lines().take(HEADER_LINE_COUNT) {
+        header.push_str(&line.map_err(SnmError::io("read", path))?);
+        header.push('\n');
+    }
+    Ok(header)
+}
+
+fn npm_version(header: &str) -> &'static str {
+    // npm 把 lockfileVersion 写在 name 和 version 之后
+    let lockfile_version = header
+        .split_once("\"lockfileVersion\"")
+        .and_then(|(_, rest)| {
+            rest.trim_start_matches([':', ' ', '\t'])
+                .split(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse::<u64>()
+                .ok()
+        });
+
+    match lockfile_version {
+        None | Some(1) => "6.14.18",
+        Some(2) => "8.19.4",
+        _ => "10.9.2",
+    }
+}
+
+fn pnpm_version(header: &str) -> &'static str {
+    let lockfile_version = header
+        .lines()
+        .find_map(|line| line.strip_prefix("lockfileVersion:"))
+        .map(|value| value.trim().trim_matches(['\'', '"']))
+        .unwrap_or_default();
+
+    match lockfile_version.split('.').next().unwrap_or_default() {
+        "5" if lockfile_version == "5.4" => "7.33.7",
+        "5" => "6.35.1",
+        "6" => "8.15.9",
+        _ => "9.15.4",
+    }
+}
+
+fn yarn_version(header: &str) -> &'static str {
+    // yarn 1 的 lock 文件没有 __metadata
+    let Some(metadata) = header.split("__metadata:").nth(1) else {
+        return "1.22.22";
+    };
+
+    let metadata_version = metadata
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("version:"))
+        .and_then(|value| value.trim().trim_matches(['\'', '"']).parse::<u32>().ok())
+        .unwrap_or_default();
+
+    match metadata_version {
+        0..=4 => "2.4.3",
+        5..=6 => "3.8.7",
+        _ => "4.5.3",
+    }
+}
+
+/// Sets `"packageManager": "<raw>"` in package.json, replacing the value in place or
+/// appending the field. The rest of the file is left as it is.
+pub fn write_package_manager(package_json_path: &Path, raw: &str) -> Result<(), SnmError> {
+    let mut document = PackageJsonDocument::from_file_path(package_json_path)?;
+    document.set(&["packageManager"], &raw)?;
+    document.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, read_to_string};
+
+    use super::*;
+
+    fn detect(file_vec: &[(&str, &str)]) -> Result<Option<String>, SnmError> {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in file_vec {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        detect_package_manager(dir.path())
+            .map(|package_manager| package_manager.map(|package_manager| package_manager.raw))
+    }
+
+    #[test]
+    fn infer_version_from_lock_file_format() {
+        let cases = [
+            (
+                "package-lock.json",
+                r#"{"lockfileVersion": 3}"#,
+                "npm@10.9.2",
+            ),
+            (
+                "package-lock.json",
+                r#"{"lockfileVersion": 2}"#,
+                "npm@8.19.4",
+            ),
+            (
+                "npm-shrinkwrap.json",
+                r#"{"lockfileVersion": 1}"#,
+                "npm@6.14.18",
+            ),
+            ("pnpm-lock.yaml", "lockfileVersion: '9.0'\n", "pnpm@9.15.4"),
+            ("pnpm-lock.yaml", "lockfileVersion: '6.0'\n", "pnpm@8.15.9"),
+            ("pnpm-lock.yaml", "lockfileVersion: 5.4\n", "pnpm@7.33.7"),
+            ("yarn.lock", "# yarn lockfile v1\n", "yarn@1.22.22"),
+            (
+                "yarn.lock",
+                "__metadata:\n  version: 8\n  cacheKey: 10c0\n",
+                "yarn@4.5.3",
+            ),
+            (
+                "package-lock.json",
+                "{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\",\n  \"lockfileVersion\": 2,\n  \"requires\": true\n",
+                "npm@8.19.4",
+            ),
+        ];
+
+        for (lock_file, content, expected) in cases {
+            assert_eq!(
+                detect(&[(lock_file, content)]).unwrap().as_deref(),
+                Some(expected),
+                "{}",
+                lock_file
+            );
+        }
+    }
+
+    #[test]
+    fn conflicting_lock_files_are_an_error() {
+        assert_eq!(detect(&[]).unwrap(), None);
+        assert_eq!(detect(&[("bun.lockb", "")]).unwrap(), None);
+        assert!(detect(&[("bun.lock", "{}"), ("yarn.lock", "")]).is_ok());
+        assert!(detect(&[("package-lock.json", "{}"), ("npm-shrinkwrap.json", "{}")]).is_ok());
+        assert!(detect(&[("package-lock.json", "{}"), ("yarn.lock", "")]).is_err());
+    }
+
+    #[test]
+    fn write_package_manager_keeps_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("package.json");
+
+        fs::write(
+            &path,
+            "{\n    \"name\": \"demo\",\n    \"private\": true\n}\n",
+        )
+        .unwrap();
+        write_package_manager(&path, "pnpm@9.15.4").unwrap();
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "{\n    \"name\": \"demo\",\n    \"private\": true,\n    \"packageManager\": \"pnpm@9.15.4\"\n}\n"
+        );
+
+        fs::write(
+            &path,
+            "{\"packageManager\" : \"pnpm@9.15.4\", \"name\": \"demo\"}",
+        )
+        .unwrap();
+        write_package_manager(&path, "pnpm@9.15.5+sha512.ab").unwrap();
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "{\"packageManager\" : \"pnpm@9.15.5+sha512.ab\", \"name\": \"demo\"}"
+        );
+
+        fs::write(&path, "{}").unwrap();
+        write_package_manager(&path, "npm@10.9.2").unwrap();
+        assert_eq!(
+            read_to_string(&path).unwrap(),
+            "{\n  \"packageManager\": \"npm@10.9.2\"\n}"
+        );
+    }
+}
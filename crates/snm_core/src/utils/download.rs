@@ -1,7 +1,13 @@
+use crate::config::{Npmrc, SnmConfig};
+use crate::model::output::OutputFormat;
 use crate::model::SnmError;
+use crate::print_warning;
+use crate::utils::http::http_client;
+use crate::utils::mirror::{is_mirror_failure, keep_most_relevant_error, MirrorHealth};
 use colored::*;
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressDrawTarget};
+use reqwest::header::AUTHORIZATION;
 use std::path::Path;
 use std::time::Duration;
 use tokio::io::AsyncWriteExt;
@@ -17,6 +23,7 @@ pub enum WriteStrategy {
 pub struct DownloadBuilder {
     retries: u8,
     write_strategy: WriteStrategy,
+    timeout: Option<Duration>,
 }
 
 impl DownloadBuilder {
@@ -24,6 +31,7 @@ impl DownloadBuilder {
         Self {
             retries: 0,
             write_strategy: WriteStrategy::WriteAfterDelete,
+            timeout: Some(Duration::from_secs(1)),
         }
     }
 
@@ -37,6 +45,12 @@ impl DownloadBuilder {
         self
     }
 
+    /// Timeout of the whole request, `None` for downloads too big to finish in it.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     pub async fn download<P: AsRef<Path>>(
         &mut self,
         download_url: &str,
@@ -64,14 +78,61 @@ impl DownloadBuilder {
                             );
                         }
                         sleep(Duration::from_millis((self.retries + 10).into())).await;
+
+                        if attempts > self.retries {
+                            return Err(e);
+                        }
                     }
                 },
             }
         }
-        Err(SnmError::Error(format!(
-            "Download {} failed after {} attempts",
-            download_url, self.retries
-        )))
+        Err(SnmError::Http {
+            url: download_url.to_string(),
+            status: None,
+            message: format!("failed after {} attempts", self.retries + 1),
+        })
+    }
+
+    /// Download the same file from the first mirror that serves it. `url_vec` is in
+    /// configured order, the primary first, and is re-ordered by [`MirrorHealth`].
+    ///
+    /// The caller verifies the checksum against the primary, a fallback only
+    /// provides the bytes.
+    pub async fn download_from_mirrors<P: AsRef<Path>>(
+        &mut self,
+        url_vec: &[String],
+        abs_path: P,
+    ) -> Result<P, SnmError> {
+        let mut health = MirrorHealth::load();
+        let sorted_url_vec = health.sort(url_vec);
+        let mut last_error = None;
+
+        for (index, download_url) in sorted_url_vec.iter().enumerate() {
+            match self.download(download_url, abs_path.as_ref()).await {
+                Ok(_) => {
+                    health.report_success(download_url);
+                    return Ok(abs_path);
+                }
+                Err(e) => {
+                    // 本地错误换镜像也没用
+                    if matches!(e, SnmError::Io { .. } | SnmError::Error(_)) {
+                        return Err(e);
+                    }
+                    if is_mirror_failure(&e) {
+                        health.report_failure(download_url);
+                    }
+                    if index + 1 < sorted_url_vec.len() {
+                        print_warning!(
+                            "Download from {} failed, trying the next mirror",
+                            download_url.bright_black()
+                        );
+                    }
+                    keep_most_relevant_error(&mut last_error, e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(SnmError::Error("no mirror configured".to_string())))
     }
 
     pub async fn original_download<P: AsRef<Path>>(
@@ -89,10 +150,8 @@ impl DownloadBuilder {
                     )));
                 }
                 WriteStrategy::WriteAfterDelete => {
-                    std::fs::remove_file(&abs_path_ref).expect(
-                        format!("download remove file error {:?}", &abs_path_ref.display())
-                            .as_str(),
-                    );
+                    std::fs::remove_file(abs_path_ref)
+                        .map_err(SnmError::io("remove", abs_path_ref))?;
                 }
                 WriteStrategy::Nothing => {
                     // 如果选择不覆盖已存在的文件，则直接返回成功
@@ -103,17 +162,20 @@ impl DownloadBuilder {
 
         if let Some(parent) = abs_path_ref.parent() {
             if !parent.exists() {
-                std::fs::create_dir_all(parent).map_err(|_| {
-                    SnmError::Error(format!("create dir error {}", &parent.display()))
-                })?;
+                std::fs::create_dir_all(parent).map_err(SnmError::io("create dir", parent))?;
             }
 
-            let response = reqwest::Client::new()
-                .get(download_url)
-                .timeout(Duration::from_secs(1))
-                .send()
-                .await
-                .expect(format!("download error {}", &download_url).as_str());
+            let mut request = http_client()?.get(download_url);
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+
+            // 私有 registry 的 tarball 需要带上 .npmrc 里的凭证
+            if let Some(authorization) = Npmrc::load().get_authorization(download_url) {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+
+            let response = request.send().await.map_err(SnmError::http(download_url))?;
 
             let response_status = response.status();
 
@@ -124,50 +186,66 @@ impl DownloadBuilder {
             }
 
             if !response_status.is_success() {
-                return Err(SnmError::Error(format!(
-                    "download error {}",
-                    response_status.as_str()
-                )));
+                return Err(SnmError::Http {
+                    url: download_url.to_string(),
+                    status: Some(response_status.as_u16()),
+                    message: response_status.to_string(),
+                });
             }
 
             let total_size = response.content_length();
 
-            let mut file = tokio::fs::File::create(abs_path_ref).await.expect(
-                format!("download create file error {:?}", &abs_path_ref.display()).as_str(),
-            );
+            let mut file = tokio::fs::File::create(abs_path_ref)
+                .await
+                .map_err(SnmError::io("create", abs_path_ref))?;
 
-            let mut stream = response.bytes_stream();
+            // 写到一半失败时删掉残缺文件，避免下一次尝试被 WriteStrategy::Nothing 当成已下载
+            let result = async {
+                let mut stream = response.bytes_stream();
 
-            let progress_bar = ProgressBar::with_draw_target(
-                Some(total_size.unwrap_or(0)),
-                ProgressDrawTarget::stdout(),
-            );
+                // keep stdout clean for `--output json`
+                let draw_target = if SnmConfig::new().get_output_format() == OutputFormat::Json {
+                    ProgressDrawTarget::stderr()
+                } else {
+                    ProgressDrawTarget::stdout()
+                };
 
-            progress_bar.set_style(
-                indicatif::ProgressStyle::with_template(
-                    "{spinner:.green} [{elapsed_precise}] {bar:25.green/white.dim} {bytes}/{total_bytes} {wide_msg:.dim}",
-                )
-                .unwrap()
-                .progress_chars("━━"),
-            );
+                let progress_bar =
+                    ProgressBar::with_draw_target(Some(total_size.unwrap_or(0)), draw_target);
 
-            progress_bar.set_message(download_url.to_string());
+                progress_bar.set_style(
+                    indicatif::ProgressStyle::with_template(
+                        "{spinner:.green} [{elapsed_precise}] {bar:25.green/white.dim} {bytes}/{total_bytes} {wide_msg:.dim}",
+                    )
+                    .unwrap()
+                    .progress_chars("━━"),
+                );
 
-            while let Some(chunk) = stream.next().await {
-                let chunk = chunk.expect("download stream chunk error");
+                progress_bar.set_message(download_url.to_string());
 
-                file.write_all(&chunk).await.expect(
-                    format!("download write file error {:?}", &abs_path_ref.display()).as_str(),
-                );
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.map_err(SnmError::http(download_url))?;
 
-                progress_bar.inc(chunk.len() as u64);
-            }
+                    file.write_all(&chunk)
+                        .await
+                        .map_err(SnmError::io("write", abs_path_ref))?;
+
+                    progress_bar.inc(chunk.len() as u64);
+                }
 
-            file.flush().await.expect(
-                format!("download flush file error {:?}", &abs_path_ref.display()).as_str(),
-            );
+                file.flush()
+                    .await
+                    .map_err(SnmError::io("flush", abs_path_ref))?;
 
-            progress_bar.finish();
+                progress_bar.finish();
+                Ok::<(), SnmError>(())
+            }
+            .await;
+
+            if result.is_err() {
+                let _ = std::fs::remove_file(abs_path_ref);
+            }
+            result?;
         }
 
         Ok(abs_path)
@@ -177,4 +255,52 @@ impl DownloadBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use snm_test_support::{block_on, Fixtures, MockServer};
+
+    #[test]
+    fn download_writes_file() {
+        let server = MockServer::start(Fixtures::new().route("/hello.txt", "hello"));
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("nested").join("hello.txt");
+
+        block_on(
+            DownloadBuilder::new().download(&format!("{}/hello.txt", server.url()), &file_path),
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "hello");
+    }
+
+    #[test]
+    fn download_404_is_not_retried() {
+        let server = MockServer::start(Fixtures::new());
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = block_on(
+            DownloadBuilder::new()
+                .retries(3)
+                .download(&format!("{}/missing", server.url()), dir.path().join("f")),
+        );
+
+        assert!(matches!(result, Err(SnmError::ResourceNotFound { .. })));
+        assert_eq!(server.requests(), vec!["/missing".to_string()]);
+    }
+
+    #[test]
+    fn download_keeps_existing_file_with_nothing_strategy() {
+        let server = MockServer::start(Fixtures::new().route("/f", "remote"));
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("f");
+        std::fs::write(&file_path, "local").unwrap();
+
+        block_on(
+            DownloadBuilder::new()
+                .write_strategy(WriteStrategy::Nothing)
+                .download(&format!("{}/f", server.url()), &file_path),
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "local");
+        assert!(server.requests().is_empty());
+    }
 }
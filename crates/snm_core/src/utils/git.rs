@@ -0,0 +1,81 @@
+use std::{path::Path, process::Command};
+
+use crate::model::SnmError;
+
+/// Runs the local `git` in `dir` and returns its stdout without the trailing
+/// newline, leading spaces are kept since `status --porcelain` starts with them.
+pub fn git(dir: &Path, args: &[&str]) -> Result<String, SnmError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(SnmError::io("run git in", dir))?;
+
+    if !output.status.success() {
+        return Err(SnmError::Error(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+pub fn is_repository(dir: &Path) -> bool {
+    git(dir, &["rev-parse", "--is-inside-work-tree"]).is_ok_and(|output| output == "true")
+}
+
+/// Tracked files with uncommitted changes, untracked files don't count.
+pub fn get_dirty_file_vec(dir: &Path) -> Result<Vec<String>, SnmError> {
+    Ok(
+        git(dir, &["status", "--porcelain", "--untracked-files=no"])?
+            .lines()
+            .map(|line| line.get(3..).unwrap_or(line).to_string())
+            .collect(),
+    )
+}
+
+pub fn tag_exists(dir: &Path, tag: &str) -> bool {
+    git(
+        dir,
+        &[
+            "rev-parse",
+            "--quiet",
+            "--verify",
+            &format!("refs/tags/{}", tag),
+        ],
+    )
+    .is_ok()
+}
+
+/// The newest `v*` tag reachable from HEAD.
+pub fn get_latest_version_tag(dir: &Path) -> Option<String> {
+    git(dir, &["describe", "--tags", "--abbrev=0", "--match", "v*"])
+        .ok()
+        .filter(|tag| !tag.is_empty())
+}
+
+/// `(hash, message)` of the commits after `since` ( all commits when `None` ) that
+/// touched `path`, newest first.
+pub fn get_commit_vec(
+    dir: &Path,
+    since: Option<&str>,
+    path: &Path,
+) -> Result<Vec<(String, String)>, SnmError> {
+    let range = since
+        .map(|since| format!("{}..HEAD", since))
+        .unwrap_or("HEAD".to_string());
+    let path = path.display().to_string();
+    let output = git(dir, &["log", "--format=%H%x1f%B%x1e", &range, "--", &path])?;
+
+    Ok(output
+        .split('\x1e')
+        .filter_map(|record| {
+            let (hash, message) = record.trim().split_once('\x1f')?;
+            Some((hash.to_string(), message.trim().to_string()))
+        })
+        .collect())
+}
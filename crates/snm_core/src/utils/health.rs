@@ -2,10 +2,17 @@ use std::env::current_dir;
 
 use crate::model::SnmError;
 
-pub static LOCK_FILE_VEC: [&'static str; 3] = ["package-lock.json", "pnpm-lock.yaml", "yarn.lock"];
+pub static LOCK_FILE_VEC: [&str; 6] = [
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lockb",
+    "bun.lock",
+];
 
 pub fn check_multi_lock_file() -> Result<Vec<String>, SnmError> {
-    let dir = current_dir().expect("get current dir error.");
+    let dir = current_dir().map_err(SnmError::io("get current dir", "."))?;
 
     let exists_vec = LOCK_FILE_VEC
         .iter()
@@ -0,0 +1,120 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
+
+use crate::{config::SnmConfig, model::SnmError};
+
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// The one `reqwest::Client` every snm request goes through, built on first use
+/// from the network settings of [`SnmConfig`]:
+///
+/// - `SNM_PROXY`, otherwise `HTTPS_PROXY` / `HTTP_PROXY` / `ALL_PROXY`, `NO_PROXY` is honored by both
+/// - `SNM_CA_BUNDLE`, extra PEM certificates trusted on top of the system store
+/// - `SNM_CLIENT_CERT` + `SNM_CLIENT_KEY`, PEM client certificate and PKCS#8 key
+/// - `SNM_USER_AGENT`
+pub fn http_client() -> Result<&'static Client, SnmError> {
+    if let Some(client) = HTTP_CLIENT.get() {
+        return Ok(client);
+    }
+
+    let client = build_http_client(&SnmConfig::new())?;
+
+    // 并发初始化时以先写入的为准
+    Ok(HTTP_CLIENT.get_or_init(|| client))
+}
+
+/// A fresh client from `snm_config`, [`http_client`] builds it once and shares it.
+pub fn build_http_client(snm_config: &SnmConfig) -> Result<Client, SnmError> {
+    let mut builder = Client::builder().user_agent(snm_config.get_user_agent());
+
+    // 没有 SNM_PROXY 时 reqwest 自己读取 HTTPS_PROXY / NO_PROXY
+    if let Some(proxy_url) = snm_config.get_proxy() {
+        let proxy = Proxy::all(&proxy_url)
+            .map_err(|error| {
+                SnmError::Error(format!("Invalid SNM_PROXY {}: {}", proxy_url, error))
+            })?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ca_bundle_path_buf) = snm_config.get_ca_bundle_path_buf() {
+        for certificate in read_certificate_vec(&ca_bundle_path_buf)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some((cert_path_buf, key_path_buf)) = snm_config.get_client_cert_path_buf() {
+        let cert = fs::read(&cert_path_buf).map_err(SnmError::io("read", &cert_path_buf))?;
+        let key = fs::read(&key_path_buf).map_err(SnmError::io("read", &key_path_buf))?;
+        let identity = Identity::from_pkcs8_pem(&cert, &key).map_err(|error| {
+            SnmError::InvalidCertificate {
+                file_path: cert_path_buf.display().to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        builder = builder.identity(identity);
+    }
+
+    builder
+        .build()
+        .map_err(|error| SnmError::Error(format!("Build http client error: {}", error)))
+}
+
+// 一个 bundle 文件里可能有多张证书，逐张解析
+fn read_certificate_vec(path: &Path) -> Result<Vec<Certificate>, SnmError> {
+    let content = fs::read_to_string(path).map_err(SnmError::io("read", path))?;
+
+    let end_marker = "-----END CERTIFICATE-----";
+    let certificate_vec = content
+        .split_inclusive(end_marker)
+        .filter(|block| block.contains("-----BEGIN CERTIFICATE-----"))
+        .map(|block| {
+            Certificate::from_pem(block.trim().as_bytes()).map_err(|error| {
+                SnmError::InvalidCertificate {
+                    file_path: path.display().to_string(),
+                    message: error.to_string(),
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if certificate_vec.is_empty() {
+        return Err(SnmError::InvalidCertificate {
+            file_path: path.display().to_string(),
+            message: "no PEM certificate found".to_string(),
+        });
+    }
+
+    Ok(certificate_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn read_every_certificate_of_a_bundle() {
+        let certificate_vec = read_certificate_vec(&fixture("ca_bundle.pem")).unwrap();
+
+        assert_eq!(certificate_vec.len(), 2);
+    }
+
+    #[test]
+    fn reject_a_bundle_without_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path_buf = dir.path().join("empty.pem");
+        fs::write(&path_buf, "not a certificate").unwrap();
+
+        let result = read_certificate_vec(&path_buf);
+
+        assert!(matches!(result, Err(SnmError::InvalidCertificate { .. })));
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use colored::*;
+use fs4::FileExt;
+use tokio::time::sleep;
+
+use crate::{model::SnmError, print_warning};
+
+/// Advisory lock held while a tool version is being downloaded, verified and extracted.
+/// Released when dropped. Waiting for another process yields to the runtime
+/// instead of blocking its thread.
+pub struct InstallLock {
+    file: File,
+}
+
+impl InstallLock {
+    pub async fn acquire<P: AsRef<Path>>(path: P, timeout: Duration) -> Result<Self, SnmError> {
+        let path_buf = path.as_ref().to_path_buf();
+
+        if let Some(parent) = path_buf.parent() {
+            fs::create_dir_all(parent).map_err(SnmError::io("create dir", parent))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path_buf)
+            .map_err(SnmError::io("open", &path_buf))?;
+
+        let start = Instant::now();
+        let mut warned = false;
+
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(_) => return Ok(Self { file }),
+                Err(error) if error.kind() == fs4::lock_contended_error().kind() => {
+                    if start.elapsed() >= timeout {
+                        return Err(SnmError::InstallLockTimeout {
+                            lock_file_path: path_buf.display().to_string(),
+                            seconds: timeout.as_secs(),
+                        });
+                    }
+
+                    if !warned {
+                        print_warning!(
+                            "Another snm process is installing the same version, waiting up to {}s for {}\n",
+                            timeout.as_secs(),
+                            path_buf.display().to_string().bright_black()
+                        );
+                        warned = true;
+                    }
+
+                    sleep(Duration::from_millis(200)).await;
+                }
+                Err(error) => return Err(SnmError::io("lock", &path_buf)(error)),
+            }
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snm_test_support::block_on;
+
+    #[test]
+    fn second_lock_waits_until_the_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path_buf = dir.path().join("node@20.11.1.lock");
+
+        block_on(async {
+            let lock = InstallLock::acquire(&path_buf, Duration::from_secs(1))
+                .await
+                .unwrap();
+
+            let result = InstallLock::acquire(&path_buf, Duration::from_millis(300)).await;
+            assert!(matches!(result, Err(SnmError::InstallLockTimeout { .. })));
+
+            // 别的工具同一个版本号互不影响
+            InstallLock::acquire(dir.path().join("npm@20.11.1.lock"), Duration::ZERO)
+                .await
+                .unwrap();
+
+            drop(lock);
+            InstallLock::acquire(&path_buf, Duration::ZERO)
+                .await
+                .unwrap();
+        });
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::SnmConfig, model::SnmError, print_warning, utils::http::http_client};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct MirrorState {
+    failures: u32,
+    last_failure: Option<u64>,
+}
+
+/// Failure history of every mirror snm talked to, keyed by origin
+/// (`scheme://host:port`) and persisted in `{SNM_BASE_DIR}/mirror_health.json`.
+///
+/// A mirror that failed within `SNM_MIRROR_COOLDOWN` seconds is tried after the
+/// healthy ones, the configured order is kept otherwise.
+pub struct MirrorHealth {
+    file_path_buf: PathBuf,
+    cooldown: Duration,
+    state_map: HashMap<String, MirrorState>,
+}
+
+impl MirrorHealth {
+    pub fn load() -> Self {
+        let snm_config = SnmConfig::new();
+        let file_path_buf = snm_config.get_mirror_health_file_path_buf();
+
+        // 健康记录只是优化，读不到或者损坏都当成全部健康
+        let state_map = fs::read_to_string(&file_path_buf)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            file_path_buf,
+            cooldown: snm_config.get_mirror_cooldown(),
+            state_map,
+        }
+    }
+
+    /// Healthy urls first in their configured order, then cooling down ones, least
+    /// recently failed first.
+    pub fn sort(&self, url_vec: &[String]) -> Vec<String> {
+        let now = now();
+        let cooling_down = |url: &String| {
+            self.state_map
+                .get(&origin(url))
+                .and_then(|state| state.last_failure)
+                .filter(|last_failure| now.saturating_sub(*last_failure) < self.cooldown.as_secs())
+        };
+
+        let (mut cooling_vec, healthy_vec): (Vec<_>, Vec<_>) =
+            url_vec.iter().partition(|url| cooling_down(url).is_some());
+
+        cooling_vec.sort_by_key(|url| cooling_down(url));
+
+        healthy_vec
+            .into_iter()
+            .chain(cooling_vec)
+            .cloned()
+            .collect()
+    }
+
+    pub fn report_success(&mut self, url: &str) {
+        if self.state_map.remove(&origin(url)).is_some() {
+            self.save();
+        }
+    }
+
+    pub fn report_failure(&mut self, url: &str) {
+        let state = self.state_map.entry(origin(url)).or_default();
+        state.failures += 1;
+        state.last_failure = Some(now());
+        self.save();
+    }
+
+    fn save(&self) {
+        let Ok(content) = serde_json::to_string_pretty(&self.state_map) else {
+            return;
+        };
+
+        // 多个 snm 进程可能同时写，先写临时文件再 rename
+        let tmp_file_path_buf = self
+            .file_path_buf
+            .with_extension(format!("json.{}", std::process::id()));
+        if fs::write(&tmp_file_path_buf, content).is_ok()
+            && fs::rename(&tmp_file_path_buf, &self.file_path_buf).is_err()
+        {
+            let _ = fs::remove_file(&tmp_file_path_buf);
+        }
+    }
+}
+
+/// `https://registry.npmmirror.com/npm/-/npm-9.9.2.tgz` -> `https://registry.npmmirror.com`
+pub fn origin(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed.origin().ascii_serialization(),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Only failures of the remote count against a mirror, a 404 may just be a mirror
+/// that has not synced the version yet and local io errors are not its fault.
+pub fn is_mirror_failure(error: &SnmError) -> bool {
+    matches!(error, SnmError::Http { .. })
+}
+
+/// GET the first url that answers, in [`MirrorHealth`] order.
+///
+/// Meant for metadata (`index.json`, `schedule.json`) only. Checksums must come
+/// from the primary or the pinned source, never from a fallback.
+pub async fn get_text_from_mirrors(url_vec: &[String]) -> Result<String, SnmError> {
+    let mut health = MirrorHealth::load();
+    let mut last_error = None;
+
+    let sorted_url_vec = health.sort(url_vec);
+
+    for (index, url) in sorted_url_vec.iter().enumerate() {
+        let result = async {
+            let response = http_client()?
+                .get(url)
+                .send()
+                .await
+                .map_err(SnmError::http(url))?;
+
+            let status = response.status();
+            if status.as_u16() == 404 {
+                return Err(SnmError::ResourceNotFound {
+                    download_url: url.to_string(),
+                });
+            }
+            if !status.is_success() {
+                return Err(SnmError::Http {
+                    url: url.to_string(),
+                    status: Some(status.as_u16()),
+                    message: status.to_string(),
+                });
+            }
+
+            response.text().await.map_err(SnmError::http(url))
+        }
+        .await;
+
+        match result {
+            Ok(text) => {
+                health.report_success(url);
+                return Ok(text);
+            }
+            Err(error) => {
+                if is_mirror_failure(&error) {
+                    health.report_failure(url);
+                }
+                if index + 1 < sorted_url_vec.len() {
+                    print_warning!("{} failed, trying the next mirror", url.bright_black());
+                }
+                keep_most_relevant_error(&mut last_error, error);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or(SnmError::Error("no mirror configured".to_string())))
+}
+
+/// Report `ResourceNotFound` only when every mirror said 404, any other failure is
+/// more useful to the user.
+pub fn keep_most_relevant_error(last_error: &mut Option<SnmError>, error: SnmError) {
+    let is_not_found = matches!(error, SnmError::ResourceNotFound { .. });
+    if last_error.is_none() || !is_not_found {
+        *last_error = Some(error);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -1,3 +1,10 @@
+pub mod detect_package_manager;
 pub mod download;
+pub mod git;
 pub mod health;
+pub mod http;
+pub mod lock;
+pub mod mirror;
+pub mod runtime;
 pub mod tarball;
+pub mod yaml;
@@ -0,0 +1,13 @@
+use std::future::Future;
+
+// Shims run without a runtime, the snm cli runs inside `#[tokio::main]`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("build tokio runtime error")
+            .block_on(future),
+    }
+}
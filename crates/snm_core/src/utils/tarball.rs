@@ -1,5 +1,9 @@
 use flate2::read::GzDecoder;
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
 use tar::Archive;
 
 use crate::model::SnmError;
@@ -13,45 +17,30 @@ where
     D: Fn(&PathBuf) -> PathBuf,
 {
     // 打开 tgz 文件
-    let tgz_file = File::open(input_path).expect(
-        format!(
-            "decompress_tgz File::open error {:?}",
-            &input_path.display()
-        )
-        .as_str(),
-    );
+    let tgz_file = File::open(input_path).map_err(SnmError::io("open", input_path))?;
     // 使用 GzDecoder 解压 gzip 文件
     let tar = GzDecoder::new(tgz_file);
     // 创建 Archive 对象以便操作 tar 文件
     let mut archive = Archive::new(tar);
 
     // 从 archive 中解压所有文件到指定路径
-    archive.unpack(output_path).expect(
-        format!(
-            "decompress_tgz archive.unpack error {:?}",
-            &output_path.display()
-        )
-        .as_str(),
-    );
+    archive
+        .unpack(output_path)
+        .map_err(SnmError::archive(input_path))?;
 
     let old_base = get_target_dir(output_path);
 
     let transform = |f: &PathBuf| -> Result<PathBuf, SnmError> {
-        let new_path = f
-            .strip_prefix(&old_base)
-            .expect("decompress_tgz strip_prefix error");
+        let new_path = f.strip_prefix(&old_base).map_err(|_| SnmError::Archive {
+            file_path: input_path.display().to_string(),
+            message: format!("{} is outside of {}", f.display(), old_base.display()),
+        })?;
         Ok(output_path.join(new_path))
     };
 
     rename(&old_base, &transform)?;
 
-    std::fs::remove_dir_all(&old_base).expect(
-        format!(
-            "decompress_tgz remove_dir_all error {:?}",
-            &old_base.display()
-        )
-        .as_str(),
-    );
+    std::fs::remove_dir_all(&old_base).map_err(SnmError::io("remove dir", &old_base))?;
 
     Ok(())
 }
@@ -60,11 +49,10 @@ pub fn rename<T>(dir: &PathBuf, transform: &T) -> Result<(), SnmError>
 where
     T: Fn(&PathBuf) -> Result<PathBuf, SnmError>,
 {
-    let mut entries = std::fs::read_dir(&dir)
-        .expect(format!("rename std::fs::read_dir error {:?}", &dir.display()).as_str());
+    let entries = std::fs::read_dir(dir).map_err(SnmError::io("read dir", dir))?;
 
-    while let Some(entry) = entries.next() {
-        let entry = entry.unwrap();
+    for entry in entries {
+        let entry = entry.map_err(SnmError::io("read dir", dir))?;
 
         let path = entry.path();
 
@@ -73,28 +61,15 @@ where
         } else {
             let from = &path;
 
-            let to = &transform(&from)?;
+            let to = &transform(from)?;
 
             if let Some(parent) = to.parent() {
                 if !parent.exists() {
-                    std::fs::create_dir_all(&parent).expect(
-                        format!(
-                            "rename std::fs::create_dir_all error {:?}",
-                            &parent.display()
-                        )
-                        .as_str(),
-                    );
+                    std::fs::create_dir_all(parent).map_err(SnmError::io("create dir", parent))?;
                 }
             }
 
-            std::fs::rename(&from, &to).expect(
-                format!(
-                    "rename std::fs::rename error from: {:?} to: {:?}",
-                    &from.display(),
-                    &to.display()
-                )
-                .as_str(),
-            );
+            std::fs::rename(from, to).map_err(SnmError::io("move", from))?;
         }
     }
 
@@ -102,45 +77,217 @@ where
 }
 
 pub fn decompress_xz(input_path: &PathBuf, output_path: &PathBuf) -> Result<(), SnmError> {
-    let input_file = File::open(input_path)
-        .expect(format!("decompress_xz File::open error {:?}", &input_path.display()).as_str());
+    let input_file = File::open(input_path).map_err(SnmError::io("open", input_path))?;
 
     let decoder = xz2::read::XzDecoder::new(input_file);
 
     let mut archive = tar::Archive::new(decoder);
 
-    archive.unpack(&output_path).expect(
-        format!(
-            "decompress_xz archive.unpack error {:?}",
-            &output_path.display()
-        )
-        .as_str(),
-    );
+    archive
+        .unpack(output_path)
+        .map_err(SnmError::archive(input_path))?;
 
     let dir = input_path
         .file_name()
         .and_then(|f| f.to_str())
-        .map(|f| f.trim_end_matches(".tar.xz").to_string());
+        .map(|f| f.trim_end_matches(".tar.xz").to_string())
+        .ok_or(SnmError::Archive {
+            file_path: input_path.display().to_string(),
+            message: "archive file name is not valid utf-8".to_string(),
+        })?;
 
-    let old_base = output_path.join(dir.as_ref().unwrap());
+    let old_base = output_path.join(&dir);
 
     let transform = |f: &PathBuf| -> Result<PathBuf, SnmError> {
-        let old_base = output_path.join(dir.as_ref().unwrap());
-        let new_path = f
-            .strip_prefix(&old_base)
-            .expect("decompress_xz strip_prefix error");
+        let new_path = f.strip_prefix(&old_base).map_err(|_| SnmError::Archive {
+            file_path: input_path.display().to_string(),
+            message: format!("{} is outside of {}", f.display(), old_base.display()),
+        })?;
         Ok(output_path.join(new_path))
     };
 
     rename(&old_base, &transform)?;
 
-    std::fs::remove_dir_all(&old_base).expect(
-        format!(
-            "decompress_xz remove_dir_all error {:?}",
-            &old_base.display()
-        )
-        .as_str(),
-    );
+    std::fs::remove_dir_all(&old_base).map_err(SnmError::io("remove dir", &old_base))?;
 
     Ok(())
 }
+
+/// Calls `on_entry` with the name and content of every file of a zip archive,
+/// zip64 included. Only stored and deflated entries are supported, which covers
+/// exports like the OSV advisory dumps.
+pub fn read_zip_entries<F>(input_path: &Path, mut on_entry: F) -> Result<(), SnmError>
+where
+    F: FnMut(&str, &[u8]) -> Result<(), SnmError>,
+{
+    let bytes = std::fs::read(input_path).map_err(SnmError::io("read", input_path))?;
+    let invalid = |message: &str| SnmError::Archive {
+        file_path: input_path.display().to_string(),
+        message: message.to_string(),
+    };
+    let u16_at = |offset: usize| -> Option<usize> {
+        Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?) as usize)
+    };
+    let u32_at = |offset: usize| -> Option<usize> {
+        Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?) as usize)
+    };
+    let u64_at = |offset: usize| -> Option<usize> {
+        usize::try_from(u64::from_le_bytes(
+            bytes.get(offset..offset + 8)?.try_into().ok()?,
+        ))
+        .ok()
+    };
+
+    // end of central directory 在文件尾部，后面最多跟 65535 字节的注释
+    let eocd = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(65536 + 22)
+        .find(|offset| u32_at(*offset) == Some(0x06054b50))
+        .ok_or(invalid("end of central directory not found"))?;
+    let mut entry_count = u16_at(eocd + 10).ok_or(invalid("truncated"))?;
+    let mut offset = u32_at(eocd + 16).ok_or(invalid("truncated"))?;
+
+    // zip64 时这里只是占位，真实数值在 locator 指向的 zip64 end of central directory 里
+    if let Some(locator) = eocd
+        .checked_sub(20)
+        .filter(|locator| u32_at(*locator) == Some(0x07064b50))
+    {
+        let zip64_eocd = u64_at(locator + 8).ok_or(invalid("truncated"))?;
+        if u32_at(zip64_eocd) != Some(0x06064b50) {
+            return Err(invalid("broken zip64 end of central directory"));
+        }
+        entry_count = u64_at(zip64_eocd + 32).ok_or(invalid("truncated"))?;
+        offset = u64_at(zip64_eocd + 48).ok_or(invalid("truncated"))?;
+    }
+
+    for _ in 0..entry_count {
+        if u32_at(offset) != Some(0x02014b50) {
+            return Err(invalid("broken central directory"));
+        }
+        let header = |relative: usize| u16_at(offset + relative).ok_or(invalid("truncated"));
+        let method = header(10)?;
+        let mut compressed_size = u32_at(offset + 20).ok_or(invalid("truncated"))?;
+        let mut uncompressed_size = u32_at(offset + 24).ok_or(invalid("truncated"))?;
+        let (name_len, extra_len, comment_len) = (header(28)?, header(30)?, header(32)?);
+        let mut local_offset = u32_at(offset + 42).ok_or(invalid("truncated"))?;
+        let name = bytes
+            .get(offset + 46..offset + 46 + name_len)
+            .map(String::from_utf8_lossy)
+            .ok_or(invalid("truncated"))?
+            .to_string();
+
+        // zip64 extra field ( 0x0001 ) 按顺序只带上 0xFFFFFFFF 的那几个字段
+        let extra_end = offset + 46 + name_len + extra_len;
+        let mut extra = offset + 46 + name_len;
+        while extra + 4 <= extra_end {
+            let id = u16_at(extra).ok_or(invalid("truncated"))?;
+            let size = u16_at(extra + 2).ok_or(invalid("truncated"))?;
+            if id == 0x0001 {
+                let mut value = extra + 4;
+                for field in [
+                    &mut uncompressed_size,
+                    &mut compressed_size,
+                    &mut local_offset,
+                ] {
+                    if *field == u32::MAX as usize {
+                        *field = u64_at(value)
+                            .filter(|_| value + 8 <= extra + 4 + size)
+                            .ok_or(invalid("truncated zip64 extra field"))?;
+                        value += 8;
+                    }
+                }
+            }
+            extra += 4 + size;
+        }
+        offset = extra_end + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+
+        let data_offset = local_offset
+            + 30
+            + u16_at(local_offset + 26).ok_or(invalid("truncated"))?
+            + u16_at(local_offset + 28).ok_or(invalid("truncated"))?;
+        let data = bytes
+            .get(data_offset..data_offset + compressed_size)
+            .ok_or(invalid("truncated"))?;
+
+        match method {
+            0 => on_entry(&name, data)?,
+            8 => {
+                let mut content = vec![];
+                flate2::read::DeflateDecoder::new(data)
+                    .read_to_end(&mut content)
+                    .map_err(SnmError::archive(input_path))?;
+                on_entry(&name, &content)?;
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "{} uses compression method {}",
+                    name, method
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_stored_and_deflated_zip_entries() {
+        let zip_path_buf = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("entries.zip");
+        let mut entry_vec = vec![];
+
+        read_zip_entries(&zip_path_buf, |name, content| {
+            entry_vec.push((
+                name.to_string(),
+                String::from_utf8_lossy(content).to_string(),
+            ));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            entry_vec,
+            [
+                ("stored.txt".to_string(), "stored\n".to_string()),
+                ("dir/deflated.txt".to_string(), "deflated ".repeat(20)),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_zip64_entries() {
+        // zip64 end of central directory，deflated.txt 的大小和偏移都放在 zip64 extra field 里
+        let zip_path_buf = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("entries_zip64.zip");
+        let mut entry_vec = vec![];
+
+        read_zip_entries(&zip_path_buf, |name, content| {
+            entry_vec.push((
+                name.to_string(),
+                String::from_utf8_lossy(content).to_string(),
+            ));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            entry_vec,
+            [
+                ("stored.txt".to_string(), "stored\n".to_string()),
+                ("dir/deflated.txt".to_string(), "deflated ".repeat(20)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,466 @@
+//! Just enough YAML for `pnpm-lock.yaml`, `pnpm-workspace.yaml` and yarn berry's
+//! `yarn.lock`: block
+//! mappings and sequences, plain or quoted scalars and one line flow collections
+//! like `{integrity: sha512-..., tarball: https://...}`. Anchors, tags, multi line
+//! scalars and multiple documents are not supported.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Yaml {
+    Scalar(String),
+    Map(Vec<(String, Yaml)>),
+    Seq(Vec<Yaml>),
+}
+
+impl Yaml {
+    pub fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entry_vec) => entry_vec
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Entries of a mapping, empty for anything else.
+    pub fn entries(&self) -> &[(String, Yaml)] {
+        match self {
+            Yaml::Map(entry_vec) => entry_vec,
+            _ => &[],
+        }
+    }
+
+    /// Items of a sequence, empty for anything else.
+    pub fn items(&self) -> &[Yaml] {
+        match self {
+            Yaml::Seq(item_vec) => item_vec,
+            _ => &[],
+        }
+    }
+
+    pub fn str_of(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Yaml::as_str)
+    }
+
+    pub fn bool_of(&self, key: &str) -> bool {
+        self.str_of(key) == Some("true")
+    }
+
+    /// The scalar entries of the mapping under `key`, e.g. a `dependencies:` block.
+    pub fn str_map_of(&self, key: &str) -> BTreeMap<String, String> {
+        self.get(key)
+            .map(Yaml::entries)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect()
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    content: &'a str,
+}
+
+pub fn parse(text: &str) -> Result<Yaml, String> {
+    let mut line_vec: Vec<Line> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let content = line.trim();
+            if content.is_empty() || content.starts_with('#') || content == "---" {
+                return None;
+            }
+            Some(Line {
+                number: index + 1,
+                indent: line.len() - line.trim_start().len(),
+                content: content.trim_end(),
+            })
+        })
+        .collect();
+
+    if line_vec.is_empty() {
+        return Ok(Yaml::Map(vec![]));
+    }
+
+    let mut index = 0;
+    let indent = line_vec[0].indent;
+    let value = parse_block(&mut line_vec, &mut index, indent)?;
+    if let Some(line) = line_vec.get(index) {
+        return Err(format!("line {}: unexpected indentation", line.number));
+    }
+    Ok(value)
+}
+
+fn parse_block(line_vec: &mut [Line], index: &mut usize, indent: usize) -> Result<Yaml, String> {
+    if line_vec[*index].content.starts_with('-') && is_seq_item(line_vec[*index].content) {
+        return parse_seq(line_vec, index, indent);
+    }
+
+    let mut entry_vec = vec![];
+    while let Some(line) = line_vec.get(*index) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent {
+            return Err(format!("line {}: unexpected indentation", line.number));
+        }
+
+        let number = line.number;
+        let (key, rest) = split_key(line.content)
+            .ok_or_else(|| format!("line {}: expected `key: value`", number))?;
+        *index += 1;
+
+        let value = if !rest.is_empty() {
+            parse_inline(rest).map_err(|error| format!("line {}: {}", number, error))?
+        } else {
+            match line_vec.get(*index) {
+                Some(next) if next.indent > indent => {
+                    let child_indent = next.indent;
+                    parse_block(line_vec, index, child_indent)?
+                }
+                // 和 key 同一缩进的列表
+                Some(next) if next.indent == indent && is_seq_item(next.content) => {
+                    parse_seq(line_vec, index, indent)?
+                }
+                _ => Yaml::Scalar(String::new()),
+            }
+        };
+        entry_vec.push((key, value));
+    }
+    Ok(Yaml::Map(entry_vec))
+}
+
+fn parse_seq(line_vec: &mut [Line], index: &mut usize, indent: usize) -> Result<Yaml, String> {
+    let mut item_vec = vec![];
+    while let Some(line) = line_vec.get(*index) {
+        if line.indent != indent || !is_seq_item(line.content) {
+            break;
+        }
+        let (number, content) = (line.number, line.content);
+        let rest = content[1..].trim_start();
+
+        if rest.is_empty() {
+            *index += 1;
+            match line_vec.get(*index) {
+                Some(next) if next.indent > indent => {
+                    let child_indent = next.indent;
+                    item_vec.push(parse_block(line_vec, index, child_indent)?);
+                }
+                _ => item_vec.push(Yaml::Scalar(String::new())),
+            }
+        } else if split_key(rest).is_some() {
+            // `- key: value` 开始一个映射，把这一行当作更深缩进的第一行
+            let child_indent = indent + (content.len() - rest.len());
+            line_vec[*index].indent = child_indent;
+            line_vec[*index].content = rest;
+            item_vec.push(parse_block(line_vec, index, child_indent)?);
+        } else {
+            *index += 1;
+            item_vec
+                .push(parse_inline(rest).map_err(|error| format!("line {}: {}", number, error))?);
+        }
+    }
+    Ok(Yaml::Seq(item_vec))
+}
+
+fn is_seq_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// `key: rest` with a plain or quoted key, the `:` must be followed by a space or
+/// end the line.
+fn split_key(content: &str) -> Option<(String, &str)> {
+    let (key, rest) = if content.starts_with(['"', '\'']) {
+        let (key, length) = read_quoted(content).ok()?;
+        (key, content[length..].strip_prefix(':')?)
+    } else {
+        let mut search_from = 0;
+        loop {
+            let position = search_from + content[search_from..].find(':')?;
+            let rest = &content[position + 1..];
+            if rest.is_empty() || rest.starts_with(' ') {
+                break (content[..position].trim_end().to_string(), rest);
+            }
+            search_from = position + 1;
+        }
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((key, rest.trim()))
+}
+
+fn parse_inline(text: &str) -> Result<Yaml, String> {
+    if text.starts_with(['{', '[']) {
+        let mut chars = text.char_indices().peekable();
+        let value = parse_flow(text, &mut chars)?;
+        return match chars.next() {
+            None => Ok(value),
+            Some((position, _)) => Err(format!("unexpected `{}`", &text[position..])),
+        };
+    }
+    if text.starts_with(['"', '\'']) {
+        let (value, length) = read_quoted(text)?;
+        if !text[length..].trim().is_empty() && !text[length..].trim_start().starts_with('#') {
+            return Err(format!("unexpected `{}`", &text[length..]));
+        }
+        return Ok(Yaml::Scalar(value));
+    }
+    let value = match text.find(" #") {
+        Some(position) => &text[..position],
+        None => text,
+    };
+    Ok(Yaml::Scalar(value.trim().to_string()))
+}
+
+type CharIter<'a> = std::iter::Peekable<std::str::CharIndices<'a>>;
+
+fn skip_spaces(chars: &mut CharIter) {
+    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+}
+
+fn parse_flow(text: &str, chars: &mut CharIter) -> Result<Yaml, String> {
+    skip_spaces(chars);
+    match chars.peek().copied() {
+        Some((_, '{')) => {
+            chars.next();
+            let mut entry_vec = vec![];
+            loop {
+                skip_spaces(chars);
+                if chars.next_if(|(_, c)| *c == '}').is_some() {
+                    break;
+                }
+                let key = match parse_flow_scalar(text, chars, true)? {
+                    Yaml::Scalar(key) => key,
+                    _ => return Err("invalid flow mapping key".to_string()),
+                };
+                skip_spaces(chars);
+                if chars.next_if(|(_, c)| *c == ':').is_none() {
+                    return Err(format!("expected `:` after {}", key));
+                }
+                let value = parse_flow(text, chars)?;
+                entry_vec.push((key, value));
+                skip_spaces(chars);
+                if chars.next_if(|(_, c)| *c == ',').is_none() {
+                    skip_spaces(chars);
+                    if chars.next_if(|(_, c)| *c == '}').is_none() {
+                        return Err("expected `,` or `}`".to_string());
+                    }
+                    break;
+                }
+            }
+            Ok(Yaml::Map(entry_vec))
+        }
+        Some((_, '[')) => {
+            chars.next();
+            let mut item_vec = vec![];
+            loop {
+                skip_spaces(chars);
+                if chars.next_if(|(_, c)| *c == ']').is_some() {
+                    break;
+                }
+                item_vec.push(parse_flow(text, chars)?);
+                skip_spaces(chars);
+                if chars.next_if(|(_, c)| *c == ',').is_none() {
+                    skip_spaces(chars);
+                    if chars.next_if(|(_, c)| *c == ']').is_none() {
+                        return Err("expected `,` or `]`".to_string());
+                    }
+                    break;
+                }
+            }
+            Ok(Yaml::Seq(item_vec))
+        }
+        _ => parse_flow_scalar(text, chars, false),
+    }
+}
+
+fn parse_flow_scalar(text: &str, chars: &mut CharIter, is_key: bool) -> Result<Yaml, String> {
+    let Some(&(start, first)) = chars.peek() else {
+        return Err("unexpected end of line".to_string());
+    };
+
+    if first == '"' || first == '\'' {
+        let (value, length) = read_quoted(&text[start..])?;
+        while chars
+            .next_if(|(position, _)| *position < start + length)
+            .is_some()
+        {}
+        return Ok(Yaml::Scalar(value));
+    }
+
+    let mut end = text.len();
+    while let Some(&(position, c)) = chars.peek() {
+        let is_end = match c {
+            ',' | '}' | ']' => true,
+            // 值里可以有 `:`，比如 tarball 的 url，只有 key 后面跟空格的 `:` 才是分隔符
+            ':' => {
+                is_key
+                    && text[position + 1..]
+                        .chars()
+                        .next()
+                        .is_none_or(|next| next == ' ')
+            }
+            _ => false,
+        };
+        if is_end {
+            end = position;
+            break;
+        }
+        chars.next();
+    }
+    Ok(Yaml::Scalar(text[start..end].trim().to_string()))
+}
+
+/// A quoted scalar at the start of `text` and the length it takes.
+fn read_quoted(text: &str) -> Result<(String, usize), String> {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut value = String::new();
+    let mut char_iter = text.char_indices().skip(1);
+
+    while let Some((position, c)) = char_iter.next() {
+        match c {
+            '\'' if quote == '\'' => {
+                if text[position + 1..].starts_with('\'') {
+                    char_iter.next();
+                    value.push('\'');
+                } else {
+                    return Ok((value, position + 1));
+                }
+            }
+            '"' if quote == '"' => return Ok((value, position + 1)),
+            '\\' if quote == '"' => match char_iter.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+    Err(format!("unterminated string {}", text))
+}
+
+/// `value` as a YAML scalar, single quoted when a plain scalar would be read back
+/// differently, e.g. `'@babel/core@7.24.0'`, `'*'` or `'9.0'`.
+pub fn quote(value: &str) -> String {
+    let needs_quote = value.is_empty()
+        || value.starts_with([
+            '@', '!', '&', '*', '%', '`', '|', '>', '\'', '"', '#', '{', '[', ',', '?', ':', '-',
+            ' ',
+        ])
+        || value.ends_with([' ', ':'])
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.contains(['{', '}', '[', ']', ','])
+        || matches!(
+            value,
+            "~" | "null" | "true" | "false" | "yes" | "no" | "on" | "off"
+        )
+        || value.parse::<f64>().is_ok();
+
+    if needs_quote {
+        format!("'{}'", value.replace('\'', "''"))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(value: &str) -> Yaml {
+        Yaml::Scalar(value.to_string())
+    }
+
+    #[test]
+    fn block_and_flow_collections() {
+        let yaml = parse(
+            r#"lockfileVersion: '9.0'
+
+importers:
+
+  .:
+    dependencies:
+      '@types/node':
+        specifier: ^20
+        version: 20.1.0
+
+packages:
+
+  '@types/node@20.1.0':
+    resolution: {integrity: sha512-abc==, tarball: https://example.com/a.tgz}
+    cpu: [x64, arm64]
+    transitivePeerDependencies:
+      - supports-color
+  "lodash@npm:^4.17.21": {}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(yaml.str_of("lockfileVersion"), Some("9.0"));
+        let dependency = yaml
+            .get("importers")
+            .and_then(|importers| importers.get("."))
+            .and_then(|importer| importer.get("dependencies"))
+            .and_then(|dependencies| dependencies.get("@types/node"))
+            .unwrap();
+        assert_eq!(dependency.str_of("version"), Some("20.1.0"));
+
+        let package = yaml
+            .get("packages")
+            .and_then(|packages| packages.get("@types/node@20.1.0"))
+            .unwrap();
+        assert_eq!(
+            package.get("resolution").unwrap(),
+            &Yaml::Map(vec![
+                ("integrity".to_string(), scalar("sha512-abc==")),
+                ("tarball".to_string(), scalar("https://example.com/a.tgz")),
+            ])
+        );
+        assert_eq!(package.get("cpu").unwrap().items().len(), 2);
+        assert_eq!(
+            package.get("transitivePeerDependencies").unwrap(),
+            &Yaml::Seq(vec![scalar("supports-color")])
+        );
+        assert_eq!(
+            yaml.get("packages")
+                .and_then(|packages| packages.get("lodash@npm:^4.17.21")),
+            Some(&Yaml::Map(vec![]))
+        );
+    }
+
+    #[test]
+    fn quote_only_when_needed() {
+        assert_eq!(quote("4.17.21"), "4.17.21");
+        assert_eq!(quote("^1.0.0"), "^1.0.0");
+        assert_eq!(quote("link:../core"), "link:../core");
+        assert_eq!(quote("9.0"), "'9.0'");
+        assert_eq!(quote("@babel/core@7.0.0"), "'@babel/core@7.0.0'");
+        assert_eq!(quote(">=18"), "'>=18'");
+        assert_eq!(quote("*"), "'*'");
+        assert_eq!(quote("it's"), "it's");
+        assert_eq!(quote("'a'"), "'''a'''");
+
+        for value in ["9.0", "@a/b@1.0.0", ">=18", "*", "'a'", "a: b"] {
+            assert_eq!(
+                parse(&format!("key: {}", quote(value)))
+                    .unwrap()
+                    .str_of("key"),
+                Some(value)
+            );
+        }
+    }
+}
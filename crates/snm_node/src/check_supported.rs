@@ -1,11 +1,11 @@
 use regex::Regex;
-use snm_core::{model::SnmError, print_warning};
+use snm_core::{model::SnmError, print_warning, utils::http::http_client};
 use std::time::Duration;
 
 use crate::conditional_compiler::{get_arch, get_os};
 
 pub async fn check_supported(node_version: &str, node_dist_url: &str) -> Result<(), SnmError> {
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let response = client
         .get(node_dist_url)
         .timeout(Duration::from_secs(1))
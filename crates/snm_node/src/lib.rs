@@ -1,5 +1,6 @@
 mod check_supported;
 mod conditional_compiler;
-mod node_model;
+mod node_advisory;
+pub mod node_model;
 mod node_schedule;
 pub mod snm_node;
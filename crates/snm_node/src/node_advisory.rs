@@ -0,0 +1,217 @@
+use std::{collections::HashMap, fmt, fs, path::PathBuf};
+
+use chrono::NaiveDate;
+use colored::*;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::{node_model::NodeModel, node_schedule::NodeSchedule};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub security: bool,
+}
+
+impl From<NodeModel> for Release {
+    fn from(node: NodeModel) -> Self {
+        Self {
+            version: node.version.trim_start_matches('v').to_string(),
+            security: node.security,
+        }
+    }
+}
+
+/// What the node shim knows about node releases, persisted in
+/// `{SNM_BASE_DIR}/node_advisory.json` and considered stale after
+/// `SNM_NODE_ADVISORY_INTERVAL` seconds.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdvisoryCache {
+    /// Last successful refresh.
+    pub checked_at: u64,
+    /// Last background refresh started by the shim.
+    pub attempted_at: u64,
+    pub release_vec: Vec<Release>,
+    pub schedule_vec: Vec<NodeSchedule>,
+    /// version -> when its advisories were last printed
+    pub warned_map: HashMap<String, u64>,
+}
+
+impl AdvisoryCache {
+    pub fn load(file_path_buf: &PathBuf) -> Self {
+        // 缓存只是优化，读不到或者损坏都当成没有
+        fs::read_to_string(file_path_buf)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, file_path_buf: &PathBuf) {
+        let Ok(content) = serde_json::to_string(self) else {
+            return;
+        };
+
+        // 多个 shim 可能同时写，先写临时文件再 rename
+        let tmp_file_path_buf =
+            file_path_buf.with_extension(format!("json.{}", std::process::id()));
+        if fs::write(&tmp_file_path_buf, content).is_ok()
+            && fs::rename(&tmp_file_path_buf, file_path_buf).is_err()
+        {
+            let _ = fs::remove_file(&tmp_file_path_buf);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Advisory {
+    EndOfLife { version: String, end: String },
+    Maintenance { version: String, end: String },
+    SecurityRelease { version: String, latest: String },
+}
+
+impl fmt::Display for Advisory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Advisory::EndOfLife { version, end } => write!(
+                f,
+                "node {} reached end-of-life on {} and no longer gets security fixes.",
+                version.bright_yellow(),
+                end
+            ),
+            Advisory::Maintenance { version, end } => write!(
+                f,
+                "node {} is in maintenance, only critical fixes until end-of-life on {}.",
+                version.bright_yellow(),
+                end
+            ),
+            Advisory::SecurityRelease { version, latest } => write!(
+                f,
+                "node {} is a security release of this line, {} is missing its fixes.",
+                latest.bright_green(),
+                version.bright_yellow()
+            ),
+        }
+    }
+}
+
+/// Advisories for `version` as of `today`. A version whose line is not in the
+/// schedule is not reported, the schedule may simply be older than the release.
+pub fn find_advisories(
+    version: &str,
+    release_vec: &[Release],
+    schedule_vec: &[NodeSchedule],
+    today: NaiveDate,
+) -> Vec<Advisory> {
+    let Ok(current) = Version::parse(version) else {
+        return vec![];
+    };
+
+    let mut advisory_vec = vec![];
+
+    let schedule = schedule_vec.iter().find(|schedule| {
+        schedule
+            .version
+            .as_ref()
+            .and_then(|v| VersionReq::parse(v).ok())
+            .is_some_and(|req| req.matches(&current))
+    });
+
+    if let Some(schedule) = schedule {
+        let parse_date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        let end = parse_date(&schedule.end);
+        let maintenance = schedule.maintenance.as_deref().and_then(parse_date);
+
+        if end.is_some_and(|end| today > end) {
+            advisory_vec.push(Advisory::EndOfLife {
+                version: version.to_string(),
+                end: schedule.end.clone(),
+            });
+        } else if maintenance.is_some_and(|maintenance| today >= maintenance) {
+            advisory_vec.push(Advisory::Maintenance {
+                version: version.to_string(),
+                end: schedule.end.clone(),
+            });
+        }
+    }
+
+    let latest_security = release_vec
+        .iter()
+        .filter(|release| release.security)
+        .filter_map(|release| Version::parse(&release.version).ok())
+        .filter(|release| release.major == current.major && *release > current)
+        .max();
+
+    if let Some(latest) = latest_security {
+        advisory_vec.push(Advisory::SecurityRelease {
+            version: version.to_string(),
+            latest: latest.to_string(),
+        });
+    }
+
+    advisory_vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(version: &str, maintenance: &str, end: &str) -> NodeSchedule {
+        NodeSchedule {
+            start: "2023-04-18".to_string(),
+            end: end.to_string(),
+            maintenance: Some(maintenance.to_string()),
+            lts: None,
+            codename: None,
+            version: Some(version.to_string()),
+        }
+    }
+
+    fn release(version: &str, security: bool) -> Release {
+        Release {
+            version: version.to_string(),
+            security,
+        }
+    }
+
+    #[test]
+    fn end_of_life_maintenance_and_security_releases() {
+        let schedule_vec = vec![
+            schedule("16", "2022-10-18", "2023-09-11"),
+            schedule("20", "2024-10-22", "2026-04-30"),
+        ];
+        let release_vec = vec![
+            release("20.11.0", false),
+            release("20.11.1", true),
+            release("20.12.0", false),
+            release("21.6.2", true),
+        ];
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+        assert_eq!(
+            find_advisories("16.20.2", &release_vec, &schedule_vec, today),
+            vec![Advisory::EndOfLife {
+                version: "16.20.2".to_string(),
+                end: "2023-09-11".to_string()
+            }]
+        );
+        assert_eq!(
+            find_advisories("20.11.0", &release_vec, &schedule_vec, today),
+            vec![
+                Advisory::Maintenance {
+                    version: "20.11.0".to_string(),
+                    end: "2026-04-30".to_string()
+                },
+                Advisory::SecurityRelease {
+                    version: "20.11.0".to_string(),
+                    latest: "20.11.1".to_string()
+                }
+            ]
+        );
+
+        let before_maintenance = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert!(
+            find_advisories("20.12.0", &release_vec, &schedule_vec, before_maintenance).is_empty()
+        );
+    }
+}
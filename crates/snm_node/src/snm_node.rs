@@ -1,6 +1,7 @@
 use crate::conditional_compiler::get_arch;
 use crate::conditional_compiler::get_os;
 use crate::conditional_compiler::get_tarball_ext;
+use crate::node_advisory::{find_advisories, AdvisoryCache, Release};
 use crate::node_model::Lts;
 use crate::node_model::NodeModel;
 use crate::node_schedule::NodeSchedule;
@@ -14,20 +15,30 @@ use semver::Version;
 use semver::VersionReq;
 use sha2::Digest;
 use sha2::Sha256;
+use snm_core::config::snm_config::AdvisoryMode;
+use snm_core::model::output::VersionItem;
 use snm_core::model::trait_manage::ManageTrait;
 use snm_core::model::trait_shared_behavior::SharedBehaviorTrait;
 use snm_core::model::trait_shim::ShimTrait;
-use snm_core::{config::SnmConfig, model::SnmError, utils::tarball::decompress_xz};
+use snm_core::utils::{http::http_client, mirror::get_text_from_mirrors, runtime::block_on};
+use snm_core::{config::SnmConfig, model::SnmError, print_warning, utils::tarball::decompress_xz};
 use std::collections::HashMap;
 use std::env::current_dir;
 use std::fs::read_to_string;
 use std::ops::Not;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{
     fs::File,
     io::{BufReader, Read},
     path::PathBuf,
 };
 
+// 刷新失败后多久再试一次
+const ADVISORY_RETRY_SECS: u64 = 600;
+const ADVISORY_SYNC_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct SnmNode {
     snm_config: SnmConfig,
 }
@@ -39,42 +50,40 @@ impl SnmNode {
         }
     }
 
-    async fn get_node_list_remote(&self) -> Result<Vec<NodeModel>, SnmError> {
-        let host = self.snm_config.get_nodejs_dist_url_prefix();
-        let node_list_url = format!("{}/index.json", host);
-        let node_vec: Vec<NodeModel> = reqwest::get(&node_list_url)
-            .await
-            .map_err(|_| SnmError::Error(format!("fetch {} failed", &node_list_url)))?
-            .json::<Vec<NodeModel>>()
-            .await
-            .map_err(|_| {
-                SnmError::Error(format!("parse {} response to json failed", &node_list_url))
-            })?;
+    pub async fn get_node_list_remote(&self) -> Result<Vec<NodeModel>, SnmError> {
+        let node_list_url_vec: Vec<String> = self
+            .snm_config
+            .get_nodejs_dist_url_vec()
+            .iter()
+            .map(|host| format!("{}/index.json", host))
+            .collect();
+        let node_list = get_text_from_mirrors(&node_list_url_vec).await?;
+        let node_vec: Vec<NodeModel> = serde_json::from_str(&node_list)
+            .map_err(SnmError::invalid_response(&node_list_url_vec.join(", ")))?;
         Ok(node_vec)
     }
 
     async fn get_node_schedule(&self) -> Result<Vec<NodeSchedule>, SnmError> {
-        let host = self.snm_config.get_nodejs_github_resource_host();
+        let node_schedule_url_vec: Vec<String> = self
+            .snm_config
+            .get_nodejs_github_resource_host_vec()
+            .iter()
+            .map(|host| format!("{}/nodejs/Release/main/schedule.json", host))
+            .collect();
 
-        let node_schedule_url = format!("{}/nodejs/Release/main/schedule.json", host);
+        let node_schedule = get_text_from_mirrors(&node_schedule_url_vec).await?;
 
-        let node_schedule_vec: Vec<NodeSchedule> = reqwest::get(&node_schedule_url)
-            .await
-            .map_err(|_| SnmError::Error(format!("fetch {} failed", node_schedule_url)))?
-            .json::<std::collections::HashMap<String, NodeSchedule>>()
-            .await
-            .map_err(|_| {
-                SnmError::Error(format!(
-                    "parse {} response to json failed",
-                    node_schedule_url
-                ))
-            })?
-            .into_iter()
-            .map(|(v, mut schedule)| {
-                schedule.version = Some(v[1..].to_string());
-                schedule
-            })
-            .collect();
+        let node_schedule_vec: Vec<NodeSchedule> =
+            serde_json::from_str::<HashMap<String, NodeSchedule>>(&node_schedule)
+                .map_err(SnmError::invalid_response(
+                    &node_schedule_url_vec.join(", "),
+                ))?
+                .into_iter()
+                .map(|(v, mut schedule)| {
+                    schedule.version = Some(v[1..].to_string());
+                    schedule
+                })
+                .collect();
 
         Ok(node_schedule_vec)
     }
@@ -83,89 +92,112 @@ impl SnmNode {
         &self,
         node_version: &str,
     ) -> Result<HashMap<String, String>, SnmError> {
-        let host = self.snm_config.get_nodejs_dist_url_prefix();
+        // 只信任主源（或 SNM_NODEJS_SHASUM_URL_KEY），不走镜像
+        let host = self.snm_config.get_nodejs_shasum_url_prefix();
         let url = format!("{}/v{}/SHASUMS256.txt", host, node_version);
 
-        let sha256_str = reqwest::get(&url)
+        let sha256_str = http_client()?
+            .get(&url)
+            .send()
             .await
-            .map_err(|_| SnmError::Error(format!("fetch {} failed", url)))?
+            .and_then(|response| response.error_for_status())
+            .map_err(SnmError::http(&url))?
             .text()
             .await
-            .map_err(|_| SnmError::Error(format!("parse {} response to text failed", url)))?;
+            .map_err(SnmError::http(&url))?;
 
         let sha256_map: std::collections::HashMap<String, String> = sha256_str
             .lines()
-            .map(|line| {
+            .filter_map(|line| {
                 let mut iter = line.split_whitespace();
-                let sha256 = iter.next().unwrap();
-                let file = iter.next().unwrap();
-                (file.to_string(), sha256.to_string())
+                let sha256 = iter.next()?;
+                let file = iter.next()?;
+                Some((file.to_string(), sha256.to_string()))
             })
             .collect();
 
         Ok(sha256_map)
     }
 
-    fn show_off_online_node_list(&self, dir_tuple: &(Vec<String>, Option<String>)) {
-        let (dir_vec, default_v) = dir_tuple;
-        for v in dir_vec {
-            let prefix = if Some(v) == default_v.as_ref() {
-                "⛳️"
-            } else {
-                " "
-            };
-            // 标记
-            println!(
-                "{:<2} {}  {}",
-                prefix,
-                v,
-                "Network exception, degraded to offline mode.".bright_black()
-            );
-        }
-    }
-
-    fn show_node_list<F>(&self, node_vec: Vec<NodeModel>, get_tag_fn: F)
-    where
-        F: Fn(&String) -> &str,
-    {
-        for node in node_vec.iter() {
-            let lts = match &node.lts {
-                Lts::Str(s) => s,
-                Lts::Bool(_) => "",
-            };
-
-            let deprecated = node.deprecated.unwrap_or(false);
-
-            let version = if deprecated {
-                format!(
-                    "{:<10} {:<10}",
-                    node.version.bright_black(),
-                    lts.bright_black()
-                )
-            } else {
-                format!(
-                    "{:<10} {:<10}",
-                    node.version.bright_green(),
-                    lts.bright_green()
-                )
-            };
+    /// Fetch the release index and schedule into the advisory cache, done by
+    /// `snm node sync-advisory` which the node shim starts in the background.
+    pub async fn sync_advisory(&self) -> Result<(), SnmError> {
+        let (node_vec, schedule_vec) =
+            try_join!(self.get_node_list_remote(), self.get_node_schedule())?;
 
-            let died = format!("died on {}", node.end.as_deref().unwrap_or("")).bright_black();
+        let file_path_buf = self.snm_config.get_node_advisory_file_path_buf();
+        let mut cache = AdvisoryCache::load(&file_path_buf);
+        cache.release_vec = node_vec.into_iter().map(Release::from).collect();
+        cache.schedule_vec = schedule_vec;
+        cache.checked_at = now_secs();
+        cache.save(&file_path_buf);
+        Ok(())
+    }
 
-            let npm = format!("npm {}", node.npm.as_deref().unwrap_or("None")).bright_black();
+    /// In `warn` mode the shim never waits for the network, stale data is refreshed
+    /// by a detached `snm node sync-advisory` for the next run. In `error` mode stale
+    /// data is refreshed inline with a hard timeout and having no data is an error,
+    /// a CI gate must not pass because the network was down.
+    fn load_advisory_cache(
+        &self,
+        mode: &AdvisoryMode,
+        now: u64,
+    ) -> Result<AdvisoryCache, SnmError> {
+        let file_path_buf = self.snm_config.get_node_advisory_file_path_buf();
+        let mut cache = AdvisoryCache::load(&file_path_buf);
+
+        let interval = self.snm_config.get_node_advisory_interval().as_secs();
+        if now.saturating_sub(cache.checked_at) < interval {
+            return Ok(cache);
+        }
 
-            let openssl =
-                format!("openssl {}", node.openssl.as_deref().unwrap_or("None")).bright_black();
+        if let AdvisoryMode::Warn = mode {
+            // 后台刷新失败时不要每次都重新拉起
+            if now.saturating_sub(cache.attempted_at) >= ADVISORY_RETRY_SECS {
+                cache.attempted_at = now;
+                cache.save(&file_path_buf);
+                spawn_sync_advisory();
+            }
+            return Ok(cache);
+        }
 
-            let desc_width = 22;
+        let synced = block_on(async {
+            tokio::time::timeout(ADVISORY_SYNC_TIMEOUT, self.sync_advisory()).await
+        });
+        if let Ok(Ok(())) = synced {
+            return Ok(AdvisoryCache::load(&file_path_buf));
+        }
 
-            let tag = get_tag_fn(&node.version);
+        if cache.release_vec.is_empty() || cache.schedule_vec.is_empty() {
+            return Err(SnmError::Error(format!(
+                "No node release data to check against, run {} once the network is reachable.",
+                "snm node sync-advisory".bright_green().bold()
+            )));
+        }
+        Ok(cache)
+    }
 
-            // 标记
-            println!(
-                "{:<2} {} {:<desc_width$} {:<desc_width$} {:<desc_width$}",
-                tag, version, died, openssl, npm,
-            );
+    fn to_version_item(
+        &self,
+        node: NodeModel,
+        dir_tuple: &(Vec<String>, Option<String>),
+    ) -> VersionItem {
+        let (dir_vec, default_v) = dir_tuple;
+        let version = node.version.trim_start_matches('v').to_string();
+        VersionItem {
+            installed: dir_vec.contains(&version),
+            default: default_v.as_ref() == Some(&version),
+            lts: match node.lts {
+                Lts::Str(s) => Some(s),
+                Lts::Bool(_) => None,
+            },
+            end: node.end,
+            deprecated: node.deprecated,
+            security: Some(node.security),
+            npm: node.npm,
+            openssl: node.openssl,
+            bundled_node: None,
+            version,
         }
     }
 }
@@ -182,24 +214,34 @@ impl SharedBehaviorTrait for SnmNode {
 
 #[async_trait(?Send)]
 impl ManageTrait for SnmNode {
-    fn get_download_url(&self, v: &str) -> String {
-        let host = self.snm_config.get_nodejs_dist_url_prefix();
-        let download_url = format!(
-            "{}/v{}/node-v{}-{}-{}.{}",
-            &host,
-            &v,
-            &v,
-            get_os(),
-            get_arch(),
-            get_tarball_ext()
-        );
-        download_url
+    fn get_download_url_vec(&self, v: &str) -> Vec<String> {
+        self.snm_config
+            .get_nodejs_dist_url_vec()
+            .iter()
+            .map(|host| {
+                format!(
+                    "{}/v{}/node-v{}-{}-{}.{}",
+                    &host,
+                    &v,
+                    &v,
+                    get_os(),
+                    get_arch(),
+                    get_tarball_ext()
+                )
+            })
+            .collect()
     }
 
     fn get_downloaded_dir_path_buf(&self, v: &str) -> PathBuf {
         self.snm_config.get_download_dir_path_buf().join(v)
     }
 
+    fn get_lock_file_path_buf(&self, v: &str) -> PathBuf {
+        self.snm_config
+            .get_download_dir_path_buf()
+            .join(format!("node@{}.lock", v))
+    }
+
     fn get_downloaded_file_path_buf(&self, v: &str) -> PathBuf {
         self.snm_config
             .get_download_dir_path_buf()
@@ -246,18 +288,16 @@ impl ManageTrait for SnmNode {
         &self,
         downloaded_file_path_buf: &PathBuf,
     ) -> Result<String, SnmError> {
-        let file = File::open(downloaded_file_path_buf).map_err(|_| {
-            SnmError::Error(format!(
-                "open file {} error",
-                downloaded_file_path_buf.display()
-            ))
-        })?;
+        let file = File::open(downloaded_file_path_buf)
+            .map_err(SnmError::io("open", downloaded_file_path_buf))?;
         let mut reader = BufReader::new(file);
         let mut hasher = Sha256::new();
 
         let mut buffer = [0; 1024];
         loop {
-            let n = reader.read(&mut buffer).expect("read error");
+            let n = reader
+                .read(&mut buffer)
+                .map_err(SnmError::io("read", downloaded_file_path_buf))?;
             if n == 0 {
                 break;
             }
@@ -271,7 +311,10 @@ impl ManageTrait for SnmNode {
         None
     }
 
-    async fn show_list(&self, dir_tuple: &(Vec<String>, Option<String>)) -> Result<(), SnmError> {
+    async fn get_list(
+        &self,
+        dir_tuple: &(Vec<String>, Option<String>),
+    ) -> Result<Vec<VersionItem>, SnmError> {
         let (dir_vec, default_v) = dir_tuple;
         if dir_vec.is_empty() {
             return Err(SnmError::Error(format!(
@@ -320,9 +363,7 @@ impl ManageTrait for SnmNode {
                         .find_map(|(req, schedule)| eq_version(req).then_some(schedule));
 
                     {
-                        node.end = node_schedule
-                            .map(|schedule| schedule.end.clone())
-                            .map_or(Some("None".to_string()), Some);
+                        node.end = node_schedule.map(|schedule| schedule.end.clone());
                     }
 
                     {
@@ -340,35 +381,32 @@ impl ManageTrait for SnmNode {
                 })
                 .collect::<Vec<NodeModel>>();
 
-            node_vec.sort_by_cached_key(|v| Version::parse(&v.version[1..]).ok());
+            node_vec.sort_by_cached_key(|v| Version::parse(&v.version).ok());
 
-            if let Some(v) = default_v {
-                self.show_node_list(node_vec, |node_v| {
-                    if node_v == v {
-                        return "⛳️";
-                    } else {
-                        return "";
-                    }
-                });
-            } else {
-                self.show_node_list(node_vec, |_node_v| {
-                    return "";
-                });
-            }
+            Ok(node_vec
+                .into_iter()
+                .map(|node| self.to_version_item(node, dir_tuple))
+                .collect())
         } else {
-            self.show_off_online_node_list(dir_tuple)
+            print_warning!("Network exception, degraded to offline mode.\n");
+
+            Ok(dir_vec
+                .iter()
+                .map(|v| VersionItem {
+                    version: v.to_string(),
+                    installed: true,
+                    default: default_v.as_ref() == Some(v),
+                    ..VersionItem::default()
+                })
+                .collect())
         }
-
-        Ok(())
     }
 
-    async fn show_list_remote(
+    async fn get_list_remote(
         &self,
         dir_tuple: &(Vec<String>, Option<String>),
         all: bool,
-    ) -> Result<(), SnmError> {
-        let (dir_vec, _default_v) = dir_tuple;
-
+    ) -> Result<Vec<VersionItem>, SnmError> {
         let (mut node_vec, node_schedule_vec) =
             try_join!(self.get_node_list_remote(), self.get_node_schedule())?;
 
@@ -400,20 +438,14 @@ impl ManageTrait for SnmNode {
                     }
                 });
             } else {
-                node.end = Some("None".to_string());
+                node.end = None;
                 node.deprecated = Some(true);
             }
         });
 
         node_vec.sort_by_cached_key(|v| Version::parse(&v.version[1..]).ok());
 
-        let mut marking_version: HashMap<String, String> = HashMap::new();
-
-        dir_vec.iter().for_each(|v| {
-            marking_version.insert(v.clone(), v.clone());
-        });
-
-        let node_vec = node_vec
+        let item_vec = node_vec
             .into_iter()
             .filter(|node| {
                 if all {
@@ -424,18 +456,10 @@ impl ManageTrait for SnmNode {
                     false
                 }
             })
-            .collect::<Vec<NodeModel>>();
-
-        self.show_node_list(node_vec, |node_v| {
-            let v = node_v.trim_start_matches("v");
-            if marking_version.contains_key(v) {
-                return "🫐";
-            } else {
-                return "";
-            }
-        });
+            .map(|node| self.to_version_item(node, dir_tuple))
+            .collect::<Vec<VersionItem>>();
 
-        Ok(())
+        Ok(item_vec)
     }
 
     fn decompress_download_file(
@@ -443,13 +467,46 @@ impl ManageTrait for SnmNode {
         input_file_path_buf: &PathBuf,
         output_dir_path_buf: &PathBuf,
     ) -> Result<(), SnmError> {
-        decompress_xz(&input_file_path_buf, &output_dir_path_buf).expect(
-            format!(
-                "decompress_xz {} error",
-                &input_file_path_buf.display().to_string()
-            )
-            .as_str(),
-        );
+        decompress_xz(input_file_path_buf, output_dir_path_buf)
+    }
+
+    fn check_staging_dir(&self, v: &str, staging_dir_path_buf: &PathBuf) -> Result<(), SnmError> {
+        let bin_dir_path_buf = staging_dir_path_buf.join("bin");
+
+        for bin_name in ["node", "npm", "npx"] {
+            let bin_path_buf = bin_dir_path_buf.join(bin_name);
+            if bin_path_buf.exists().not() {
+                return Err(SnmError::Error(format!(
+                    "Install of node {} is incomplete, {} not found.",
+                    v,
+                    bin_path_buf.display()
+                )));
+            }
+        }
+
+        if self.snm_config.get_install_smoke_check() {
+            let node_path_buf = bin_dir_path_buf.join("node");
+            let output = Command::new(&node_path_buf)
+                .arg("--version")
+                .output()
+                .map_err(|_| {
+                    SnmError::Error(format!("run {} --version error", node_path_buf.display()))
+                })?;
+
+            let actual = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            let expect = format!("v{}", v);
+
+            if actual != expect {
+                return Err(SnmError::Error(format!(
+                    "Smoke check of node {} failed, expected {} but {} --version printed {}.",
+                    v,
+                    expect,
+                    node_path_buf.display(),
+                    actual
+                )));
+            }
+        }
+
         Ok(())
     }
 
@@ -461,7 +518,7 @@ impl ManageTrait for SnmNode {
 impl ShimTrait for SnmNode {
     fn get_strict_shim_version(&self) -> Result<String, SnmError> {
         let node_version_path_buf = current_dir()
-            .expect("get current dir failed")
+            .map_err(SnmError::io("get current dir", "."))?
             .join(".node-version");
         if node_version_path_buf.exists().not() {
             return Err(SnmError::Error(format!(
@@ -473,13 +530,7 @@ impl ShimTrait for SnmNode {
             |value: String| value.trim_start_matches(['v', 'V']).trim().to_string();
         let version = read_to_string(&node_version_path_buf)
             .map(version_processor)
-            .expect(
-                format!(
-                    "read_to_string {} error",
-                    &node_version_path_buf.display().to_string()
-                )
-                .as_str(),
-            );
+            .map_err(SnmError::io("read", &node_version_path_buf))?;
         Ok(version)
     }
 
@@ -499,7 +550,7 @@ impl ShimTrait for SnmNode {
                     &version
                 ))
                 .interact()
-                .expect("download_condition Confirm error")),
+                .map_err(SnmError::prompt)?),
             snm_core::config::snm_config::InstallStrategy::Panic => {
                 Err(SnmError::Error(format!("Unsupported version: {}", version)))
             }
@@ -507,6 +558,50 @@ impl ShimTrait for SnmNode {
         }
     }
 
+    fn check_advisory(&self, version: &str) -> Result<(), SnmError> {
+        let mode = self.snm_config.get_node_advisory_mode()?;
+        if let AdvisoryMode::Off = mode {
+            return Ok(());
+        }
+
+        let now = now_secs();
+        let mut cache = self.load_advisory_cache(&mode, now)?;
+
+        let advisory_vec = find_advisories(
+            version,
+            &cache.release_vec,
+            &cache.schedule_vec,
+            Utc::now().date_naive(),
+        );
+        if advisory_vec.is_empty() {
+            return Ok(());
+        }
+
+        if let AdvisoryMode::Error = mode {
+            let message_vec: Vec<String> = advisory_vec.iter().map(ToString::to_string).collect();
+            return Err(SnmError::Error(format!(
+                "{}\nSet {} to run it anyway.",
+                message_vec.join("\n"),
+                "SNM_NODE_ADVISORY=warn".bright_green().bold()
+            )));
+        }
+
+        // 同一个版本每个间隔只提醒一次
+        let interval = self.snm_config.get_node_advisory_interval().as_secs();
+        let warned_at = cache.warned_map.get(version).copied().unwrap_or(0);
+        if now.saturating_sub(warned_at) < interval {
+            return Ok(());
+        }
+
+        for advisory in &advisory_vec {
+            print_warning!("{}\n", advisory);
+        }
+        cache.warned_map.insert(version.to_string(), now);
+        cache.save(&self.snm_config.get_node_advisory_file_path_buf());
+
+        Ok(())
+    }
+
     fn get_runtime_binary_file_path_buf(
         &self,
         bin_name: &str,
@@ -533,3 +628,27 @@ impl ShimTrait for SnmNode {
         }
     }
 }
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// `snm` is installed next to the shims.
+fn spawn_sync_advisory() {
+    let Ok(current_exe) = std::env::current_exe() else {
+        return;
+    };
+    let snm_path_buf = current_exe.with_file_name(format!("snm{}", std::env::consts::EXE_SUFFIX));
+    if snm_path_buf.exists().not() {
+        return;
+    }
+    let _ = Command::new(snm_path_buf)
+        .args(["node", "sync-advisory"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
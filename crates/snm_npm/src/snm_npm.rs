@@ -1,21 +1,22 @@
 use async_trait::async_trait;
-use colored::*;
 use dialoguer::Confirm;
+use reqwest::header::AUTHORIZATION;
 use serde_json::Value;
 use sha1::Digest;
 use sha1::Sha1;
 use snm_core::{
-    config::SnmConfig,
+    config::{Npmrc, SnmConfig},
     model::{
-        trait_manage::ManageTrait, trait_shared_behavior::SharedBehaviorTrait,
-        trait_shim::ShimTrait, PackageJson, SnmError,
+        output::VersionItem, trait_manage::ManageTrait, trait_shared_behavior::SharedBehaviorTrait,
+        trait_shim::ShimTrait, Bin, PackageJson, SnmError,
     },
-    utils::tarball::decompress_tgz,
+    utils::{http::http_client, tarball::decompress_tgz},
 };
 use std::{
     env::current_dir,
     fs::File,
     io::{BufReader, Read},
+    ops::Not,
     path::PathBuf,
 };
 
@@ -38,6 +39,59 @@ impl SnmNpm {
             prefix: prefix.to_string(),
         }
     }
+
+    /// With `SNM_BUNDLED_NPM=true` the npm of the node in use runs, unless the
+    /// packageManager of the current dir pins npm.
+    pub fn use_bundled_npm(&self) -> Result<bool, SnmError> {
+        if self.prefix != "npm" || self.snm_config.get_bundled_npm().not() {
+            return Ok(false);
+        }
+
+        let package_json_path_buf = current_dir()
+            .map_err(SnmError::io("get current dir", "."))?
+            .join("package.json");
+        if package_json_path_buf.exists().not() {
+            return Ok(true);
+        }
+
+        let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
+        if package_json.package_manager.is_none() {
+            return Ok(true);
+        }
+        Ok(package_json.parse_package_manager()?.name != "npm")
+    }
+
+    /// `(npm version, node version)` of the npm inside every installed node.
+    fn get_bundled_vec(&self) -> Vec<(String, String)> {
+        let node_bin_dir_path_buf = self.snm_config.get_node_bin_dir_path_buf();
+        let Ok(read_dir) = node_bin_dir_path_buf.read_dir() else {
+            return vec![];
+        };
+
+        let mut bundled_vec: Vec<(String, String)> = read_dir
+            .filter_map(|dir_entry| dir_entry.ok())
+            .filter_map(|dir_entry| {
+                let node_v = dir_entry.file_name().into_string().ok()?;
+                // staging dirs and the default link
+                if node_v.starts_with('.') || node_v.ends_with("-default") {
+                    return None;
+                }
+                let package_json_path_buf = dir_entry
+                    .path()
+                    .join("lib")
+                    .join("node_modules")
+                    .join("npm")
+                    .join("package.json");
+                let npm_v = PackageJson::from_file_path(&package_json_path_buf)
+                    .ok()?
+                    .version?;
+                Some((npm_v, node_v))
+            })
+            .collect();
+
+        bundled_vec.sort_by_cached_key(|(_, node_v)| semver::Version::parse(node_v).ok());
+        bundled_vec
+    }
 }
 
 impl SharedBehaviorTrait for SnmNpm {
@@ -52,12 +106,17 @@ impl SharedBehaviorTrait for SnmNpm {
 
 #[async_trait(?Send)]
 impl ManageTrait for SnmNpm {
-    fn get_download_url(&self, v: &str) -> String {
-        let npm_registry = self.snm_config.get_npm_registry_host();
-        format!(
-            "{}/{}/-/{}-{}.tgz",
-            npm_registry, &self.prefix, &self.prefix, &v
-        )
+    fn get_download_url_vec(&self, v: &str) -> Vec<String> {
+        self.snm_config
+            .get_package_registry_host_vec(&self.prefix)
+            .iter()
+            .map(|npm_registry| {
+                format!(
+                    "{}/{}/-/{}-{}.tgz",
+                    npm_registry, &self.prefix, &self.prefix, &v
+                )
+            })
+            .collect()
     }
 
     fn get_downloaded_file_path_buf(&self, v: &str) -> PathBuf {
@@ -75,6 +134,12 @@ impl ManageTrait for SnmNpm {
             .join(&v)
     }
 
+    fn get_lock_file_path_buf(&self, v: &str) -> PathBuf {
+        self.snm_config
+            .get_download_dir_path_buf()
+            .join(format!("{}@{}.lock", &self.prefix, &v))
+    }
+
     fn get_runtime_dir_path_buf(&self, v: &str) -> PathBuf {
         self.snm_config
             .get_node_modules_dir_path_buf()
@@ -96,15 +161,23 @@ impl ManageTrait for SnmNpm {
     }
 
     async fn get_expect_shasum(&self, v: &str) -> Result<String, SnmError> {
-        let npm_registry = self.snm_config.get_npm_registry_host();
+        // 只信任主源（或 SNM_NPM_SHASUM_REGISTRY_HOST），不走镜像
+        let npm_registry = self.snm_config.get_npm_shasum_registry_host(&self.prefix);
         let download_url = format!("{}/{}/{}", npm_registry, &self.prefix, &v);
 
-        let value: Value = reqwest::get(&download_url)
+        let mut request = http_client()?.get(&download_url);
+        if let Some(authorization) = Npmrc::load().get_authorization(&download_url) {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+
+        let value: Value = request
+            .send()
             .await
-            .expect(format!("download error {}", &download_url).as_str())
+            .and_then(|response| response.error_for_status())
+            .map_err(SnmError::http(&download_url))?
             .json()
             .await
-            .expect(format!("json error {}", &download_url).as_str());
+            .map_err(SnmError::http(&download_url))?;
 
         let x = value
             .get("dist")
@@ -120,13 +193,8 @@ impl ManageTrait for SnmNpm {
         &self,
         downloaded_file_path_buf: &PathBuf,
     ) -> Result<String, SnmError> {
-        let file = File::open(downloaded_file_path_buf).expect(
-            format!(
-                "get_actual_shasum File::open error {:?}",
-                &downloaded_file_path_buf.display()
-            )
-            .as_str(),
-        );
+        let file = File::open(downloaded_file_path_buf)
+            .map_err(SnmError::io("open", downloaded_file_path_buf))?;
         let mut reader = BufReader::new(file);
         let mut hasher = Sha1::new();
 
@@ -134,7 +202,7 @@ impl ManageTrait for SnmNpm {
         loop {
             let n = reader
                 .read(&mut buffer)
-                .expect("get_actual_shasum read error");
+                .map_err(SnmError::io("read", downloaded_file_path_buf))?;
             if n == 0 {
                 break;
             }
@@ -145,29 +213,48 @@ impl ManageTrait for SnmNpm {
     }
 
     fn get_host(&self) -> Option<String> {
-        todo!("get_host")
+        None
     }
 
-    async fn show_list(&self, dir_tuple: &(Vec<String>, Option<String>)) -> Result<(), SnmError> {
+    async fn get_list(
+        &self,
+        dir_tuple: &(Vec<String>, Option<String>),
+    ) -> Result<Vec<VersionItem>, SnmError> {
         let (dir_vec, default_v) = &dir_tuple;
 
-        dir_vec.into_iter().for_each(|dir| {
-            let prefix = if Some(dir) == default_v.as_ref() {
-                "⛳️"
-            } else {
-                " "
-            };
-            println!("{:<2} {:<10}", prefix, dir.bright_green());
-        });
-        Ok(())
+        let mut item_vec: Vec<VersionItem> = dir_vec
+            .iter()
+            .map(|dir| VersionItem {
+                version: dir.to_string(),
+                installed: true,
+                default: Some(dir) == default_v.as_ref(),
+                ..VersionItem::default()
+            })
+            .collect();
+
+        if self.prefix == "npm" {
+            item_vec.extend(self.get_bundled_vec().into_iter().map(|(npm_v, node_v)| {
+                VersionItem {
+                    version: npm_v,
+                    installed: true,
+                    bundled_node: Some(node_v),
+                    ..VersionItem::default()
+                }
+            }));
+        }
+
+        Ok(item_vec)
     }
 
-    async fn show_list_remote(
+    async fn get_list_remote(
         &self,
         _dir_tuple: &(Vec<String>, Option<String>),
         _all: bool,
-    ) -> Result<(), SnmError> {
-        todo!("show_list_remote")
+    ) -> Result<Vec<VersionItem>, SnmError> {
+        Err(SnmError::Error(format!(
+            "snm {} list-remote is not supported yet.",
+            &self.prefix
+        )))
     }
 
     fn get_shim_trait(&self) -> Box<dyn ShimTrait> {
@@ -184,17 +271,46 @@ impl ManageTrait for SnmNpm {
         })?;
         Ok(())
     }
+
+    fn check_staging_dir(&self, v: &str, staging_dir_path_buf: &PathBuf) -> Result<(), SnmError> {
+        let package_json = PackageJson::from_file_path(&staging_dir_path_buf.join("package.json"))?;
+
+        let bin_vec = match &package_json.bin {
+            Some(Bin::Str(bin)) => vec![bin.clone()],
+            Some(Bin::Map(map)) => map.values().cloned().collect(),
+            None => vec![],
+        };
+
+        for bin in bin_vec {
+            let bin_path_buf = staging_dir_path_buf.join(&bin);
+            if bin_path_buf.exists().not() {
+                return Err(SnmError::Error(format!(
+                    "Install of {}@{} is incomplete, {} not found.",
+                    &self.prefix,
+                    v,
+                    bin_path_buf.display()
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl ShimTrait for SnmNpm {
     fn get_strict_shim_version(&self) -> Result<String, SnmError> {
         let package_json_path_buf = current_dir()
-            .expect("get current dir failed")
+            .map_err(SnmError::io("get current dir", "."))?
             .join("package.json");
 
         let package_json = PackageJson::from_file_path(&package_json_path_buf)?;
 
-        let package_manager = package_json.parse_package_manager()?;
+        let package_manager = package_json
+            .resolve_package_manager()?
+            .ok_or(SnmError::Error(format!(
+                "Not found packageManager or lock file for {}",
+                package_json_path_buf.display()
+            )))?;
 
         let version = package_manager.version;
 
@@ -218,10 +334,7 @@ impl ShimTrait for SnmNpm {
                     &version
                 ))
                 .interact()
-                .expect(
-                    "
-                download Confirm error",
-                )),
+                .map_err(SnmError::prompt)?),
             snm_core::config::snm_config::InstallStrategy::Panic => {
                 Err(SnmError::UnsupportedPackageManager {
                     name: self.prefix.to_string(),
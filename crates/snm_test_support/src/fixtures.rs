@@ -0,0 +1,278 @@
+use std::{collections::HashMap, io::Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::{json, Value};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Declarative description of what the mock server serves. Bodies that embed the
+/// server url (packuments) are rendered once the server knows its address.
+#[derive(Default, Clone)]
+pub struct Fixtures {
+    node_version_vec: Vec<String>,
+    npm_package_vec: Vec<(String, String)>,
+    corrupted_shasum_vec: Vec<String>,
+    route_map: HashMap<String, Vec<u8>>,
+    pub(crate) authorization: Option<String>,
+}
+
+impl Fixtures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `/dist/v{version}/node-v{version}-{os}-{arch}.tar.xz`, its `SHASUMS256.txt`
+    /// and an entry in `/dist/index.json`.
+    pub fn node(mut self, version: &str) -> Self {
+        self.node_version_vec.push(version.to_string());
+        self
+    }
+
+    /// Like [`Fixtures::node`] but `SHASUMS256.txt` lists a wrong hash for the tarball.
+    pub fn node_with_bad_shasum(mut self, version: &str) -> Self {
+        self.node_version_vec.push(version.to_string());
+        self.corrupted_shasum_vec.push(version.to_string());
+        self
+    }
+
+    /// Serve `/{name}/-/{name}-{version}.tgz`, `/{name}/{version}` and `/{name}`.
+    pub fn npm_package(mut self, name: &str, version: &str) -> Self {
+        self.npm_package_vec
+            .push((name.to_string(), version.to_string()));
+        self
+    }
+
+    pub fn route(mut self, path: &str, body: impl Into<Vec<u8>>) -> Self {
+        self.route_map.insert(path.to_string(), body.into());
+        self
+    }
+
+    /// Answer 401 to every request whose `Authorization` header is not `authorization`.
+    pub fn require_authorization(mut self, authorization: &str) -> Self {
+        self.authorization = Some(authorization.to_string());
+        self
+    }
+
+    pub(crate) fn render(&self, base_url: &str) -> HashMap<String, Vec<u8>> {
+        let mut route_map = HashMap::new();
+
+        let mut index_vec = vec![];
+        let mut schedule_map = serde_json::Map::new();
+
+        for version in &self.node_version_vec {
+            let file_name = node_file_name(version);
+            let tarball = node_tarball(version);
+
+            let shasum = if self.corrupted_shasum_vec.contains(version) {
+                "0".repeat(64)
+            } else {
+                hex(&Sha256::digest(&tarball))
+            };
+
+            route_map.insert(
+                format!("/dist/v{}/SHASUMS256.txt", version),
+                format!("{}  {}\n", shasum, file_name).into_bytes(),
+            );
+            route_map.insert(format!("/dist/v{}/{}", version, file_name), tarball);
+
+            index_vec.push(json!({
+                "version": format!("v{}", version),
+                "date": "2024-01-01",
+                "files": [format!("{}-{}", os(), arch())],
+                "npm": "10.2.4",
+                "v8": "11.3.244.8",
+                "uv": "1.46.0",
+                "zlib": "1.2.13",
+                "openssl": "3.0.12+quic",
+                "modules": "115",
+                "lts": "Fixture",
+                "security": false
+            }));
+
+            let major = version.split('.').next().unwrap_or(version);
+            schedule_map.insert(
+                format!("v{}", major),
+                json!({
+                    "start": "2023-01-01",
+                    "lts": "2023-06-01",
+                    "maintenance": "2098-01-01",
+                    "end": "2099-01-01",
+                    "codename": "Fixture"
+                }),
+            );
+        }
+
+        route_map.insert(
+            "/dist/index.json".to_string(),
+            Value::Array(index_vec).to_string().into_bytes(),
+        );
+        route_map.insert(
+            "/nodejs/Release/main/schedule.json".to_string(),
+            Value::Object(schedule_map).to_string().into_bytes(),
+        );
+
+        let mut packument_map: HashMap<String, serde_json::Map<String, Value>> = HashMap::new();
+
+        for (name, version) in &self.npm_package_vec {
+            let tarball = npm_tarball(name, version);
+            let tarball_path = format!("/{}/-/{}-{}.tgz", name, name, version);
+
+            let manifest = json!({
+                "name": name,
+                "version": version,
+                "bin": { name.as_str(): format!("bin/{}-cli.js", name) },
+                "dist": {
+                    "shasum": hex(&Sha1::digest(&tarball)),
+                    "integrity": format!("sha512-{}", STANDARD.encode(Sha512::digest(&tarball))),
+                    "tarball": format!("{}{}", base_url, tarball_path)
+                }
+            });
+
+            route_map.insert(
+                format!("/{}/{}", name, version),
+                manifest.to_string().into_bytes(),
+            );
+            route_map.insert(tarball_path, tarball);
+
+            packument_map
+                .entry(name.to_string())
+                .or_default()
+                .insert(version.to_string(), manifest);
+        }
+
+        for (name, version_map) in packument_map {
+            let latest = version_map.keys().max().cloned().unwrap_or_default();
+            let packument = json!({
+                "name": name,
+                "dist-tags": { "latest": latest },
+                "versions": version_map
+            });
+            route_map.insert(format!("/{}", name), packument.to_string().into_bytes());
+        }
+
+        route_map.extend(self.route_map.clone());
+
+        route_map
+    }
+}
+
+/// The file name snm computes for the current platform, e.g. `node-v20.11.1-linux-x64.tar.xz`.
+pub fn node_file_name(version: &str) -> String {
+    format!("node-v{}-{}-{}.tar.xz", version, os(), arch())
+}
+
+fn os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+fn arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "arm" => "armv7l",
+        arch => arch,
+    }
+}
+
+/// Hex encoded sha256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Hex encoded sha512 of `bytes`, the hash corepack puts after `+sha512.`.
+pub fn sha512_hex(bytes: &[u8]) -> String {
+    hex(&Sha512::digest(bytes))
+}
+
+/// A `.tar.gz` of `(path, content)` entries, every entry is executable.
+pub fn tar_gz(entry_vec: &[(String, String)]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    write_tar(&mut encoder, entry_vec);
+    encoder.finish().expect("finish gzip error")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// A node "release" whose binaries are shell scripts, `node --version` prints the version.
+fn node_tarball(version: &str) -> Vec<u8> {
+    let root = node_file_name(version)
+        .trim_end_matches(".tar.xz")
+        .to_string();
+
+    let entry_vec = vec![
+        (
+            format!("{}/bin/node", root),
+            format!("#!/bin/sh\necho v{}\n", version),
+        ),
+        (
+            format!("{}/bin/npm", root),
+            "#!/bin/sh\necho 10.2.4\n".to_string(),
+        ),
+        (
+            format!("{}/bin/npx", root),
+            "#!/bin/sh\necho 10.2.4\n".to_string(),
+        ),
+        (
+            format!("{}/lib/node_modules/npm/package.json", root),
+            json!({
+                "name": "npm",
+                "version": "10.2.4",
+                "bin": { "npm": "bin/npm-cli.js", "npx": "bin/npx-cli.js" }
+            })
+            .to_string(),
+        ),
+        (
+            format!("{}/lib/node_modules/npm/bin/npm-cli.js", root),
+            "#!/usr/bin/env node\n".to_string(),
+        ),
+        (
+            format!("{}/lib/node_modules/npm/bin/npx-cli.js", root),
+            "#!/usr/bin/env node\n".to_string(),
+        ),
+    ];
+
+    let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 1);
+    write_tar(&mut encoder, &entry_vec);
+    encoder.finish().expect("finish xz error")
+}
+
+/// The tarball served for [`Fixtures::npm_package`].
+pub fn npm_tarball(name: &str, version: &str) -> Vec<u8> {
+    let entry_vec = vec![
+        (
+            "package/package.json".to_string(),
+            json!({
+                "name": name,
+                "version": version,
+                "bin": { name: format!("bin/{}-cli.js", name) }
+            })
+            .to_string(),
+        ),
+        (
+            format!("package/bin/{}-cli.js", name),
+            format!("#!/bin/sh\necho {}\n", version),
+        ),
+    ];
+
+    tar_gz(&entry_vec)
+}
+
+fn write_tar<W: Write>(writer: &mut W, entry_vec: &[(String, String)]) {
+    let mut builder = tar::Builder::new(writer);
+    for (path, content) in entry_vec {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .expect("append tar entry error");
+    }
+    builder.finish().expect("finish tar error");
+}
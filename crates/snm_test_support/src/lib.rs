@@ -0,0 +1,7 @@
+pub mod fixtures;
+pub mod mock_server;
+pub mod test_env;
+
+pub use self::fixtures::Fixtures;
+pub use self::mock_server::MockServer;
+pub use self::test_env::{block_on, TestEnv};
@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+use tiny_http::{Response, Server};
+
+use crate::fixtures::Fixtures;
+
+/// Local stand-in for nodejs.org, raw.githubusercontent.com and the npm registry.
+///
+/// | snm config                           | mock path                              |
+/// |--------------------------------------|----------------------------------------|
+/// | `SNM_NODEJS_DIST_URL_KEY`            | `{url}/dist`                           |
+/// | `SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY`| `{url}` (`/nodejs/Release/main/...`)   |
+/// | `SNM_NPM_REGISTRY_HOST`              | `{url}`                                |
+pub struct MockServer {
+    url: String,
+    server: Arc<Server>,
+    request_vec: Arc<Mutex<Vec<String>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn start(fixtures: Fixtures) -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").expect("start mock server error"));
+
+        let url = format!(
+            "http://{}",
+            server
+                .server_addr()
+                .to_ip()
+                .expect("mock server is not bound to an ip address")
+        );
+
+        let route_map: HashMap<String, Vec<u8>> = fixtures.render(&url);
+        let authorization = fixtures.authorization.clone();
+        let request_vec = Arc::new(Mutex::new(vec![]));
+
+        let handle = {
+            let server = server.clone();
+            let request_vec = request_vec.clone();
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let path = request.url().split('?').next().unwrap_or("").to_string();
+
+                    request_vec
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .push(path.clone());
+
+                    let authorized = authorization.as_ref().is_none_or(|expect| {
+                        request.headers().iter().any(|header| {
+                            header.field.equiv("Authorization") && header.value.as_str() == expect
+                        })
+                    });
+
+                    let response = match route_map.get(&path) {
+                        _ if !authorized => {
+                            Response::from_string("unauthorized").with_status_code(401)
+                        }
+                        Some(body) => Response::from_data(body.clone()),
+                        None => Response::from_string("not found").with_status_code(404),
+                    };
+
+                    let _ = request.respond(response);
+                }
+            })
+        };
+
+        Self {
+            url,
+            server,
+            request_vec,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.request_vec
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    env,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use tempfile::TempDir;
+
+use crate::{fixtures::Fixtures, mock_server::MockServer};
+
+// snm reads its whole configuration from env vars, tests touching them must not overlap.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+// 不只是 snm 自己的变量，用户的 npm 配置也会影响测试
+const EXTRA_ENV_KEYS: [&str; 1] = ["NPM_CONFIG_USERCONFIG"];
+
+/// Remove every `SNM_*` var, whatever the developer or an earlier test set.
+fn clear_snm_env() {
+    let key_vec: Vec<String> = env::vars_os()
+        .filter_map(|(key, _)| key.into_string().ok())
+        .filter(|key| key.starts_with("SNM_"))
+        .collect();
+    for key in key_vec {
+        env::remove_var(key);
+    }
+    for key in EXTRA_ENV_KEYS {
+        env::remove_var(key);
+    }
+}
+
+/// A temporary `SNM_BASE_DIR` and project dir wired to a [`MockServer`].
+///
+/// Holds a process wide lock for its whole lifetime, restores the working dir and
+/// clears the snm env vars on drop.
+pub struct TestEnv {
+    pub server: MockServer,
+    base_dir: TempDir,
+    project_dir: TempDir,
+    original_dir: PathBuf,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    pub fn new(fixtures: Fixtures) -> Self {
+        let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let server = MockServer::start(fixtures);
+        let base_dir = TempDir::new().expect("create temp base dir error");
+        let project_dir = TempDir::new().expect("create temp project dir error");
+        let original_dir = env::current_dir().expect("get current dir error");
+
+        clear_snm_env();
+
+        env::set_var("SNM_BASE_DIR", base_dir.path());
+        env::set_var("SNM_STRICT", "false");
+        env::set_var("SNM_NODEJS_DIST_URL_KEY", format!("{}/dist", server.url()));
+        env::set_var("SNM_NODEJS_GITHUB_RESOURCE_HOST_KEY", server.url());
+        env::set_var("SNM_NPM_REGISTRY_HOST", server.url());
+        env::set_var("SNM_NODE_INSTALL_STRATEGY", "auto");
+        env::set_var("SNM_PACKAGE_MANAGER_INSTALL_STRATEGY", "auto");
+        // keep the developer's own ~/.npmrc out of the tests
+        env::set_var("NPM_CONFIG_USERCONFIG", base_dir.path().join(".npmrc"));
+
+        Self {
+            server,
+            base_dir,
+            project_dir,
+            original_dir,
+            _guard: guard,
+        }
+    }
+
+    pub fn base_dir(&self) -> &Path {
+        self.base_dir.path()
+    }
+
+    pub fn project_dir(&self) -> &Path {
+        self.project_dir.path()
+    }
+
+    pub fn set_var(&self, key: &str, value: &str) {
+        env::set_var(key, value);
+    }
+
+    pub fn remove_var(&self, key: &str) {
+        env::remove_var(key);
+    }
+
+    /// Write `content` to `relative` inside the project dir.
+    pub fn write_project_file(&self, relative: &str, content: &str) -> PathBuf {
+        let path_buf = self.project_dir.path().join(relative);
+        if let Some(parent) = path_buf.parent() {
+            std::fs::create_dir_all(parent).expect("create project sub dir error");
+        }
+        std::fs::write(&path_buf, content).expect("write project file error");
+        path_buf
+    }
+
+    /// Make the project dir the current dir, like running snm inside the project.
+    pub fn enter_project(&self) {
+        env::set_current_dir(self.project_dir.path()).expect("enter project dir error");
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = env::set_current_dir(&self.original_dir);
+        clear_snm_env();
+    }
+}
+
+/// Drive an async snm api from a plain `#[test]`.
+///
+/// `DispatchManage::proxy_process` starts its own runtime when it has to download,
+/// so tests stay synchronous and only wrap the async calls.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("build tokio runtime error")
+        .block_on(future)
+}